@@ -0,0 +1,255 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Read as _, Seek as _, SeekFrom, Write as _},
+    path::Path,
+    sync::Mutex,
+};
+
+const MAGIC: [u8; 8] = *b"BLADEPAK";
+const FORMAT_VERSION: u32 = 1;
+const FLAG_COMPRESSED: u32 = 1;
+/// Size of an index entry with an empty name.
+const MIN_ENTRY_SIZE: u64 = 4 + 4 + 3 * 8;
+const MAX_NAME_LENGTH: usize = 0x10000;
+
+/// Compression of the archive entries.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Compression {
+    /// Store the data as is.
+    #[default]
+    None,
+    /// Compress with LZ4, requires the "compression" feature.
+    Lz4,
+}
+
+struct Entry {
+    flags: u32,
+    offset: u64,
+    size: u64,
+    raw_size: u64,
+}
+
+/// Builder of an archive.
+///
+/// The layout is: magic, version, entry count, index of the entries,
+/// followed by the data of all the entries.
+#[derive(Default)]
+pub struct Packer {
+    entries: Vec<(String, u32, Vec<u8>, u64)>,
+}
+
+impl Packer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an entry with the specified name.
+    ///
+    /// The data is stored uncompressed if compression doesn't make it smaller.
+    pub fn add(&mut self, name: &str, data: Vec<u8>, compression: Compression) -> io::Result<()> {
+        let raw_size = data.len() as u64;
+        let (flags, stored) = match compression {
+            Compression::None => (0, data),
+            #[cfg(feature = "compression")]
+            Compression::Lz4 => {
+                let compressed = lz4_flex::compress_prepend_size(&data);
+                if compressed.len() < data.len() {
+                    (FLAG_COMPRESSED, compressed)
+                } else {
+                    (0, data)
+                }
+            }
+            #[cfg(not(feature = "compression"))]
+            Compression::Lz4 => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "Compression feature is not enabled",
+                ))
+            }
+        };
+        self.entries
+            .push((name.to_string(), flags, stored, raw_size));
+        Ok(())
+    }
+
+    /// Add all the cooked assets from the `target` directory of an `AssetManager`.
+    ///
+    /// Only the cooked data is stored, without the dependency information.
+    /// Returns the number of added entries.
+    pub fn add_cooked_directory(
+        &mut self,
+        target: &Path,
+        compression: Compression,
+    ) -> io::Result<usize> {
+        let mut count = 0;
+        for dir_entry in fs::read_dir(target)? {
+            let path = dir_entry?.path();
+            if path.extension().is_none_or(|ext| ext != "raw") {
+                continue;
+            }
+            let name = match path.file_name().and_then(|name| name.to_str()) {
+                Some(name) => name.to_string(),
+                None => continue,
+            };
+            let data = super::read_target(&path)?;
+            self.add(&name, data, compression)?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Write the archive out into a file.
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        let mut file = io::BufWriter::new(fs::File::create(path)?);
+        file.write_all(&MAGIC)?;
+        file.write_all(&FORMAT_VERSION.to_le_bytes())?;
+        file.write_all(&(self.entries.len() as u32).to_le_bytes())?;
+
+        let index_size = self
+            .entries
+            .iter()
+            .map(|entry| 4 + entry.0.len() + 4 + 3 * 8)
+            .sum::<usize>();
+        let mut offset = (MAGIC.len() + 8 + index_size) as u64;
+        for &(ref name, flags, ref data, raw_size) in self.entries.iter() {
+            file.write_all(&(name.len() as u32).to_le_bytes())?;
+            file.write_all(name.as_bytes())?;
+            file.write_all(&flags.to_le_bytes())?;
+            file.write_all(&offset.to_le_bytes())?;
+            file.write_all(&(data.len() as u64).to_le_bytes())?;
+            file.write_all(&raw_size.to_le_bytes())?;
+            offset += data.len() as u64;
+        }
+        for entry in self.entries.iter() {
+            file.write_all(&entry.2)?;
+        }
+        file.flush()
+    }
+}
+
+/// Read-only archive of cooked assets.
+pub struct Archive {
+    file: Mutex<fs::File>,
+    entries: HashMap<String, Entry>,
+}
+
+fn read_u32(file: &mut impl io::Read) -> io::Result<u32> {
+    let mut bytes = [0u8; 4];
+    file.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64(file: &mut impl io::Read) -> io::Result<u64> {
+    let mut bytes = [0u8; 8];
+    file.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+#[cfg(feature = "compression")]
+fn decompress(data: &[u8], raw_size: u64) -> io::Result<Vec<u8>> {
+    // LZ4 doesn't expand the data more than 255 times, so the prepended size
+    // is checked against that before it's used for the allocation.
+    let prepended_size = data
+        .get(..4)
+        .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()) as u64);
+    if prepended_size != Some(raw_size) || raw_size > data.len() as u64 * 255 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Compressed size mismatch",
+        ));
+    }
+    let raw = lz4_flex::decompress_size_prepended(data)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    if raw.len() as u64 != raw_size {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Decompressed size mismatch",
+        ));
+    }
+    Ok(raw)
+}
+
+#[cfg(not(feature = "compression"))]
+fn decompress(_data: &[u8], _raw_size: u64) -> io::Result<Vec<u8>> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Compression feature is not enabled",
+    ))
+}
+
+impl Archive {
+    /// Open an archive and read its index.
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message);
+        let mut file = fs::File::open(path)?;
+        let mut reader = io::BufReader::new(&mut file);
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if magic != MAGIC {
+            return Err(invalid("Not a Blade archive"));
+        }
+        if read_u32(&mut reader)? != FORMAT_VERSION {
+            return Err(invalid("Unsupported archive version"));
+        }
+        // Sizes are checked against the file, so that a corrupt archive
+        // doesn't make us allocate a lot of memory.
+        let file_size = reader.get_ref().metadata()?.len();
+        let count = read_u32(&mut reader)?;
+        if count as u64 * MIN_ENTRY_SIZE > file_size {
+            return Err(invalid("Too many entries"));
+        }
+        let mut entries = HashMap::with_capacity(count as usize);
+        for _ in 0..count {
+            let name_len = read_u32(&mut reader)? as usize;
+            if name_len > MAX_NAME_LENGTH || name_len as u64 > file_size {
+                return Err(invalid("Entry name is too long"));
+            }
+            let mut name = vec![0u8; name_len];
+            reader.read_exact(&mut name)?;
+            let name = String::from_utf8(name).map_err(|_| invalid("Malformed entry name"))?;
+            let entry = Entry {
+                flags: read_u32(&mut reader)?,
+                offset: read_u64(&mut reader)?,
+                size: read_u64(&mut reader)?,
+                raw_size: read_u64(&mut reader)?,
+            };
+            if entry
+                .offset
+                .checked_add(entry.size)
+                .is_none_or(|end| end > file_size)
+            {
+                return Err(invalid("Entry is out of the archive bounds"));
+            }
+            entries.insert(name, entry);
+        }
+        Ok(Self {
+            file: Mutex::new(file),
+            entries,
+        })
+    }
+
+    /// Iterate over the names of all the entries.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.entries.keys().map(|name| name.as_str())
+    }
+
+    /// Read the data of an entry, if it exists.
+    pub fn read(&self, name: &str) -> io::Result<Option<Vec<u8>>> {
+        let entry = match self.entries.get(name) {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+        let mut data = vec![0u8; entry.size as usize];
+        {
+            let mut file = self.file.lock().unwrap();
+            file.seek(SeekFrom::Start(entry.offset))?;
+            file.read_exact(&mut data)?;
+        }
+        if entry.flags & FLAG_COMPRESSED == 0 {
+            Ok(Some(data))
+        } else {
+            decompress(&data, entry.raw_size).map(Some)
+        }
+    }
+}
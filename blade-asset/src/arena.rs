@@ -3,7 +3,7 @@ use std::{
     marker::PhantomData,
     mem,
     num::NonZeroU8,
-    ops, ptr,
+    ops,
     sync::{
         atomic::{AtomicPtr, Ordering},
         Mutex,
@@ -20,7 +20,7 @@ struct Address {
 pub struct Handle<T>(Address, PhantomData<T>);
 impl<T> Clone for Handle<T> {
     fn clone(&self) -> Self {
-        Handle(self.0, PhantomData)
+        *self
     }
 }
 impl<T> Copy for Handle<T> {}
@@ -29,12 +29,19 @@ impl<T> PartialEq for Handle<T> {
         self.0 == other.0
     }
 }
+impl<T> Eq for Handle<T> {}
 impl<T> hash::Hash for Handle<T> {
     fn hash<H: hash::Hasher>(&self, hasher: &mut H) {
         self.0.hash(hasher);
         self.1.hash(hasher);
     }
 }
+impl<T> Handle<T> {
+    /// Erase the type of the handle.
+    pub fn erase(self) -> Handle<()> {
+        Handle(self.0, PhantomData)
+    }
+}
 impl<T> fmt::Debug for Handle<T> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         self.0.fmt(f)
@@ -85,7 +92,7 @@ impl<T: Default> Arena<T> {
         self.min_size << chunk.get()
     }
 
-    pub fn alloc(&self, value: T) -> Handle<T> {
+    fn alloc_address(&self) -> (Address, *mut T) {
         let mut freeman = self.freeman.lock().unwrap();
         let (address, chunk_start) = match freeman.free_list.pop() {
             Some(address) => {
@@ -108,15 +115,15 @@ impl<T: Default> Arena<T> {
                 (address, chunk_start)
             }
         };
-        unsafe {
-            ptr::write(chunk_start.add(address.index as usize), value);
-        }
-        Handle(address, PhantomData)
+        (address, unsafe { chunk_start.add(address.index as usize) })
     }
 
+    /// Allocate a slot without overwriting it.
+    ///
+    /// The value is either the default, or the tombstone left by `dealloc`.
     pub fn alloc_default(&self) -> (Handle<T>, *mut T) {
-        let handle = self.alloc(T::default());
-        (handle, self.get_mut_ptr(handle))
+        let (address, ptr) = self.alloc_address();
+        (Handle(address, PhantomData), ptr)
     }
 
     pub fn get_mut_ptr(&self, handle: Handle<T>) -> *mut T {
@@ -124,11 +131,12 @@ impl<T: Default> Arena<T> {
         unsafe { first_ptr.add(handle.0.index as usize) }
     }
 
-    pub fn _dealloc(&self, handle: Handle<T>) -> T {
+    /// Free a slot, leaving the `tombstone` value in its place.
+    pub fn dealloc(&self, handle: Handle<T>, tombstone: T) -> T {
         let mut freeman = self.freeman.lock().unwrap();
         freeman.free_list.push(handle.0);
         let ptr = self.get_mut_ptr(handle);
-        mem::take(unsafe { &mut *ptr })
+        mem::replace(unsafe { &mut *ptr }, tombstone)
     }
 
     fn for_internal(&self, mut fun: impl FnMut(Address, *mut T)) {
@@ -186,6 +194,8 @@ unsafe fn _test_option_handle<A>(handle: Handle<A>) -> Option<Handle<A>> {
 #[test]
 fn test_single_thread() {
     let arena = Arena::<usize>::new(1);
-    let _ = arena.alloc(3);
-    let _ = arena.alloc(4);
+    let (_, first) = arena.alloc_default();
+    unsafe { *first = 3 };
+    let (_, second) = arena.alloc_default();
+    unsafe { *second = 4 };
 }
@@ -0,0 +1,74 @@
+//! Report the contents of the cooked asset cache, and prune the stale entries.
+//!
+//! Usage: blade-cache <cooked directory> [--prune] [--max-age-days <days>] [--source-root <directory>]
+
+use std::{cmp::Reverse, env, path::PathBuf, process, time::Duration};
+
+const USAGE: &str = "Usage: blade-cache <cooked directory> [--prune] [--max-age-days <days>] [--source-root <directory>]";
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(1);
+}
+
+fn main() {
+    let mut target = None;
+    let mut prune = false;
+    let mut max_age = None;
+    let mut source_root = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--prune" => prune = true,
+            "--max-age-days" => {
+                let days = args
+                    .next()
+                    .and_then(|value| value.parse::<u64>().ok())
+                    .unwrap_or_else(|| usage());
+                max_age = Some(Duration::from_secs(days * 24 * 60 * 60));
+            }
+            "--source-root" => {
+                source_root = Some(PathBuf::from(args.next().unwrap_or_else(|| usage())));
+            }
+            _ if target.is_none() => target = Some(PathBuf::from(arg)),
+            _ => usage(),
+        }
+    }
+    let target = target.unwrap_or_else(|| usage());
+
+    if prune {
+        let removed = blade_asset::prune_cache(&target, source_root.as_deref(), max_age)
+            .unwrap_or_else(|e| {
+                eprintln!("Unable to prune {}: {}", target.display(), e);
+                process::exit(1);
+            });
+        let size = removed.iter().map(|entry| entry.size).sum::<u64>();
+        println!(
+            "Removed {} entries, freeing {} KB",
+            removed.len(),
+            size >> 10
+        );
+        return;
+    }
+
+    let mut entries = blade_asset::list_cache(&target).unwrap_or_else(|e| {
+        eprintln!("Unable to list {}: {}", target.display(), e);
+        process::exit(1);
+    });
+    entries.sort_by_key(|entry| Reverse(entry.size));
+    for entry in entries.iter() {
+        let age = entry.last_use.elapsed().unwrap_or_default();
+        println!(
+            "{:>10} KB {:>6}h ago\t{}\t{}",
+            entry.size >> 10,
+            age.as_secs() / 3600,
+            entry
+                .source
+                .as_ref()
+                .map_or("<unknown>".into(), |source| source.display().to_string()),
+            entry.meta.as_deref().unwrap_or(""),
+        );
+    }
+    let size = entries.iter().map(|entry| entry.size).sum::<u64>();
+    println!("Total: {} entries, {} KB", entries.len(), size >> 10);
+}
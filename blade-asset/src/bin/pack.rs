@@ -0,0 +1,88 @@
+//! Pack the cooked assets into a single archive.
+//!
+//! Usage: blade-pack <cooked directory> <output archive> [--compress] [--source-root <directory>]
+//!
+//! With `--source-root`, the entries are checked to be cooked relative to
+//! the given root, so that the archive can be loaded from any location.
+
+use std::{env, path::PathBuf, process};
+
+const USAGE: &str =
+    "Usage: blade-pack <cooked directory> <output archive> [--compress] [--source-root <directory>]";
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(1);
+}
+
+fn main() {
+    let mut compression = blade_asset::Compression::None;
+    let mut source_root = None;
+    let mut paths = Vec::new();
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--compress" => compression = blade_asset::Compression::Lz4,
+            "--source-root" => {
+                source_root = Some(PathBuf::from(args.next().unwrap_or_else(|| usage())));
+            }
+            _ => paths.push(PathBuf::from(arg)),
+        }
+    }
+    if paths.len() != 2 {
+        usage();
+    }
+
+    if let Some(ref root) = source_root {
+        let entries = blade_asset::list_cache(&paths[0]).unwrap_or_else(|e| {
+            eprintln!("Unable to list {}: {}", paths[0].display(), e);
+            process::exit(1);
+        });
+        let mut num_errors = 0;
+        for entry in entries.iter() {
+            match entry.source {
+                Some(ref source) if source.is_relative() => {
+                    if !root.join(source).is_file() {
+                        eprintln!(
+                            "Warning: source {} of {} is missing",
+                            source.display(),
+                            entry.path.display()
+                        );
+                    }
+                }
+                Some(ref source) => {
+                    eprintln!(
+                        "Error: {} is cooked from {}, which is not relative to a source root",
+                        entry.path.display(),
+                        source.display()
+                    );
+                    num_errors += 1;
+                }
+                None => {
+                    eprintln!("Error: {} has an unknown source", entry.path.display());
+                    num_errors += 1;
+                }
+            }
+        }
+        if num_errors != 0 {
+            eprintln!(
+                "Cook the assets with `CacheConfig::source_root` set to {}",
+                root.display()
+            );
+            process::exit(1);
+        }
+    }
+
+    let mut packer = blade_asset::Packer::new();
+    let count = packer
+        .add_cooked_directory(&paths[0], compression)
+        .unwrap_or_else(|e| {
+            eprintln!("Unable to read {}: {}", paths[0].display(), e);
+            process::exit(1);
+        });
+    if let Err(e) = packer.write(&paths[1]) {
+        eprintln!("Unable to write {}: {}", paths[1].display(), e);
+        process::exit(1);
+    }
+    println!("Packed {} assets into {}", count, paths[1].display());
+}
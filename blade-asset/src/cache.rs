@@ -0,0 +1,167 @@
+use std::{
+    fs,
+    io::{self, Read, Write},
+    mem,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+/// Description of a cooked asset, stored in the header of its target.
+pub(super) struct Label {
+    /// Main source path, relative to the source root if there is one.
+    pub source: PathBuf,
+    pub meta: String,
+}
+
+fn read_string(file: &mut impl Read) -> io::Result<String> {
+    let mut len_bytes = [0u8; mem::size_of::<usize>()];
+    file.read_exact(&mut len_bytes)?;
+    let len = usize::from_le_bytes(len_bytes);
+    if len > 0x10000 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "String is too long",
+        ));
+    }
+    let mut bytes = vec![0u8; len];
+    file.read_exact(&mut bytes)?;
+    String::from_utf8(bytes).map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Not UTF-8"))
+}
+
+fn write_string(file: &mut impl Write, string: &str) -> io::Result<()> {
+    file.write_all(&string.len().to_le_bytes())?;
+    file.write_all(string.as_bytes())
+}
+
+impl Label {
+    pub fn read(file: &mut impl Read) -> io::Result<Self> {
+        Ok(Self {
+            source: PathBuf::from(read_string(file)?),
+            meta: read_string(file)?,
+        })
+    }
+
+    pub fn write(&self, file: &mut impl Write) -> io::Result<()> {
+        let source = self.source.to_str().unwrap_or_default();
+        write_string(file, source)?;
+        write_string(file, &self.meta)
+    }
+}
+
+/// Read the label of a target, skipping over the rest of the header.
+fn read_label(path: &Path) -> io::Result<Label> {
+    let mut file = io::BufReader::new(fs::File::open(path)?);
+    let mut bytes = [0u8; 8];
+    file.read_exact(&mut bytes)?; // hash
+    file.read_exact(&mut bytes)?; // data offset
+    let mut temp_bytes = [0u8; mem::size_of::<usize>()];
+    file.read_exact(&mut temp_bytes)?;
+    let num_deps = usize::from_le_bytes(temp_bytes);
+    for _ in 0..num_deps {
+        read_string(&mut file)?;
+    }
+    Label::read(&mut file)
+}
+
+/// Make sure the target is considered recently used.
+pub(super) fn touch(target_path: &Path) {
+    let result = fs::File::options()
+        .write(true)
+        .open(target_path)
+        .and_then(|file| file.set_modified(SystemTime::now()));
+    if let Err(e) = result {
+        log::debug!("Unable to touch {}: {}", target_path.display(), e);
+    }
+}
+
+/// Entry of the cooked asset cache.
+#[derive(Clone, Debug)]
+pub struct CacheEntry {
+    /// Path to the cooked file.
+    pub path: PathBuf,
+    /// Main source path, relative to the source root if there is one.
+    ///
+    /// It's `None` if the header can't be read, for example if the entry
+    /// was produced by an older version of the library.
+    pub source: Option<PathBuf>,
+    /// Description of the asset metadata.
+    pub meta: Option<String>,
+    /// Size of the cooked file, in bytes.
+    pub size: u64,
+    /// Time the asset was last cooked or loaded from the cache.
+    pub last_use: SystemTime,
+}
+
+/// List all the entries of the cooked asset cache in the `target` directory.
+pub fn list_cache(target: &Path) -> io::Result<Vec<CacheEntry>> {
+    let mut entries = Vec::new();
+    for dir_entry in fs::read_dir(target)? {
+        let path = dir_entry?.path();
+        if path.extension().is_none_or(|ext| ext != "raw") {
+            continue;
+        }
+        // The entry may be removed while listing.
+        let metadata = match fs::metadata(&path) {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                log::warn!("Unable to stat {}: {}", path.display(), e);
+                continue;
+            }
+        };
+        let (source, meta) = match read_label(&path) {
+            Ok(label) => (Some(label.source), Some(label.meta)),
+            Err(_) => (None, None),
+        };
+        entries.push(CacheEntry {
+            path,
+            source,
+            meta,
+            size: metadata.len(),
+            last_use: metadata.modified()?,
+        });
+    }
+    Ok(entries)
+}
+
+/// Remove the entries of the cooked asset cache in the `target` directory,
+/// which sources no longer exist, or which were not used within `max_age`.
+///
+/// The `source_root` needs to match `CacheConfig::source_root`.
+/// Without it, only the entries with absolute source paths are checked
+/// for the sources to exist, since the relative ones can't be resolved.
+/// Entries with unreadable headers are never removed, since they
+/// may be in the process of being written.
+/// Failures to remove individual entries are logged and skipped.
+/// Returns the removed entries.
+pub fn prune_cache(
+    target: &Path,
+    source_root: Option<&Path>,
+    max_age: Option<Duration>,
+) -> io::Result<Vec<CacheEntry>> {
+    let now = SystemTime::now();
+    let mut removed = Vec::new();
+    for entry in list_cache(target)? {
+        let source = match entry.source {
+            Some(ref source) => source,
+            None => continue,
+        };
+        let is_old = max_age.is_some_and(|max_age| {
+            now.duration_since(entry.last_use)
+                .is_ok_and(|age| age > max_age)
+        });
+        let is_missing = match source_root {
+            Some(root) => !root.join(source).is_file(),
+            None if source.is_absolute() => !source.is_file(),
+            None => false,
+        };
+        if !is_old && !is_missing {
+            continue;
+        }
+        log::info!("Removing stale {}", entry.path.display());
+        match fs::remove_file(&entry.path) {
+            Ok(()) => removed.push(entry),
+            Err(e) => log::warn!("Unable to remove {}: {}", entry.path.display(), e),
+        }
+    }
+    Ok(removed)
+}
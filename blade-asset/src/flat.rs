@@ -40,6 +40,7 @@ macro_rules! impl_basic {
 }
 
 impl_basic!(bool);
+impl_basic!(u8);
 impl_basic!(u32);
 impl_basic!(u64);
 impl_basic!(usize);
@@ -70,7 +71,8 @@ impl<T: Flat> Flat for Vec<T> {
         })
     }
     unsafe fn write(&self, ptr: *mut u8) {
-        ptr::write(ptr as *mut usize, self.len());
+        // The counter is not necessarily aligned, e.g. for a nested `Vec<Vec<u8>>`.
+        ptr::write_unaligned(ptr as *mut usize, self.len());
         let mut offset = mem::size_of::<usize>();
         for item in self.iter() {
             offset = round_up(offset, T::ALIGNMENT);
@@ -79,7 +81,7 @@ impl<T: Flat> Flat for Vec<T> {
         }
     }
     unsafe fn read(ptr: *const u8) -> Self {
-        let counter = ptr::read(ptr as *const usize);
+        let counter = ptr::read_unaligned(ptr as *const usize);
         let mut offset = mem::size_of::<usize>();
         (0..counter)
             .map(|_| {
@@ -103,7 +105,7 @@ impl<T: bytemuck::Pod, const C: usize> Flat for [T; C] {
     }
 }
 
-impl<'a, T: bytemuck::Pod> Flat for &'a [T] {
+impl<T: bytemuck::Pod> Flat for &[T] {
     const ALIGNMENT: usize = mem::align_of::<T>();
     const FIXED_SIZE: Option<NonZeroUsize> = None;
     fn size(&self) -> usize {
@@ -111,14 +113,14 @@ impl<'a, T: bytemuck::Pod> Flat for &'a [T] {
         round_up(mem::size_of::<usize>(), mem::align_of::<T>()) + elem_size * self.len()
     }
     unsafe fn write(&self, ptr: *mut u8) {
-        ptr::write(ptr as *mut usize, self.len());
+        ptr::write_unaligned(ptr as *mut usize, self.len());
         if !self.is_empty() {
             let offset = round_up(mem::size_of::<usize>(), mem::align_of::<T>());
             ptr::copy_nonoverlapping(self.as_ptr(), ptr.add(offset) as *mut T, self.len());
         }
     }
     unsafe fn read(ptr: *const u8) -> Self {
-        let counter = ptr::read(ptr as *const usize);
+        let counter = ptr::read_unaligned(ptr as *const usize);
         if counter != 0 {
             let offset = round_up(mem::size_of::<usize>(), mem::align_of::<T>());
             slice::from_raw_parts(ptr.add(offset) as *const T, counter)
@@ -141,3 +143,49 @@ impl<'a, T: bytemuck::Pod> Flat for Cow<'a, [T]> {
         Cow::Borrowed(<&'a [T] as Flat>::read(ptr))
     }
 }
+
+impl<T: Flat> Flat for Option<T> {
+    const ALIGNMENT: usize = T::ALIGNMENT;
+    const FIXED_SIZE: Option<NonZeroUsize> = None;
+    fn size(&self) -> usize {
+        match *self {
+            Some(ref value) => round_up(1, T::ALIGNMENT) + value.size(),
+            None => 1,
+        }
+    }
+    unsafe fn write(&self, ptr: *mut u8) {
+        match *self {
+            Some(ref value) => {
+                ptr::write(ptr, 1);
+                value.write(ptr.add(round_up(1, T::ALIGNMENT)));
+            }
+            None => ptr::write(ptr, 0),
+        }
+    }
+    unsafe fn read(ptr: *const u8) -> Self {
+        if ptr::read(ptr) != 0 {
+            Some(T::read(ptr.add(round_up(1, T::ALIGNMENT))))
+        } else {
+            None
+        }
+    }
+}
+
+impl Flat for String {
+    const ALIGNMENT: usize = mem::align_of::<usize>();
+    const FIXED_SIZE: Option<NonZeroUsize> = None;
+    fn size(&self) -> usize {
+        mem::size_of::<usize>() + self.len()
+    }
+    unsafe fn write(&self, ptr: *mut u8) {
+        ptr::write_unaligned(ptr as *mut usize, self.len());
+        let offset = mem::size_of::<usize>();
+        ptr::copy_nonoverlapping(self.as_ptr(), ptr.add(offset), self.len());
+    }
+    unsafe fn read(ptr: *const u8) -> Self {
+        let counter = ptr::read_unaligned(ptr as *const usize);
+        let offset = mem::size_of::<usize>();
+        let bytes = slice::from_raw_parts(ptr.add(offset), counter);
+        String::from_utf8_unchecked(bytes.to_vec())
+    }
+}
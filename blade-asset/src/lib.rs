@@ -11,21 +11,33 @@
 
 use std::{
     any::TypeId,
-    collections::hash_map::{DefaultHasher, Entry, HashMap},
+    collections::{
+        hash_map::{DefaultHasher, Entry, HashMap},
+        HashSet,
+    },
     fmt, fs,
     hash::{Hash, Hasher},
-    io::{Read, Seek as _, SeekFrom},
+    io::{self, Read, Seek as _, SeekFrom},
     marker::PhantomData,
     mem, ops,
     path::{Path, PathBuf},
     ptr, str,
-    sync::{Arc, Mutex},
+    sync::{Arc, Mutex, Weak},
+    time::{Duration, Instant},
 };
 
+mod archive;
 mod arena;
+mod cache;
 mod flat;
+mod progress;
+#[cfg(feature = "watch")]
+mod watch;
 
+pub use archive::{Archive, Compression, Packer};
+pub use cache::{list_cache, prune_cache, CacheEntry};
 pub use flat::{round_up, Flat};
+pub use progress::{AssetStatus, LoadState, Progress, Timings};
 
 type Version = u32;
 
@@ -36,10 +48,7 @@ pub struct Handle<T> {
 }
 impl<T> Clone for Handle<T> {
     fn clone(&self) -> Self {
-        Handle {
-            inner: self.inner,
-            version: self.version,
-        }
+        *self
     }
 }
 impl<T> Copy for Handle<T> {}
@@ -63,13 +72,183 @@ impl<T> fmt::Debug for Handle<T> {
     }
 }
 
+impl<T: 'static> Handle<T> {
+    /// Get the identifier of the asset, which is independent of its version.
+    pub fn id(&self) -> AssetId {
+        AssetId {
+            type_id: TypeId::of::<T>(),
+            inner: self.inner.erase(),
+        }
+    }
+}
+
+/// Type-erased identifier of an asset, used for tracking
+/// the dependencies between assets of different types.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct AssetId {
+    type_id: TypeId,
+    inner: arena::Handle<()>,
+}
+
+/// Set of reloaded assets, which other assets may depend on.
+///
+/// See `AssetManager::hot_reload_dependent`.
+#[derive(Default)]
+pub struct ChangeSet {
+    ids: HashSet<AssetId>,
+    tasks: Vec<choir::RunningTask>,
+}
+
+impl ChangeSet {
+    /// Record an asset that is being reloaded by the `task`.
+    pub fn add<T: 'static>(&mut self, handle: Handle<T>, task: &choir::RunningTask) {
+        self.ids.insert(handle.id());
+        self.tasks.push(task.clone());
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// Tasks of reloading the changed assets.
+    pub fn tasks(&self) -> &[choir::RunningTask] {
+        &self.tasks
+    }
+}
+
+/// Handle that keeps its asset alive.
+///
+/// Assets that had strong handles are deleted by `AssetManager::collect_unused`
+/// once all of them are dropped. It dereferences into a plain `Handle`,
+/// which can be used to access the asset.
+pub struct StrongHandle<T> {
+    handle: Handle<T>,
+    _keep_alive: Arc<()>,
+}
+impl<T> Clone for StrongHandle<T> {
+    fn clone(&self) -> Self {
+        StrongHandle {
+            handle: self.handle,
+            _keep_alive: Arc::clone(&self._keep_alive),
+        }
+    }
+}
+impl<T> ops::Deref for StrongHandle<T> {
+    type Target = Handle<T>;
+    fn deref(&self) -> &Handle<T> {
+        &self.handle
+    }
+}
+impl<T> fmt::Debug for StrongHandle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("StrongHandle").field(&self.handle).finish()
+    }
+}
+
+/// Error of cooking or serving an asset.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Error {
+    message: String,
+}
+
+impl Error {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<String> for Error {
+    fn from(message: String) -> Self {
+        Self { message }
+    }
+}
+
+impl From<&str> for Error {
+    fn from(message: &str) -> Self {
+        Self::new(message)
+    }
+}
+
+struct Failure {
+    error: Error,
+    /// Stamp of the sources at the moment of failure.
+    /// The asset is only retried once it changes.
+    sources_stamp: u64,
+}
+
+/// Compute a stamp of the modification times of the sources.
+fn stamp_sources(base_path: &Path, sources: &[PathBuf]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for source in sources {
+        fs::metadata(base_path.join(source))
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
 struct DataRef<T> {
     data: *mut Option<T>,
     version: *mut Version,
     sources: *mut Vec<PathBuf>,
+    failure: *mut Option<Failure>,
+    dependencies: *mut Vec<AssetId>,
+    tracker: Arc<Mutex<progress::Tracker>>,
 }
 unsafe impl<T> Send for DataRef<T> {}
 
+impl<T> DataRef<T> {
+    /// Store the result of loading into the slot.
+    ///
+    /// On failure, the previous data is kept around.
+    unsafe fn store<B: Baker<Output = T>>(
+        self,
+        result: Result<T, Error>,
+        version: Version,
+        sources: Vec<PathBuf>,
+        base_path: &Path,
+        baker: &B,
+    ) {
+        match result {
+            Ok(output) => {
+                let dependencies = &mut *self.dependencies;
+                dependencies.clear();
+                baker.dependencies(&output, dependencies);
+                if let Some(data) = (*self.data).replace(output) {
+                    baker.delete(data);
+                }
+                *self.failure = None;
+                self.tracker.lock().unwrap().enter(LoadState::Ready);
+            }
+            Err(error) => {
+                log::error!("Failed to load {:?}: {}", sources.first(), error);
+                self.tracker.lock().unwrap().fail(error.clone());
+                *self.failure = Some(Failure {
+                    error,
+                    sources_stamp: stamp_sources(base_path, &sources),
+                });
+            }
+        }
+        *self.version = version;
+        *self.sources = sources;
+    }
+}
+
 struct Slot<T> {
     load_task: Option<choir::RunningTask>,
     version: Version,
@@ -78,6 +257,14 @@ struct Slot<T> {
     // Boxed erased type of metadata
     meta: *const (),
     data: Option<T>,
+    failure: Option<Failure>,
+    // Set once the asset is referenced by a `StrongHandle`.
+    // Only accessed with the `paths` locked.
+    keep_alive: Option<Weak<()>>,
+    // Assets of other managers that the data depends on.
+    dependencies: Vec<AssetId>,
+    // Set once the slot is created, and shared with the loading tasks.
+    tracker: Option<Arc<Mutex<progress::Tracker>>>,
 }
 unsafe impl<T> Send for Slot<T> {}
 unsafe impl<T> Sync for Slot<T> {}
@@ -91,15 +278,66 @@ impl<T> Default for Slot<T> {
             sources: Vec::new(),
             meta: ptr::null(),
             data: None,
+            failure: None,
+            keep_alive: None,
+            dependencies: Vec::new(),
+            tracker: None,
+        }
+    }
+}
+
+/// Method of checking the cached assets against their sources.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Invalidation {
+    /// Hash the modification times of the sources.
+    ///
+    /// This is the fastest, but any checkout or copy of the sources
+    /// invalidates the cache.
+    #[default]
+    ModificationTime,
+    /// Hash the sizes and the contents of the sources.
+    ///
+    /// This survives the timestamp churn, allowing the cache to be
+    /// shared between machines running the same build.
+    Content,
+}
+
+impl Invalidation {
+    fn hash_source(
+        self,
+        metadata: &fs::Metadata,
+        content: &[u8],
+        hasher: &mut DefaultHasher,
+    ) -> io::Result<()> {
+        match self {
+            Self::ModificationTime => metadata.modified()?.hash(hasher),
+            Self::Content => {
+                metadata.len().hash(hasher);
+                content.hash(hasher);
+            }
         }
+        Ok(())
     }
 }
 
+/// Configuration of the cooked asset cache.
+#[derive(Clone, Debug, Default)]
+pub struct CacheConfig {
+    /// Method of checking the cached assets against their sources.
+    pub invalidation: Invalidation,
+    /// Root of the source paths.
+    ///
+    /// If set, the cached assets are named by their paths relative to it,
+    /// so that the cache can be shared between different checkouts.
+    pub source_root: Option<PathBuf>,
+}
+
 #[derive(Default)]
 struct Inner {
     result: Vec<u8>,
     dependencies: Vec<PathBuf>,
     hasher: DefaultHasher,
+    error: Option<Error>,
 }
 
 #[allow(unused)]
@@ -125,6 +363,7 @@ struct CachedAssetHeader {
 pub struct Cooker<B> {
     inner: Mutex<Inner>,
     base_path: PathBuf,
+    invalidation: Invalidation,
     _phantom: PhantomData<B>,
 }
 // T doesn't matter for Send/Sync, since we aren't storing it here.
@@ -139,8 +378,10 @@ impl<B: Baker> Cooker<B> {
                 result: Vec::new(),
                 dependencies: Vec::new(),
                 hasher,
+                error: None,
             }),
             base_path: base_path.to_path_buf(),
+            invalidation: Invalidation::default(),
             _phantom: PhantomData,
         }
     }
@@ -150,14 +391,19 @@ impl<B: Baker> Cooker<B> {
         Self {
             inner: Mutex::new(Inner::default()),
             base_path: Default::default(),
+            invalidation: Invalidation::default(),
             _phantom: PhantomData,
         }
     }
 
-    pub fn extract_embedded(&self) -> Vec<u8> {
+    /// Extract the cooked data, or the error of cooking.
+    pub fn extract_embedded(&self) -> Result<Vec<u8>, Error> {
         let mut inner = self.inner.lock().unwrap();
         assert!(inner.dependencies.is_empty());
-        mem::take(&mut inner.result)
+        match inner.error.take() {
+            Some(error) => Err(error),
+            None => Ok(mem::take(&mut inner.result)),
+        }
     }
 
     /// Return the base path of the asset.
@@ -172,26 +418,36 @@ impl<B: Baker> Cooker<B> {
         unsafe { value.write(inner.result.as_mut_ptr()) };
     }
 
+    /// Report a failure to cook.
+    ///
+    /// This is meant for the tasks forked by `Baker::cook`,
+    /// which can't return the error directly.
+    /// Only the first reported error is kept.
+    pub fn fail(&self, error: Error) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.error.is_none() {
+            inner.error = Some(error);
+        }
+    }
+
     /// Read another file as a dependency.
-    pub fn add_dependency(&self, relative_path: &Path) -> Vec<u8> {
+    pub fn add_dependency(&self, relative_path: &Path) -> Result<Vec<u8>, Error> {
         let mut inner = self.inner.lock().unwrap();
         inner.dependencies.push(relative_path.to_path_buf());
         let full_path = self.base_path.join(relative_path);
-        match fs::File::open(&full_path) {
-            Ok(mut file) => {
-                // Read the file at the same time as we include the hash
-                // of its modification time in the header.
-                let mut buf = Vec::new();
-                file.metadata()
-                    .unwrap()
-                    .modified()
-                    .unwrap()
-                    .hash(&mut inner.hasher);
-                file.read_to_end(&mut buf).unwrap();
-                buf
-            }
-            Err(e) => panic!("Unable to read {}: {:?}", full_path.display(), e),
-        }
+        let read = |inner: &mut Inner| -> io::Result<Vec<u8>> {
+            let mut file = fs::File::open(&full_path)?;
+            // Read the file at the same time as we include the hash
+            // of its modification time (or contents) in the header.
+            let mut buf = Vec::new();
+            let metadata = file.metadata()?;
+            file.read_to_end(&mut buf)?;
+            self.invalidation
+                .hash_source(&metadata, &buf, &mut inner.hasher)?;
+            Ok(buf)
+        };
+        read(&mut inner)
+            .map_err(|e| Error::new(format!("Unable to read {}: {}", full_path.display(), e)))
     }
 }
 
@@ -207,7 +463,8 @@ pub trait Baker: Sized + Send + Sync + 'static {
     ///
     /// This method is called within a task within the `exe_context` execution context.
     /// It may fork out other tasks if necessary.
-    /// It must put the result into `result` at some point during execution.
+    /// It must put the result into `result` at some point during execution,
+    /// unless it returns an error, or reports one with `Cooker::fail`.
     fn cook(
         &self,
         source: &[u8],
@@ -215,13 +472,22 @@ pub trait Baker: Sized + Send + Sync + 'static {
         meta: Self::Meta,
         cooker: Arc<Cooker<Self>>,
         exe_context: &choir::ExecutionContext,
-    );
+    ) -> Result<(), Error>;
     /// Produce the output bsed on a cooked asset.
     ///
     /// This method is also called within a task `exe_context`.
-    fn serve(&self, cooked: Self::Data<'_>, exe_context: &choir::ExecutionContext) -> Self::Output;
+    fn serve(
+        &self,
+        cooked: Self::Data<'_>,
+        exe_context: &choir::ExecutionContext,
+    ) -> Result<Self::Output, Error>;
     /// Delete the output of an asset.
     fn delete(&self, output: Self::Output);
+    /// List the assets of other managers that the output depends on.
+    ///
+    /// The output is served again when any of them are reloaded,
+    /// see `AssetManager::hot_reload_dependent`.
+    fn dependencies(&self, _output: &Self::Output, _list: &mut Vec<AssetId>) {}
 }
 
 #[derive(Debug)]
@@ -229,6 +495,7 @@ enum InvalidDependency {
     MalformedPath,
     DoesntExist,
     NotFile,
+    Unreadable,
 }
 
 #[derive(Debug)]
@@ -248,8 +515,9 @@ enum CookReason {
 fn check_target_relevancy(
     target_path: &Path,
     base_path: &Path,
+    invalidation: Invalidation,
     mut hasher: DefaultHasher,
-) -> Result<(), CookReason> {
+) -> Result<Vec<PathBuf>, CookReason> {
     let mut file = fs::File::open(target_path).map_err(|_| CookReason::NoTarget)?;
     let mut hash_bytes = [0u8; 8];
     file.read_exact(&mut hash_bytes)
@@ -267,6 +535,7 @@ fn check_target_relevancy(
         return Err(CookReason::TooManyDependencies(num_deps));
     }
     let mut dep_str = Vec::new();
+    let mut dependencies = Vec::with_capacity(num_deps);
     for i in 0..num_deps {
         file.read_exact(&mut temp_bytes)
             .map_err(|_| CookReason::BadHeader)?;
@@ -274,24 +543,122 @@ fn check_target_relevancy(
         dep_str.resize(str_len, 0u8);
         file.read_exact(&mut dep_str)
             .map_err(|_| CookReason::BadHeader)?;
-        let dep_path = base_path.join(
+        let dependency = PathBuf::from(
             str::from_utf8(&dep_str)
                 .map_err(|_| CookReason::Dependency(i, InvalidDependency::MalformedPath))?,
         );
-        let metadata = fs::metadata(dep_path)
+        let dep_path = base_path.join(&dependency);
+        let metadata = fs::metadata(&dep_path)
             .map_err(|_| CookReason::Dependency(i, InvalidDependency::DoesntExist))?;
         if !metadata.is_file() {
             return Err(CookReason::Dependency(i, InvalidDependency::NotFile));
         }
-        metadata.modified().unwrap().hash(&mut hasher);
+        let content = match invalidation {
+            Invalidation::ModificationTime => Vec::new(),
+            Invalidation::Content => fs::read(&dep_path)
+                .map_err(|_| CookReason::Dependency(i, InvalidDependency::Unreadable))?,
+        };
+        invalidation
+            .hash_source(&metadata, &content, &mut hasher)
+            .map_err(|_| CookReason::Dependency(i, InvalidDependency::Unreadable))?;
+        dependencies.push(dependency);
     }
+    cache::Label::read(&mut file).map_err(|_| CookReason::BadHeader)?;
 
     if hasher.finish() != current_hash {
         Err(CookReason::Outdated)
     } else if file.stream_position().unwrap() != data_offset {
         Err(CookReason::WrongDataOffset)
     } else {
-        Ok(())
+        Ok(dependencies)
+    }
+}
+
+fn write_target(target_path: &Path, label: &cache::Label, inner: &Inner) -> io::Result<()> {
+    use std::io::Write as _;
+
+    let mut file = fs::File::create(target_path)?;
+    file.write_all(&[0; 8])?; // write zero hash first
+    file.write_all(&[0; 8])?; // write zero data offset
+                              // write down the dependencies
+    file.write_all(&inner.dependencies.len().to_le_bytes())?;
+    for dep in inner.dependencies.iter() {
+        let dep_bytes = dep.to_str().unwrap().as_bytes();
+        file.write_all(&dep_bytes.len().to_le_bytes())?;
+        file.write_all(dep_bytes)?;
+    }
+    label.write(&mut file)?;
+    let data_offset = file.stream_position()?;
+    file.write_all(&inner.result)?;
+    // Write the real hash last, so that the cached file is not valid
+    // unless everything went smooth.
+    file.seek(SeekFrom::Start(0))?;
+    let hash = inner.hasher.finish();
+    file.write_all(&hash.to_le_bytes())?;
+    file.write_all(&data_offset.to_le_bytes())?;
+    Ok(())
+}
+
+/// Check the result of cooking and write it down into the target.
+fn finish_cooking(
+    target_path: &Path,
+    label: &cache::Label,
+    inner: &mut Inner,
+) -> Result<(), Error> {
+    if let Some(error) = inner.error.take() {
+        return Err(error);
+    }
+    if inner.result.is_empty() {
+        return Err(Error::new("Cooking didn't produce any data"));
+    }
+    write_target(target_path, label, inner)
+        .map_err(|e| Error::new(format!("Unable to write {}: {}", target_path.display(), e)))
+}
+
+fn read_target(target_path: &Path) -> io::Result<Vec<u8>> {
+    let mut file = fs::File::open(target_path)?;
+    let mut bytes = [0u8; 8];
+    file.read_exact(&mut bytes)?;
+    let _hash = u64::from_le_bytes(bytes);
+    file.read_exact(&mut bytes)?;
+    let offset = u64::from_le_bytes(bytes);
+    file.seek(SeekFrom::Start(offset))?;
+    let mut data = Vec::new();
+    file.read_to_end(&mut data)?;
+    Ok(data)
+}
+
+/// Task of cooking an asset ahead of time, see `AssetManager::bake`.
+pub struct BakeTask {
+    task: choir::RunningTask,
+    result: Arc<Mutex<Result<Duration, Error>>>,
+}
+
+impl BakeTask {
+    pub fn is_done(&self) -> bool {
+        self.task.is_done()
+    }
+
+    /// Wait for the cooking to finish, and return its result.
+    ///
+    /// On success, returns the time it took to cook this asset.
+    pub fn join(self) -> Result<Duration, Error> {
+        self.task.join();
+        mem::replace(&mut *self.result.lock().unwrap(), Ok(Duration::ZERO))
+    }
+}
+
+/// Split the path of an asset into the base path and the file name.
+fn split_path(path: &Path) -> Result<(&Path, &Path), Error> {
+    match path.file_name() {
+        Some(file_name) => Ok((
+            path.parent().unwrap_or_else(|| Path::new(".")),
+            Path::new(file_name),
+        )),
+        None => Err(Error::new(format!(
+            "Path {} doesn't name a file",
+            path.display()
+        ))),
     }
 }
 
@@ -302,9 +669,15 @@ fn check_target_relevancy(
 /// and scheduling tasks for cooking and serving assets.
 pub struct AssetManager<B: Baker> {
     target: PathBuf,
+    cache_config: CacheConfig,
+    /// Read-only source of cooked assets, replacing the target.
+    archive: Option<Arc<Archive>>,
     slots: arena::Arena<Slot<B::Output>>,
     #[allow(clippy::type_complexity)]
     paths: Mutex<HashMap<(PathBuf, B::Meta), Handle<B::Output>>>,
+    #[cfg(feature = "watch")]
+    #[allow(clippy::type_complexity)]
+    watcher: Mutex<Option<watch::Watcher<arena::Handle<Slot<B::Output>>>>>,
     pub choir: Arc<choir::Choir>,
     /// Asset-specific implementation.
     pub baker: Arc<B>,
@@ -315,7 +688,11 @@ impl<B: Baker> ops::Index<Handle<B::Output>> for AssetManager<B> {
     fn index(&self, handle: Handle<B::Output>) -> &Self::Output {
         let slot = &self.slots[handle.inner];
         assert_eq!(handle.version, slot.version, "Outdated {:?}", handle);
-        slot.data.as_ref().unwrap()
+        match (slot.data.as_ref(), slot.failure.as_ref()) {
+            (Some(data), _) => data,
+            (None, Some(failure)) => panic!("Asset {:?} failed: {}", handle, failure.error),
+            (None, None) => panic!("Asset {:?} is not loaded", handle),
+        }
     }
 }
 
@@ -324,45 +701,187 @@ impl<B: Baker> AssetManager<B> {
     ///
     /// The `target` points to the folder to store cooked assets in.
     pub fn new(target: &Path, choir: &Arc<choir::Choir>, baker: B) -> Self {
+        Self::with_cache_config(target, choir, baker, CacheConfig::default())
+    }
+
+    /// Create a new asset manager with a specific configuration of the cache.
+    ///
+    /// The `target` may be a build-cache directory shared between
+    /// checkouts and machines, if the configuration allows it.
+    pub fn with_cache_config(
+        target: &Path,
+        choir: &Arc<choir::Choir>,
+        baker: B,
+        cache_config: CacheConfig,
+    ) -> Self {
         if !target.is_dir() {
             log::info!("Creating target {}", target.display());
             fs::create_dir_all(target).unwrap();
         }
         Self {
             target: target.to_path_buf(),
+            cache_config,
+            archive: None,
+            slots: arena::Arena::new(64),
+            paths: Mutex::default(),
+            #[cfg(feature = "watch")]
+            watcher: Mutex::new(None),
+            choir: Arc::clone(choir),
+            baker: Arc::new(baker),
+        }
+    }
+
+    /// Create a new asset manager that loads cooked assets from an archive.
+    ///
+    /// The sources don't need to be present, and nothing is ever cooked.
+    /// The archive can be shared between managers of different asset types.
+    ///
+    /// The assets are found by their paths relative to `cache_config.source_root`,
+    /// so the archive needs to be packed from a cache cooked with a source root
+    /// in order to be loaded from a different location.
+    pub fn with_archive(
+        archive: &Arc<Archive>,
+        choir: &Arc<choir::Choir>,
+        baker: B,
+        cache_config: CacheConfig,
+    ) -> Self {
+        Self {
+            target: PathBuf::new(),
+            cache_config,
+            archive: Some(Arc::clone(archive)),
             slots: arena::Arena::new(64),
             paths: Mutex::default(),
+            #[cfg(feature = "watch")]
+            watcher: Mutex::new(None),
             choir: Arc::clone(choir),
             baker: Arc::new(baker),
         }
     }
 
+    /// Get the asset data, if it's available.
+    ///
+    /// If the last (re-)load failed, this returns the data from the
+    /// previous successful load, if there was one.
+    pub fn get(&self, handle: Handle<B::Output>) -> Option<&B::Output> {
+        let slot = &self.slots[handle.inner];
+        if handle.version != slot.version {
+            return None;
+        }
+        slot.data.as_ref()
+    }
+
+    /// Get the error of the last attempt to load an asset, if it failed.
+    pub fn get_error(&self, handle: Handle<B::Output>) -> Option<&Error> {
+        let slot = &self.slots[handle.inner];
+        if handle.version != slot.version {
+            return None;
+        }
+        slot.failure.as_ref().map(|failure| &failure.error)
+    }
+
     pub fn get_main_source_path(&self, handle: Handle<B::Output>) -> Option<&PathBuf> {
         self.slots[handle.inner].sources.first()
     }
 
-    fn make_target_path(&self, base_path: &Path, file_name: &Path, meta: &B::Meta) -> PathBuf {
+    fn relative_base_path<'a>(&self, base_path: &'a Path) -> &'a Path {
+        match self.cache_config.source_root {
+            Some(ref root) => base_path.strip_prefix(root).unwrap_or(base_path),
+            None => base_path,
+        }
+    }
+
+    fn make_target_name(&self, base_path: &Path, file_name: &Path, meta: &B::Meta) -> String {
         use base64::engine::{general_purpose::URL_SAFE as ENCODING_ENGINE, Engine as _};
         // The name hash includes the parent path and the metadata.
         let mut hasher = DefaultHasher::new();
-        base_path.hash(&mut hasher);
+        self.relative_base_path(base_path).hash(&mut hasher);
         meta.hash(&mut hasher);
         let hash = hasher.finish().to_le_bytes();
         let mut file_name_str = format!("{}-", file_name.display());
         ENCODING_ENGINE.encode_string(hash, &mut file_name_str);
         file_name_str += ".raw";
-        self.target.join(file_name_str)
+        file_name_str
+    }
+
+    fn make_label(&self, base_path: &Path, file_name: &Path, meta: &B::Meta) -> cache::Label {
+        cache::Label {
+            source: self.relative_base_path(base_path).join(file_name),
+            meta: meta.to_string(),
+        }
+    }
+
+    fn make_content_hasher() -> DefaultHasher {
+        let mut hasher = DefaultHasher::new();
+        TypeId::of::<B::Data<'static>>().hash(&mut hasher);
+        hasher
+    }
+
+    /// Spawn a task to cook an asset into the `cooker`.
+    fn spawn_cook(
+        &self,
+        file_name: &Path,
+        content: Option<Vec<u8>>,
+        meta: B::Meta,
+        cooker: &Arc<Cooker<B>>,
+        tracker: Option<&Arc<Mutex<progress::Tracker>>>,
+    ) -> choir::IdleTask {
+        // Note: this task is separate, because it may spawn sub-tasks.
+        let baker = Arc::clone(&self.baker);
+        let cooker = Arc::clone(cooker);
+        let tracker = tracker.cloned();
+        let file_name = file_name.to_owned();
+        self.choir
+            .spawn(format!("cook {} as {}", file_name.display(), meta))
+            .init(move |exe_context| {
+                if let Some(ref tracker) = tracker {
+                    tracker.lock().unwrap().enter(LoadState::Cooking);
+                }
+                // Read the source file through the same mechanism as the
+                // dependencies, so that its modified time makes it into the hash.
+                let extension = match file_name.extension().and_then(|ext| ext.to_str()) {
+                    Some(extension) => extension,
+                    None => {
+                        cooker.fail(Error::new(format!(
+                            "Unable to determine the extension of {}",
+                            file_name.display()
+                        )));
+                        return;
+                    }
+                };
+                let source = match content {
+                    Some(data) => data,
+                    None => match cooker.add_dependency(&file_name) {
+                        Ok(data) => data,
+                        Err(error) => {
+                            cooker.fail(error);
+                            return;
+                        }
+                    },
+                };
+                if let Err(error) =
+                    baker.cook(&source, extension, meta, Arc::clone(&cooker), &exe_context)
+                {
+                    cooker.fail(error);
+                }
+            })
     }
 
+    /// Start loading an asset into the slot.
+    ///
+    /// If `wait_for` is provided, the asset is served again even if it's
+    /// up to date, but only after all of these tasks are done.
     fn create_impl<'a>(
         &self,
         slot: &'a mut Slot<B::Output>,
         file_name: &Path,
         content: Option<&[u8]>,
+        wait_for: Option<&[choir::RunningTask]>,
     ) -> Option<(u32, &'a choir::RunningTask)> {
-        use std::{hash::Hasher as _, io::Write as _};
-
         let version = slot.version + 1;
+        let tracker = Arc::clone(slot.tracker.get_or_insert_with(|| {
+            let path = slot.base_path.join(file_name);
+            Arc::new(Mutex::new(progress::Tracker::new(path)))
+        }));
         let (task_option, meta, data_ref) = (
             &mut slot.load_task,
             unsafe { &*(slot.meta as *const B::Meta) },
@@ -370,114 +889,119 @@ impl<B: Baker> AssetManager<B> {
                 data: &mut slot.data,
                 version: &mut slot.version,
                 sources: &mut slot.sources,
+                failure: &mut slot.failure,
+                dependencies: &mut slot.dependencies,
+                tracker: Arc::clone(&tracker),
             },
         );
 
-        let target_path = self.make_target_path(&slot.base_path, file_name, meta);
+        let target_name = self.make_target_name(&slot.base_path, file_name, meta);
+        let target_path = self.target.join(&target_name);
         let file_name = file_name.to_owned();
         let content = content.map(Vec::from);
-        let mut hasher = DefaultHasher::new();
-        TypeId::of::<B::Data<'static>>().hash(&mut hasher);
+        let hasher = Self::make_content_hasher();
 
-        let load_task = if let Err(reason) =
-            check_target_relevancy(&target_path, &slot.base_path, hasher.clone())
-        {
+        let relevancy = match self.archive {
+            Some(_) => Ok(Vec::new()),
+            None => check_target_relevancy(
+                &target_path,
+                &slot.base_path,
+                self.cache_config.invalidation,
+                hasher.clone(),
+            ),
+        };
+
+        let mut load_task = if let Some(ref archive) = self.archive {
+            // Archived assets never change, so there is nothing to reload.
+            if task_option.is_some() && wait_for.is_none() {
+                return None;
+            }
+            tracker.lock().unwrap().restart();
+            let archive = Arc::clone(archive);
+            let baker = Arc::clone(&self.baker);
+            let base_path = slot.base_path.clone();
+            self.choir
+                .spawn(format!("load {} from archive", file_name.display()))
+                .init(move |exe_context| {
+                    data_ref.tracker.lock().unwrap().enter(LoadState::Serving);
+                    let result = match archive.read(&target_name) {
+                        Ok(Some(data)) => {
+                            let cooked = unsafe { <B::Data<'_> as Flat>::read(data.as_ptr()) };
+                            baker.serve(cooked, &exe_context)
+                        }
+                        Ok(None) => Err(Error::new(format!(
+                            "{} is missing from the archive",
+                            target_name
+                        ))),
+                        Err(e) => Err(Error::new(format!(
+                            "Unable to read {} from the archive: {}",
+                            target_name, e
+                        ))),
+                    };
+                    let sources = vec![file_name];
+                    unsafe { data_ref.store(result, version, sources, &base_path, &*baker) };
+                })
+        } else if let Err(ref reason) = relevancy {
             log::info!(
                 "Cooking {:?}: {} version={}",
                 reason,
                 file_name.display(),
                 version
             );
-            let cooker = Arc::new(Cooker::new(&slot.base_path, hasher));
-            let cooker_arg = Arc::clone(&cooker);
+            tracker.lock().unwrap().restart();
+            let cooker = Arc::new(Cooker {
+                invalidation: self.cache_config.invalidation,
+                ..Cooker::new(&slot.base_path, hasher)
+            });
+            let cook_task =
+                self.spawn_cook(&file_name, content, meta.clone(), &cooker, Some(&tracker));
             let baker = Arc::clone(&self.baker);
+            let base_path = slot.base_path.clone();
+            let label = self.make_label(&base_path, &file_name, meta);
             let mut load_task = self
                 .choir
                 .spawn(format!("cook finish for {}", file_name.display()))
                 .init(move |exe_context| {
                     let mut inner = cooker.inner.lock().unwrap();
-                    assert!(!inner.result.is_empty());
-                    let mut file = fs::File::create(&target_path).unwrap_or_else(|e| {
-                        panic!("Unable to create {}: {}", target_path.display(), e)
+                    let result = finish_cooking(&target_path, &label, &mut inner).and_then(|()| {
+                        data_ref.tracker.lock().unwrap().enter(LoadState::Serving);
+                        let cooked = unsafe { <B::Data<'_> as Flat>::read(inner.result.as_ptr()) };
+                        baker.serve(cooked, &exe_context)
                     });
-                    file.write_all(&[0; 8]).unwrap(); // write zero hash first
-                    file.write_all(&[0; 8]).unwrap(); // write zero data offset
-                                                      // write down the dependencies
-                    file.write_all(&inner.dependencies.len().to_le_bytes())
-                        .unwrap();
-                    for dep in inner.dependencies.iter() {
-                        let dep_bytes = dep.to_str().unwrap().as_bytes();
-                        file.write_all(&dep_bytes.len().to_le_bytes()).unwrap();
-                        file.write_all(dep_bytes).unwrap();
-                    }
-                    let data_offset = file.stream_position().unwrap();
-                    file.write_all(&inner.result).unwrap();
-                    // Write the real hash last, so that the cached file is not valid
-                    // unless everything went smooth.
-                    file.seek(SeekFrom::Start(0)).unwrap();
-                    let hash = inner.hasher.finish();
-                    file.write_all(&hash.to_le_bytes()).unwrap();
-                    file.write_all(&data_offset.to_le_bytes()).unwrap();
-
-                    let dr = data_ref;
-                    if let Some(data) = unsafe { (*dr.data).take() } {
-                        baker.delete(data);
-                    }
-                    let cooked = unsafe { <B::Data<'_> as Flat>::read(inner.result.as_ptr()) };
-                    let target = baker.serve(cooked, &exe_context);
-                    unsafe {
-                        *dr.data = Some(target);
-                        *dr.version = version;
-                        *dr.sources = mem::take(&mut inner.dependencies);
-                    }
-                });
-
-            // Note: this task is separate, because it may spawn sub-tasks.
-            let baker = Arc::clone(&self.baker);
-            let meta = meta.clone();
-            let cook_task = self
-                .choir
-                .spawn(format!("cook {} as {}", file_name.display(), meta))
-                .init(move |exe_context| {
-                    // Read the source file through the same mechanism as the
-                    // dependencies, so that its modified time makes it into the hash.
-                    let extension = file_name.extension().unwrap().to_str().unwrap();
-                    let source = match content {
-                        Some(data) => data,
-                        None => cooker_arg.add_dependency(&file_name),
-                    };
-                    baker.cook(&source, extension, meta, cooker_arg, &exe_context);
+                    let sources = mem::take(&mut inner.dependencies);
+                    unsafe { data_ref.store(result, version, sources, &base_path, &*baker) };
                 });
 
             load_task.depend_on(&cook_task);
             load_task
-        } else if task_option.is_none() {
+        } else if task_option.is_none() || wait_for.is_some() {
+            // Same sources as recorded by the cooking.
+            let sources = relevancy.unwrap_or_default();
+            tracker.lock().unwrap().restart();
             let baker = Arc::clone(&self.baker);
+            let base_path = slot.base_path.clone();
             self.choir
                 .spawn(format!("load {} with {}", file_name.display(), meta))
                 .init(move |exe_context| {
-                    let mut file = fs::File::open(target_path).unwrap();
-                    let mut bytes = [0u8; 8];
-                    file.read_exact(&mut bytes).unwrap();
-                    let _hash = u64::from_le_bytes(bytes);
-                    file.read_exact(&mut bytes).unwrap();
-                    let offset = u64::from_le_bytes(bytes);
-                    file.seek(SeekFrom::Start(offset)).unwrap();
-                    let mut data = Vec::new();
-                    file.read_to_end(&mut data).unwrap();
-                    let cooked = unsafe { <B::Data<'_> as Flat>::read(data.as_ptr()) };
-                    let target = baker.serve(cooked, &exe_context);
-                    let dr = data_ref;
-                    unsafe {
-                        *dr.data = Some(target);
-                        *dr.version = version;
-                        (*dr.sources).push(file_name);
-                    }
+                    data_ref.tracker.lock().unwrap().enter(LoadState::Serving);
+                    let result = read_target(&target_path)
+                        .map_err(|e| {
+                            Error::new(format!("Unable to read {}: {}", target_path.display(), e))
+                        })
+                        .and_then(|data| {
+                            cache::touch(&target_path);
+                            let cooked = unsafe { <B::Data<'_> as Flat>::read(data.as_ptr()) };
+                            baker.serve(cooked, &exe_context)
+                        });
+                    unsafe { data_ref.store(result, version, sources, &base_path, &*baker) };
                 })
         } else {
             return None;
         };
 
+        for task in wait_for.unwrap_or_default() {
+            load_task.depend_on(task);
+        }
         let running_task = task_option.insert(load_task.run());
         Some((version, running_task))
     }
@@ -485,8 +1009,9 @@ impl<B: Baker> AssetManager<B> {
     fn create(&self, source_path: &Path, meta: B::Meta) -> Handle<B::Output> {
         let (handle, slot_ptr) = self.slots.alloc_default();
         let slot = unsafe { &mut *slot_ptr };
-        assert_eq!(slot.version, 0);
+        // Keep the version of a freed slot, so that the old handles stay invalid.
         *slot = Slot {
+            version: slot.version,
             base_path: source_path
                 .parent()
                 .unwrap_or_else(|| Path::new("."))
@@ -496,7 +1021,7 @@ impl<B: Baker> AssetManager<B> {
         };
 
         let file_name = Path::new(source_path.file_name().unwrap());
-        let (version, _) = self.create_impl(slot, file_name, None).unwrap();
+        let (version, _) = self.create_impl(slot, file_name, None, None).unwrap();
         Handle {
             inner: handle,
             version,
@@ -516,13 +1041,13 @@ impl<B: Baker> AssetManager<B> {
     ) -> (Handle<B::Output>, &choir::RunningTask) {
         let (handle, slot_ptr) = self.slots.alloc_default();
         let slot = unsafe { &mut *slot_ptr };
-        assert_eq!(slot.version, 0);
         *slot = Slot {
+            version: slot.version,
             meta: Box::into_raw(Box::new(meta)) as *const _,
             ..Default::default()
         };
 
-        let (version, _) = self.create_impl(slot, name, Some(data)).unwrap();
+        let (version, _) = self.create_impl(slot, name, Some(data), None).unwrap();
 
         let task = self.slots[handle].load_task.as_ref().unwrap();
         let out_handle = Handle {
@@ -532,6 +1057,127 @@ impl<B: Baker> AssetManager<B> {
         (out_handle, task)
     }
 
+    /// Cook an asset ahead of time, without serving it.
+    ///
+    /// Returns `None` if the cooked asset is already up to date.
+    pub fn bake(&self, path: impl AsRef<Path>, meta: B::Meta) -> Result<Option<BakeTask>, Error> {
+        assert!(self.archive.is_none(), "Archives can't be baked into");
+        let path = path.as_ref();
+        let (base_path, file_name) = split_path(path)?;
+        let target_path = self
+            .target
+            .join(self.make_target_name(base_path, file_name, &meta));
+        let hasher = Self::make_content_hasher();
+        if let Err(reason) = check_target_relevancy(
+            &target_path,
+            base_path,
+            self.cache_config.invalidation,
+            hasher.clone(),
+        ) {
+            log::info!("Baking {:?}: {}", reason, path.display());
+        } else {
+            cache::touch(&target_path);
+            return Ok(None);
+        }
+
+        let cooker = Arc::new(Cooker {
+            invalidation: self.cache_config.invalidation,
+            ..Cooker::new(base_path, hasher)
+        });
+        let label = self.make_label(base_path, file_name, &meta);
+        let mut cook_task = self.spawn_cook(file_name, None, meta, &cooker, None);
+        // Measure from the moment a worker picks up the cooking.
+        let start = Arc::new(Mutex::new(None));
+        let start_arg = Arc::clone(&start);
+        let start_task = self
+            .choir
+            .spawn(format!("bake start for {}", path.display()))
+            .init(move |_| {
+                *start_arg.lock().unwrap() = Some(Instant::now());
+            });
+        cook_task.depend_on(&start_task);
+        let result = Arc::new(Mutex::new(Ok(Duration::ZERO)));
+        let result_arg = Arc::clone(&result);
+        let mut finish_task = self
+            .choir
+            .spawn(format!("bake finish for {}", path.display()))
+            .init(move |_| {
+                let mut inner = cooker.inner.lock().unwrap();
+                let elapsed = start
+                    .lock()
+                    .unwrap()
+                    .map_or(Duration::ZERO, |s| s.elapsed());
+                *result_arg.lock().unwrap() =
+                    finish_cooking(&target_path, &label, &mut inner).map(|()| elapsed);
+            });
+        finish_task.depend_on(&cook_task);
+        Ok(Some(BakeTask {
+            task: finish_task.run(),
+            result,
+        }))
+    }
+
+    /// Read the cooked data of an asset without serving it.
+    ///
+    /// The asset has to be cooked already, for example with `bake`.
+    pub fn read_cooked<T>(
+        &self,
+        path: impl AsRef<Path>,
+        meta: &B::Meta,
+        fun: impl FnOnce(B::Data<'_>) -> T,
+    ) -> Result<T, Error> {
+        let (base_path, file_name) = split_path(path.as_ref())?;
+        self.read_target_data(base_path, file_name, meta, fun)
+    }
+
+    /// Get the source path and the metadata of a loaded asset,
+    /// which its cooked data can be read with by `read_cooked`.
+    ///
+    /// Fails for the assets served from the data of other assets,
+    /// such as the ones embedded into models, since they aren't cooked on their own.
+    pub fn get_cooked_source(
+        &self,
+        handle: Handle<B::Output>,
+    ) -> Result<(PathBuf, B::Meta), Error> {
+        let slot = &self.slots[handle.inner];
+        if handle.version != slot.version {
+            return Err(Error::new("Asset handle is outdated"));
+        }
+        match slot.sources.first() {
+            Some(file_name) if !slot.meta.is_null() => {
+                let meta = unsafe { &*(slot.meta as *const B::Meta) };
+                Ok((slot.base_path.join(file_name), meta.clone()))
+            }
+            _ => Err(Error::new("Asset doesn't have cooked data of its own")),
+        }
+    }
+
+    fn read_target_data<T>(
+        &self,
+        base_path: &Path,
+        file_name: &Path,
+        meta: &B::Meta,
+        fun: impl FnOnce(B::Data<'_>) -> T,
+    ) -> Result<T, Error> {
+        let target_name = self.make_target_name(base_path, file_name, meta);
+        let data = match self.archive {
+            Some(ref archive) => archive
+                .read(&target_name)
+                .map_err(|e| Error::new(format!("Unable to read {}: {}", target_name, e)))?
+                .ok_or_else(|| {
+                    Error::new(format!("{} is missing from the archive", target_name))
+                })?,
+            None => {
+                let target_path = self.target.join(&target_name);
+                read_target(&target_path).map_err(|e| {
+                    Error::new(format!("Unable to read {}: {}", target_path.display(), e))
+                })?
+            }
+        };
+        let cooked = unsafe { <B::Data<'_> as Flat>::read(data.as_ptr()) };
+        Ok(fun(cooked))
+    }
+
     /// Load an asset given the relative path.
     ///
     /// Metadata is an asset-specific piece of information that determines how the asset is processed.
@@ -544,17 +1190,69 @@ impl<B: Baker> AssetManager<B> {
         path: impl AsRef<Path>,
         meta: B::Meta,
     ) -> (Handle<B::Output>, &choir::RunningTask) {
-        let path_buf = path.as_ref().to_path_buf();
         let mut paths = self.paths.lock().unwrap();
-        let handle = match paths.entry((path_buf, meta)) {
+        let handle = self.find_or_create(&mut paths, path.as_ref(), meta);
+        let task = self.slots[handle.inner].load_task.as_ref().unwrap();
+        (handle, task)
+    }
+
+    /// Load an asset given the relative path, and keep it alive
+    /// for as long as the returned handle or its clones exist.
+    ///
+    /// See `load` and `collect_unused`.
+    pub fn load_strong(
+        &self,
+        path: impl AsRef<Path>,
+        meta: B::Meta,
+    ) -> (StrongHandle<B::Output>, &choir::RunningTask) {
+        let mut paths = self.paths.lock().unwrap();
+        let handle = self.find_or_create(&mut paths, path.as_ref(), meta);
+        let strong = self.strengthen(handle);
+        let task = self.slots[handle.inner].load_task.as_ref().unwrap();
+        (strong, task)
+    }
+
+    /// Produce a strong handle for an asset, which keeps it alive.
+    pub fn make_strong(&self, handle: Handle<B::Output>) -> StrongHandle<B::Output> {
+        let _paths = self.paths.lock().unwrap();
+        assert_eq!(
+            handle.version, self.slots[handle.inner].version,
+            "Outdated {:?}",
+            handle
+        );
+        self.strengthen(handle)
+    }
+
+    fn find_or_create(
+        &self,
+        paths: &mut HashMap<(PathBuf, B::Meta), Handle<B::Output>>,
+        path: &Path,
+        meta: B::Meta,
+    ) -> Handle<B::Output> {
+        match paths.entry((path.to_path_buf(), meta)) {
             Entry::Occupied(e) => *e.get(),
             Entry::Vacant(e) => {
                 let handle = self.create(&e.key().0, e.key().1.clone());
                 *e.insert(handle)
             }
+        }
+    }
+
+    /// Must be called with the `paths` locked.
+    fn strengthen(&self, handle: Handle<B::Output>) -> StrongHandle<B::Output> {
+        let slot = unsafe { &mut *self.slots.get_mut_ptr(handle.inner) };
+        let keep_alive = match slot.keep_alive.as_ref().and_then(Weak::upgrade) {
+            Some(keep_alive) => keep_alive,
+            None => {
+                let keep_alive = Arc::new(());
+                slot.keep_alive = Some(Arc::downgrade(&keep_alive));
+                keep_alive
+            }
         };
-        let task = self.slots[handle.inner].load_task.as_ref().unwrap();
-        (handle, task)
+        StrongHandle {
+            handle,
+            _keep_alive: keep_alive,
+        }
     }
 
     /// Load an asset that has been pre-cooked already.
@@ -565,13 +1263,31 @@ impl<B: Baker> AssetManager<B> {
         cooked: B::Data<'_>,
         exe_context: &choir::ExecutionContext,
     ) -> Handle<B::Output> {
-        let value = self.baker.serve(cooked, exe_context);
+        self.add_served(self.baker.serve(cooked, exe_context))
+    }
+
+    /// Add an asset that has been served already, such as by
+    /// a specific method of the baker.
+    ///
+    /// Like with `load_cooked_inside_task`, the asset has no sources.
+    pub fn add_served(&self, result: Result<B::Output, Error>) -> Handle<B::Output> {
+        let (data, failure) = match result {
+            Ok(value) => (Some(value), None),
+            Err(error) => {
+                log::error!("Failed to serve a cooked asset: {}", error);
+                let failure = Failure {
+                    error,
+                    sources_stamp: 0,
+                };
+                (None, Some(failure))
+            }
+        };
         let (handle, slot_ptr) = self.slots.alloc_default();
         let slot = unsafe { &mut *slot_ptr };
-        assert_eq!(slot.version, 0);
         *slot = Slot {
-            version: 1,
-            data: Some(value),
+            version: slot.version + 1,
+            data,
+            failure,
             ..Slot::default()
         };
         Handle {
@@ -585,30 +1301,245 @@ impl<B: Baker> AssetManager<B> {
     /// Invalidates all handles produced from loading assets.
     pub fn clear(&self) {
         self.paths.lock().unwrap().clear();
-        self.slots.dealloc_each(|_handle, slot| {
-            if let Some(task) = slot.load_task {
-                task.join();
+        self.slots
+            .dealloc_each(|_handle, slot| self.free_slot(slot))
+    }
+
+    /// Delete the assets that were referenced by strong handles,
+    /// but have none of them left.
+    ///
+    /// Assets that never had strong handles are not affected,
+    /// and neither are the ones still loading.
+    /// Plain handles to the deleted assets become invalid.
+    /// Returns the number of deleted assets.
+    pub fn collect_unused(&self) -> usize {
+        let mut paths = self.paths.lock().unwrap();
+        let mut unused = HashSet::new();
+        self.slots.for_each(|handle, slot| {
+            let is_unused = slot
+                .keep_alive
+                .as_ref()
+                .is_some_and(|keep_alive| keep_alive.strong_count() == 0);
+            let is_loaded = slot.load_task.as_ref().is_none_or(|task| task.is_done());
+            if is_unused && is_loaded {
+                unused.insert(handle);
             }
-            if let Some(data) = slot.data {
-                self.baker.delete(data);
+        });
+        if unused.is_empty() {
+            return 0;
+        }
+
+        paths.retain(|_, handle| !unused.contains(&handle.inner));
+        #[cfg(feature = "watch")]
+        if let Some(ref mut watcher) = *self.watcher.lock().unwrap() {
+            for &handle in unused.iter() {
+                watcher.untrack(handle);
             }
-            if !slot.meta.is_null() {
-                unsafe {
-                    let _ = Box::from_raw(slot.meta as *mut B::Meta);
-                }
+        }
+        for &handle in unused.iter() {
+            log::debug!("Deleting unused {:?}", handle);
+            // Bump the version, so that the old handles become invalid.
+            let tombstone = Slot {
+                version: self.slots[handle].version + 1,
+                ..Slot::default()
+            };
+            let slot = self.slots.dealloc(handle, tombstone);
+            self.free_slot(slot);
+        }
+        unused.len()
+    }
+
+    fn free_slot(&self, slot: Slot<B::Output>) {
+        if let Some(task) = slot.load_task {
+            task.join();
+        }
+        if let Some(data) = slot.data {
+            self.baker.delete(data);
+        }
+        if !slot.meta.is_null() {
+            unsafe {
+                let _ = Box::from_raw(slot.meta as *mut B::Meta);
             }
-        })
+        }
     }
 
     /// Hot reload a changed asset.
+    ///
+    /// A failed asset is retried once any of its sources change.
+    /// Assets without a source file, such as the ones created by
+    /// `load_cooked_inside_task`, are not reloaded.
+    /// The asset is removed from the queue of the watched changes.
     pub fn hot_reload(&self, handle: &mut Handle<B::Output>) -> Option<&choir::RunningTask> {
+        self.acknowledge_change(*handle);
         let slot = unsafe { &mut *self.slots.get_mut_ptr(handle.inner) };
-        let file_name = slot.sources.first().unwrap().to_owned();
-        self.create_impl(slot, &file_name, None)
-            .map(|(version, task)| {
-                handle.version = version;
-                task
-            })
+        if let Some(ref failure) = slot.failure {
+            if failure.sources_stamp == stamp_sources(&slot.base_path, &slot.sources) {
+                return None;
+            }
+        }
+        let file_name = slot.sources.first()?.to_owned();
+        let (version, task) = self.create_impl(slot, &file_name, None, None)?;
+        self.update_version(handle, version);
+        Some(task)
+    }
+
+    fn acknowledge_change(&self, _handle: Handle<B::Output>) {
+        #[cfg(feature = "watch")]
+        if let Some(ref mut watcher) = *self.watcher.lock().unwrap() {
+            watcher.acknowledge(_handle.inner);
+        }
+    }
+
+    /// Hot reload an asset if it depends on any of the changed assets.
+    ///
+    /// The asset is served again after the changed assets are loaded.
+    pub fn hot_reload_dependent(
+        &self,
+        handle: &mut Handle<B::Output>,
+        changes: &ChangeSet,
+    ) -> Option<&choir::RunningTask> {
+        let slot = unsafe { &mut *self.slots.get_mut_ptr(handle.inner) };
+        if !slot.dependencies.iter().any(|id| changes.ids.contains(id)) {
+            return None;
+        }
+        self.acknowledge_change(*handle);
+        let file_name = slot.sources.first()?.to_owned();
+        let (version, task) = self.create_impl(slot, &file_name, None, Some(&changes.tasks))?;
+        self.update_version(handle, version);
+        Some(task)
+    }
+
+    /// Hot reload all the changed assets, and record them into `changes`.
+    ///
+    /// If the sources are watched, only the reported changes are considered.
+    /// Otherwise, all the assets are checked.
+    pub fn hot_reload_all(&self, changes: &mut ChangeSet)
+    where
+        B::Output: 'static,
+    {
+        let handles = self.changed().unwrap_or_else(|| {
+            let mut list = Vec::new();
+            self.slots.for_each(|inner, slot| {
+                if !slot.sources.is_empty() {
+                    list.push(Handle {
+                        inner,
+                        version: slot.version,
+                    });
+                }
+            });
+            list
+        });
+        for mut handle in handles {
+            if let Some(task) = self.hot_reload(&mut handle) {
+                changes.add(handle, task);
+            }
+        }
+    }
+
+    /// Update the handle to a new version, along with the cached one,
+    /// so that loading the same path again produces the new version.
+    fn update_version(&self, handle: &mut Handle<B::Output>, version: Version) {
+        let mut paths = self.paths.lock().unwrap();
+        for cached in paths.values_mut() {
+            if cached.inner == handle.inner {
+                cached.version = version;
+            }
+        }
+        handle.version = version;
+    }
+
+    /// Start watching the sources of the assets for changes.
+    ///
+    /// The changes are reported by `changed`.
+    #[cfg(feature = "watch")]
+    pub fn watch(&self) -> Result<(), Error> {
+        let mut watcher = self.watcher.lock().unwrap();
+        if watcher.is_none() {
+            *watcher = Some(watch::Watcher::new()?);
+        }
+        Ok(())
+    }
+
+    /// Get the assets with changed sources, which are not reloaded yet.
+    ///
+    /// The changes stay queued until the assets are passed to `hot_reload`,
+    /// so they aren't lost if the caller isn't interested in some of them yet.
+    /// Returns `None` if the manager isn't watching the sources,
+    /// in which case any asset may have changed.
+    /// The returned handles are the current ones, and they are
+    /// meant to be compared against and passed to `hot_reload`.
+    pub fn changed(&self) -> Option<Vec<Handle<B::Output>>> {
+        #[cfg(feature = "watch")]
+        if let Some(ref mut watcher) = *self.watcher.lock().unwrap() {
+            self.slots.for_each(|handle, slot| {
+                let is_loaded = slot.load_task.as_ref().is_none_or(|task| task.is_done());
+                if is_loaded && !slot.sources.is_empty() {
+                    let paths = slot.sources.iter().map(|path| slot.base_path.join(path));
+                    watcher.track(handle, slot.version, paths);
+                }
+            });
+            watcher.collect();
+            return Some(
+                watcher
+                    .pending()
+                    .map(|inner| Handle {
+                        inner,
+                        version: self.slots[inner].version,
+                    })
+                    .collect(),
+            );
+        }
+        None
+    }
+
+    /// Get the status of the last (re-)load of an asset.
+    ///
+    /// Returns `None` for the assets produced by `load_cooked_inside_task`,
+    /// which aren't tracked.
+    pub fn status(&self, handle: Handle<B::Output>) -> Option<AssetStatus> {
+        let slot = &self.slots[handle.inner];
+        slot.tracker
+            .as_ref()
+            .map(|tracker| tracker.lock().unwrap().status())
+    }
+
+    /// Count the tracked assets in each stage of loading.
+    pub fn progress(&self) -> Progress {
+        let mut progress = Progress::default();
+        self.slots.for_each(|_, slot| {
+            if let Some(ref tracker) = slot.tracker {
+                progress.count(tracker.lock().unwrap().state());
+            }
+        });
+        progress
+    }
+
+    /// List the status of all the tracked assets.
+    pub fn list_status(&self, list: &mut Vec<AssetStatus>) {
+        self.slots.for_each(|_, slot| {
+            if let Some(ref tracker) = slot.tracker {
+                list.push(tracker.lock().unwrap().status());
+            }
+        });
+    }
+
+    /// List the entries of the cooked asset cache in the target.
+    ///
+    /// The target may be shared with the managers of other asset types,
+    /// in which case their entries are listed as well.
+    pub fn list_cache(&self) -> io::Result<Vec<CacheEntry>> {
+        list_cache(&self.target)
+    }
+
+    /// Remove the stale entries of the cooked asset cache in the target.
+    ///
+    /// See `prune_cache` for the details.
+    pub fn prune_cache(&self, max_age: Option<Duration>) -> io::Result<Vec<CacheEntry>> {
+        prune_cache(
+            &self.target,
+            self.cache_config.source_root.as_deref(),
+            max_age,
+        )
     }
 
     pub fn list_running_tasks(&self, list: &mut Vec<choir::RunningTask>) {
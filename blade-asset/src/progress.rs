@@ -0,0 +1,169 @@
+use std::{
+    ops,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+/// Stage of loading an asset.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum LoadState {
+    /// Waiting for the tasks to start.
+    Queued,
+    /// Cooking the source into the cached representation.
+    Cooking,
+    /// Producing the output out of the cooked data.
+    Serving,
+    Ready,
+    Failed,
+}
+
+/// Time spent by an asset in each stage of loading.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Timings {
+    pub queued: Duration,
+    pub cooking: Duration,
+    pub serving: Duration,
+}
+
+impl Timings {
+    pub fn total(&self) -> Duration {
+        self.queued + self.cooking + self.serving
+    }
+}
+
+/// Status of the last (re-)load of an asset.
+#[derive(Clone, Debug)]
+pub struct AssetStatus {
+    /// Path to the source of the asset.
+    pub path: PathBuf,
+    pub state: LoadState,
+    /// Time spent in each stage, including the current one.
+    pub timings: Timings,
+    /// Error of loading, if the state is `Failed`.
+    pub error: Option<super::Error>,
+}
+
+/// Number of assets in each stage of loading.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Progress {
+    pub queued: usize,
+    pub cooking: usize,
+    pub serving: usize,
+    pub ready: usize,
+    pub failed: usize,
+}
+
+impl Progress {
+    pub(super) fn count(&mut self, state: LoadState) {
+        match state {
+            LoadState::Queued => self.queued += 1,
+            LoadState::Cooking => self.cooking += 1,
+            LoadState::Serving => self.serving += 1,
+            LoadState::Ready => self.ready += 1,
+            LoadState::Failed => self.failed += 1,
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.queued + self.cooking + self.serving + self.ready + self.failed
+    }
+
+    /// Number of assets that are done loading, successfully or not.
+    pub fn finished(&self) -> usize {
+        self.ready + self.failed
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished() == self.total()
+    }
+
+    /// Fraction of the finished assets, in [0, 1] range.
+    pub fn fraction(&self) -> f32 {
+        match self.total() {
+            0 => 1.0,
+            total => self.finished() as f32 / total as f32,
+        }
+    }
+}
+
+impl ops::AddAssign for Progress {
+    fn add_assign(&mut self, other: Self) {
+        self.queued += other.queued;
+        self.cooking += other.cooking;
+        self.serving += other.serving;
+        self.ready += other.ready;
+        self.failed += other.failed;
+    }
+}
+
+impl ops::Add for Progress {
+    type Output = Self;
+    fn add(mut self, other: Self) -> Self {
+        self += other;
+        self
+    }
+}
+
+/// Tracker of the loading stages of an asset, shared with the loading tasks.
+pub(super) struct Tracker {
+    path: PathBuf,
+    state: LoadState,
+    phase_start: Instant,
+    timings: Timings,
+    error: Option<super::Error>,
+}
+
+impl Tracker {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            state: LoadState::Queued,
+            phase_start: Instant::now(),
+            timings: Timings::default(),
+            error: None,
+        }
+    }
+
+    pub fn state(&self) -> LoadState {
+        self.state
+    }
+
+    /// Start tracking a new load of the asset.
+    pub fn restart(&mut self) {
+        *self = Self::new(std::mem::take(&mut self.path));
+    }
+
+    fn account(&self, timings: &mut Timings) {
+        let elapsed = self.phase_start.elapsed();
+        match self.state {
+            LoadState::Queued => timings.queued += elapsed,
+            LoadState::Cooking => timings.cooking += elapsed,
+            LoadState::Serving => timings.serving += elapsed,
+            LoadState::Ready | LoadState::Failed => {}
+        }
+    }
+
+    pub fn enter(&mut self, state: LoadState) {
+        let mut timings = self.timings;
+        self.account(&mut timings);
+        self.timings = timings;
+        self.state = state;
+        self.phase_start = Instant::now();
+    }
+
+    pub fn fail(&mut self, error: super::Error) {
+        self.enter(LoadState::Failed);
+        self.error = Some(error);
+    }
+
+    pub fn status(&self) -> AssetStatus {
+        let mut timings = self.timings;
+        self.account(&mut timings);
+        AssetStatus {
+            path: self.path.clone(),
+            state: self.state,
+            timings,
+            error: self.error.clone(),
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs, hash,
+    path::{Path, PathBuf},
+    sync::mpsc,
+};
+
+/// Watcher of the asset source files.
+///
+/// It uses inotify on Linux, and the native notification API on other platforms.
+/// Since editors often save files by replacing them, the watching is done on
+/// the parent directories, and only the known files are reported.
+pub(super) struct Watcher<K> {
+    raw: notify::RecommendedWatcher,
+    receiver: mpsc::Receiver<PathBuf>,
+    directories: HashSet<PathBuf>,
+    files: HashMap<PathBuf, HashSet<K>>,
+    // Version of the asset at the time its sources were tracked.
+    tracked: HashMap<K, u32>,
+    // Assets with changed sources, which haven't been reloaded yet.
+    pending: HashSet<K>,
+}
+
+fn normalize(path: &Path) -> PathBuf {
+    // Only the directory is canonicalized, since the file itself may be missing.
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(file_name)) => fs::canonicalize(parent)
+            .unwrap_or_else(|_| parent.to_path_buf())
+            .join(file_name),
+        _ => path.to_path_buf(),
+    }
+}
+
+impl<K: Copy + Eq + hash::Hash> Watcher<K> {
+    pub fn new() -> Result<Self, super::Error> {
+        use notify::EventKind as Ek;
+
+        let (sender, receiver) = mpsc::channel();
+        let raw =
+            notify::recommended_watcher(
+                move |result: notify::Result<notify::Event>| match result {
+                    Ok(event) => match event.kind {
+                        Ek::Create(_) | Ek::Modify(_) | Ek::Remove(_) => {
+                            for path in event.paths {
+                                let _ = sender.send(path);
+                            }
+                        }
+                        Ek::Any | Ek::Access(_) | Ek::Other => {}
+                    },
+                    Err(e) => log::warn!("File watching error: {}", e),
+                },
+            )
+            .map_err(|e| super::Error::new(format!("Unable to create a file watcher: {}", e)))?;
+        Ok(Self {
+            raw,
+            receiver,
+            directories: HashSet::default(),
+            files: HashMap::default(),
+            tracked: HashMap::default(),
+            pending: HashSet::default(),
+        })
+    }
+
+    /// Start tracking the sources of an asset, unless they are
+    /// already tracked for this version of it.
+    pub fn track(&mut self, key: K, version: u32, sources: impl Iterator<Item = PathBuf>) {
+        use notify::Watcher as _;
+
+        match self.tracked.insert(key, version) {
+            Some(old) if old == version => return,
+            // The sources may have changed since the last version
+            Some(_) => self.forget_files(key),
+            None => {}
+        }
+        for source in sources {
+            let path = normalize(&source);
+            if let Some(dir) = path.parent() {
+                if !self.directories.contains(dir) {
+                    match self.raw.watch(dir, notify::RecursiveMode::NonRecursive) {
+                        Ok(()) => {
+                            log::debug!("Watching {}", dir.display());
+                            self.directories.insert(dir.to_path_buf());
+                        }
+                        Err(e) => log::warn!("Unable to watch {}: {}", dir.display(), e),
+                    }
+                }
+            }
+            self.files.entry(path).or_default().insert(key);
+        }
+    }
+
+    fn forget_files(&mut self, key: K) {
+        self.files.retain(|_, keys| {
+            keys.remove(&key);
+            !keys.is_empty()
+        });
+    }
+
+    /// Stop tracking the sources of a deleted asset.
+    pub fn untrack(&mut self, key: K) {
+        if self.tracked.remove(&key).is_some() {
+            self.forget_files(key);
+        }
+        self.pending.remove(&key);
+    }
+
+    /// Receive the file changes, and queue the affected assets.
+    pub fn collect(&mut self) {
+        for path in self.receiver.try_iter() {
+            if let Some(keys) = self.files.get(&normalize(&path)) {
+                log::info!("Detected a change in {}", path.display());
+                self.pending.extend(keys.iter().cloned());
+            }
+        }
+    }
+
+    /// Assets with the changed sources, which are not reloaded yet.
+    pub fn pending(&self) -> impl Iterator<Item = K> + '_ {
+        self.pending.iter().cloned()
+    }
+
+    /// Remove an asset from the queue, once it's reloaded.
+    pub fn acknowledge(&mut self, key: K) {
+        self.pending.remove(&key);
+    }
+}
@@ -1,15 +1,41 @@
 use std::{
-    fmt,
-    path::PathBuf,
+    fmt, fs, ops,
+    path::{Path, PathBuf},
     sync::{
         atomic::{AtomicBool, Ordering},
         Arc,
     },
 };
 
+/// Temporary directory of a test, removed at the end.
+///
+/// Tests run in parallel, so they shouldn't share the cooked targets.
+struct TestDir(PathBuf);
+impl TestDir {
+    fn new(name: &str) -> Self {
+        let path =
+            std::env::temp_dir().join(format!("blade-asset-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+}
+impl ops::Deref for TestDir {
+    type Target = Path;
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
 struct Baker {
     allow_cooking: AtomicBool,
 }
+const FAILING_META: u32 = 13;
 impl blade_asset::Baker for Baker {
     type Meta = u32;
     type Data<'a> = u32;
@@ -21,13 +47,88 @@ impl blade_asset::Baker for Baker {
         meta: u32,
         cooker: Arc<blade_asset::Cooker<Self>>,
         _exe_context: &choir::ExecutionContext,
-    ) {
+    ) -> Result<(), blade_asset::Error> {
         assert!(self.allow_cooking.load(Ordering::SeqCst));
-        let _ = cooker.add_dependency("README.md".as_ref());
+        let _ = cooker.add_dependency("README.md".as_ref())?;
         cooker.finish(meta);
+        Ok(())
     }
-    fn serve(&self, cooked: u32, _exe_context: &choir::ExecutionContext) -> usize {
-        cooked as usize
+    fn serve(
+        &self,
+        cooked: u32,
+        _exe_context: &choir::ExecutionContext,
+    ) -> Result<usize, blade_asset::Error> {
+        if cooked == FAILING_META {
+            return Err("Unlucky number".into());
+        }
+        Ok(cooked as usize)
+    }
+    fn delete(&self, _output: usize) {}
+}
+
+struct DependentBaker {
+    root: PathBuf,
+    base: Arc<blade_asset::AssetManager<Baker>>,
+}
+impl blade_asset::Baker for DependentBaker {
+    type Meta = u32;
+    type Data<'a> = u32;
+    type Output = blade_asset::StrongHandle<usize>;
+    fn cook(
+        &self,
+        _source: &[u8],
+        _extension: &str,
+        meta: u32,
+        cooker: Arc<blade_asset::Cooker<Self>>,
+        _exe_context: &choir::ExecutionContext,
+    ) -> Result<(), blade_asset::Error> {
+        let _ = cooker.add_dependency("README.md".as_ref())?;
+        cooker.finish(meta);
+        Ok(())
+    }
+    fn serve(
+        &self,
+        cooked: u32,
+        exe_context: &choir::ExecutionContext,
+    ) -> Result<Self::Output, blade_asset::Error> {
+        let (handle, task) = self.base.load_strong(self.root.join("Cargo.toml"), cooked);
+        exe_context.add_fork(task);
+        Ok(handle)
+    }
+    fn delete(&self, _output: Self::Output) {}
+    fn dependencies(&self, output: &Self::Output, list: &mut Vec<blade_asset::AssetId>) {
+        list.push(output.id());
+    }
+}
+
+/// Baker that depends on the file named by the source contents.
+#[cfg(feature = "watch")]
+struct IncludeBaker;
+#[cfg(feature = "watch")]
+impl blade_asset::Baker for IncludeBaker {
+    type Meta = u32;
+    type Data<'a> = u32;
+    type Output = usize;
+    fn cook(
+        &self,
+        source: &[u8],
+        _extension: &str,
+        meta: u32,
+        cooker: Arc<blade_asset::Cooker<Self>>,
+        _exe_context: &choir::ExecutionContext,
+    ) -> Result<(), blade_asset::Error> {
+        let name =
+            std::str::from_utf8(source).map_err(|e| blade_asset::Error::new(e.to_string()))?;
+        let _ = cooker.add_dependency(name.trim().as_ref())?;
+        cooker.finish(meta);
+        Ok(())
+    }
+    fn serve(
+        &self,
+        cooked: u32,
+        _exe_context: &choir::ExecutionContext,
+    ) -> Result<usize, blade_asset::Error> {
+        Ok(cooked as usize)
     }
     fn delete(&self, _output: usize) {}
 }
@@ -37,8 +138,9 @@ fn test_asset() {
     let choir = choir::Choir::new();
     let _w1 = choir.add_worker("main");
     let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let dir = TestDir::new("asset");
     let am = blade_asset::AssetManager::<Baker>::new(
-        &root.join("cooked"),
+        &dir.join("cooked"),
         &choir,
         Baker {
             allow_cooking: AtomicBool::new(true),
@@ -57,6 +159,611 @@ fn test_asset() {
     t.join();
 }
 
+#[test]
+fn test_failure() {
+    let choir = choir::Choir::new();
+    let _w1 = choir.add_worker("main");
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let dir = TestDir::new("failure");
+    let am = blade_asset::AssetManager::<Baker>::new(
+        &dir.join("cooked"),
+        &choir,
+        Baker {
+            allow_cooking: AtomicBool::new(true),
+        },
+    );
+
+    let (mut handle, task) = am.load(root.join("Cargo.toml"), FAILING_META);
+    task.join();
+    assert!(am.get(handle).is_none());
+    assert_eq!(am.get_error(handle).unwrap().message(), "Unlucky number");
+    // the sources haven't changed, so there is nothing to retry
+    assert!(am.hot_reload(&mut handle).is_none());
+
+    let (handle, task) = am.load(root.join("missing.txt"), 0);
+    task.join();
+    assert!(am.get(handle).is_none());
+    assert!(am.get_error(handle).is_some());
+}
+
+#[test]
+fn test_cooked_inside_task() {
+    let choir = choir::Choir::new();
+    let _w1 = choir.add_worker("main");
+    let dir = TestDir::new("cooked-inside-task");
+    let am = Arc::new(blade_asset::AssetManager::<Baker>::new(
+        &dir.join("cooked"),
+        &choir,
+        Baker {
+            allow_cooking: AtomicBool::new(false),
+        },
+    ));
+
+    let handles = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let am_task = Arc::clone(&am);
+    let handles_task = Arc::clone(&handles);
+    choir
+        .spawn("serve cooked")
+        .init(move |exe_context| {
+            let mut handles = handles_task.lock().unwrap();
+            for cooked in [5, FAILING_META] {
+                handles.push(am_task.load_cooked_inside_task(cooked, &exe_context));
+            }
+        })
+        .run()
+        .join();
+
+    let mut handles = handles.lock().unwrap();
+    assert_eq!(am.get(handles[0]), Some(&5));
+    assert!(am.get_error(handles[1]).is_some());
+    // there is no source file to reload or read from
+    for handle in handles.iter_mut() {
+        assert!(am.hot_reload(handle).is_none());
+        assert!(am.get_cooked_source(*handle).is_err());
+    }
+}
+
+#[test]
+fn test_content_invalidation() {
+    let choir = choir::Choir::new();
+    let _w1 = choir.add_worker("main");
+    let root = TestDir::new("content-invalidation");
+    let source_dir = root.join("content-sources");
+    let source = source_dir.join("content.txt");
+    fs::create_dir_all(&source_dir).unwrap();
+    // the baker depends on a README next to the source
+    fs::write(source_dir.join("README.md"), "readme").unwrap();
+    fs::write(&source, "content").unwrap();
+    let create = || {
+        blade_asset::AssetManager::<Baker>::with_cache_config(
+            &root.join("content"),
+            &choir,
+            Baker {
+                allow_cooking: AtomicBool::new(true),
+            },
+            blade_asset::CacheConfig {
+                invalidation: blade_asset::Invalidation::Content,
+                source_root: None,
+            },
+        )
+    };
+    let meta = 7;
+    let am = create();
+    let (handle, task) = am.load(&source, meta);
+    task.join();
+    assert_eq!(am[handle], meta as usize);
+
+    // rewriting the same contents only changes the modification time
+    fs::write(&source, "content").unwrap();
+    let am = create();
+    am.baker.allow_cooking.store(false, Ordering::SeqCst);
+    let (handle, task) = am.load(&source, meta);
+    task.join();
+    assert_eq!(am[handle], meta as usize);
+}
+
+#[test]
+fn test_source_root() {
+    let choir = choir::Choir::new();
+    let _w1 = choir.add_worker("main");
+    let root = TestDir::new("source-root");
+    let target = root.join("shared");
+    // the same sources in two different checkouts
+    let checkouts = [root.join("checkout-a"), root.join("checkout-b")];
+    for checkout in checkouts.iter() {
+        let source_dir = checkout.join("assets");
+        fs::create_dir_all(&source_dir).unwrap();
+        fs::write(source_dir.join("README.md"), "readme").unwrap();
+        fs::write(source_dir.join("content.txt"), "content").unwrap();
+    }
+    let create = |checkout: &PathBuf, allow_cooking| {
+        blade_asset::AssetManager::<Baker>::with_cache_config(
+            &target,
+            &choir,
+            Baker {
+                allow_cooking: AtomicBool::new(allow_cooking),
+            },
+            blade_asset::CacheConfig {
+                invalidation: blade_asset::Invalidation::Content,
+                source_root: Some(checkout.clone()),
+            },
+        )
+    };
+    let meta = 8;
+
+    let am = create(&checkouts[0], true);
+    let (handle, task) = am.load(checkouts[0].join("assets").join("content.txt"), meta);
+    task.join();
+    assert_eq!(am[handle], meta as usize);
+    let entries = am.list_cache().unwrap();
+    assert_eq!(entries.len(), 1);
+    // the cache refers to the source relative to the root
+    assert_eq!(
+        entries[0].source.as_deref(),
+        Some(Path::new("assets").join("content.txt").as_path())
+    );
+
+    // the other checkout finds the same cached asset
+    let am = create(&checkouts[1], false);
+    let (handle, task) = am.load(checkouts[1].join("assets").join("content.txt"), meta);
+    task.join();
+    assert_eq!(am[handle], meta as usize);
+    assert_eq!(am.list_cache().unwrap().len(), 1);
+}
+
+#[test]
+fn test_bake() {
+    let choir = choir::Choir::new();
+    let _w1 = choir.add_worker("main");
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let dir = TestDir::new("bake");
+    let target = dir.join("cooked");
+    let am = blade_asset::AssetManager::<Baker>::new(
+        &target,
+        &choir,
+        Baker {
+            allow_cooking: AtomicBool::new(true),
+        },
+    );
+    let path = root.join("Cargo.toml");
+    let task = am.bake(&path, 14).unwrap().unwrap();
+    assert!(task.join().is_ok());
+    assert!(am.bake(&path, 14).unwrap().is_none());
+    assert_eq!(am.read_cooked(&path, &14, |cooked| cooked).unwrap(), 14);
+    assert!(am.read_cooked(&path, &15, |cooked| cooked).is_err());
+    let (handle, task) = am.load(&path, 14);
+    task.join();
+    let (source, meta) = am.get_cooked_source(handle).unwrap();
+    assert_eq!((source.as_path(), meta), (path.as_path(), 14));
+    assert_eq!(am.read_cooked(&source, &meta, |cooked| cooked).unwrap(), 14);
+    // paths that don't name a file are rejected
+    assert!(am.bake(root.join(".."), 14).is_err());
+}
+
+#[test]
+fn test_archive() {
+    let choir = choir::Choir::new();
+    let _w1 = choir.add_worker("main");
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let dir = TestDir::new("archive");
+    let target = dir.join("cooked");
+    let am = blade_asset::AssetManager::<Baker>::new(
+        &target,
+        &choir,
+        Baker {
+            allow_cooking: AtomicBool::new(true),
+        },
+    );
+    let meta = 9;
+    let path = root.join("Cargo.toml");
+    let (_, task) = am.load(&path, meta);
+    task.join();
+
+    let archive_path = dir.join("test.pak");
+    let mut packer = blade_asset::Packer::new();
+    packer
+        .add_cooked_directory(&target, blade_asset::Compression::None)
+        .unwrap();
+    packer.write(&archive_path).unwrap();
+
+    let archive = Arc::new(blade_asset::Archive::open(&archive_path).unwrap());
+    let am = blade_asset::AssetManager::<Baker>::with_archive(
+        &archive,
+        &choir,
+        Baker {
+            allow_cooking: AtomicBool::new(false),
+        },
+        blade_asset::CacheConfig::default(),
+    );
+    let (handle, task) = am.load(&path, meta);
+    task.join();
+    assert_eq!(am[handle], meta as usize);
+}
+
+#[test]
+fn test_archive_corrupt() {
+    let dir = TestDir::new("archive-corrupt");
+    let path = dir.join("test.pak");
+    let mut packer = blade_asset::Packer::new();
+    packer
+        .add("a", vec![7; 100], blade_asset::Compression::None)
+        .unwrap();
+    packer.write(&path).unwrap();
+    let bytes = fs::read(&path).unwrap();
+    assert_eq!(
+        blade_asset::Archive::open(&path)
+            .unwrap()
+            .read("a")
+            .unwrap()
+            .unwrap(),
+        vec![7; 100]
+    );
+
+    // the header is followed by the name length, the name,
+    // the flags, the offset, the size, and the raw size
+    let name_len_offset = 16;
+    let size_offset = name_len_offset + 4 + 1 + 4 + 8;
+    let corruptions: [(&str, usize, &[u8]); 3] = [
+        ("entry count", 12, &u32::MAX.to_le_bytes()),
+        ("name length", name_len_offset, &u32::MAX.to_le_bytes()),
+        ("entry size", size_offset, &(1u64 << 40).to_le_bytes()),
+    ];
+    for (what, offset, value) in corruptions {
+        let mut corrupt = bytes.clone();
+        corrupt[offset..offset + value.len()].copy_from_slice(value);
+        fs::write(&path, &corrupt).unwrap();
+        assert!(blade_asset::Archive::open(&path).is_err(), "{}", what);
+    }
+    fs::write(&path, &bytes[..bytes.len() - 1]).unwrap();
+    assert!(blade_asset::Archive::open(&path).is_err(), "truncated");
+
+    #[cfg(feature = "compression")]
+    {
+        let mut packer = blade_asset::Packer::new();
+        packer
+            .add("a", vec![7; 100], blade_asset::Compression::Lz4)
+            .unwrap();
+        packer.write(&path).unwrap();
+        let mut corrupt = fs::read(&path).unwrap();
+        // the prepended size of the compressed data
+        let data_offset = size_offset + 16;
+        corrupt[data_offset..data_offset + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        fs::write(&path, &corrupt).unwrap();
+        let archive = blade_asset::Archive::open(&path).unwrap();
+        assert!(archive.read("a").is_err());
+    }
+}
+
+#[test]
+fn test_archive_source_root() {
+    let choir = choir::Choir::new();
+    let _w1 = choir.add_worker("main");
+    let root = TestDir::new("archive-source-root");
+    let target = root.join("archive-rooted");
+    let cooking_root = root.join("archive-sources");
+    let source_dir = cooking_root.join("assets");
+    fs::create_dir_all(&source_dir).unwrap();
+    fs::write(source_dir.join("README.md"), "readme").unwrap();
+    fs::write(source_dir.join("content.txt"), "content").unwrap();
+    let meta = 12;
+
+    let am = blade_asset::AssetManager::<Baker>::with_cache_config(
+        &target,
+        &choir,
+        Baker {
+            allow_cooking: AtomicBool::new(true),
+        },
+        blade_asset::CacheConfig {
+            invalidation: blade_asset::Invalidation::Content,
+            source_root: Some(cooking_root.clone()),
+        },
+    );
+    let (_, task) = am.load(source_dir.join("content.txt"), meta);
+    task.join();
+
+    let archive_path = root.join("rooted.pak");
+    let mut packer = blade_asset::Packer::new();
+    packer
+        .add_cooked_directory(&target, blade_asset::Compression::None)
+        .unwrap();
+    packer.write(&archive_path).unwrap();
+
+    // the application is installed elsewhere, without the sources
+    let install_root = root.join("archive-install");
+    let archive = Arc::new(blade_asset::Archive::open(&archive_path).unwrap());
+    let am = blade_asset::AssetManager::<Baker>::with_archive(
+        &archive,
+        &choir,
+        Baker {
+            allow_cooking: AtomicBool::new(false),
+        },
+        blade_asset::CacheConfig {
+            invalidation: blade_asset::Invalidation::Content,
+            source_root: Some(install_root.clone()),
+        },
+    );
+    let (handle, task) = am.load(install_root.join("assets").join("content.txt"), meta);
+    task.join();
+    assert_eq!(am[handle], meta as usize);
+}
+
+#[test]
+fn test_collect_unused() {
+    let choir = choir::Choir::new();
+    let _w1 = choir.add_worker("main");
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let dir = TestDir::new("collect-unused");
+    let am = blade_asset::AssetManager::<Baker>::new(
+        &dir.join("cooked"),
+        &choir,
+        Baker {
+            allow_cooking: AtomicBool::new(true),
+        },
+    );
+    let path = root.join("Cargo.toml");
+    let (weak, task) = am.load(&path, 3);
+    task.join();
+    let (strong, task) = am.load_strong(&path, 4);
+    task.join();
+    let other = strong.clone();
+    let plain = *other;
+    assert_eq!(am[plain], 4);
+
+    // the asset is alive while any of the strong handles are
+    drop(strong);
+    assert_eq!(am.collect_unused(), 0);
+    drop(other);
+    assert_eq!(am.collect_unused(), 1);
+    assert!(am.get(plain).is_none());
+    // assets that never had strong handles are not collected
+    assert_eq!(am[weak], 3);
+
+    // the path is loaded anew after being collected
+    let (strong, task) = am.load_strong(&path, 4);
+    task.join();
+    assert_eq!(am[*strong], 4);
+}
+
+#[test]
+fn test_cache_pruning() {
+    let choir = choir::Choir::new();
+    let _w1 = choir.add_worker("main");
+    let root = TestDir::new("cache-pruning");
+    let source_dir = root.join("pruning-sources");
+    let target = root.join("pruning");
+    fs::create_dir_all(&source_dir).unwrap();
+    fs::write(source_dir.join("README.md"), "readme").unwrap();
+    let kept = source_dir.join("kept.txt");
+    let removed = source_dir.join("removed.txt");
+    fs::write(&kept, "kept").unwrap();
+    fs::write(&removed, "removed").unwrap();
+
+    let am = blade_asset::AssetManager::<Baker>::new(
+        &target,
+        &choir,
+        Baker {
+            allow_cooking: AtomicBool::new(true),
+        },
+    );
+    for path in [&kept, &removed] {
+        let (_, task) = am.load(path, 11);
+        task.join();
+    }
+    let entries = am.list_cache().unwrap();
+    assert_eq!(entries.len(), 2);
+    assert!(entries
+        .iter()
+        .all(|entry| entry.meta.as_deref() == Some("11") && entry.size != 0));
+
+    // relative to the working directory, which can't be resolved without a root
+    let relative_dir = PathBuf::from(format!("pruning-sources-{}", std::process::id()));
+    fs::create_dir_all(&relative_dir).unwrap();
+    fs::write(relative_dir.join("README.md"), "readme").unwrap();
+    let relative = relative_dir.join("relative.txt");
+    fs::write(&relative, "relative").unwrap();
+    let (_, task) = am.load(&relative, 11);
+    task.join();
+    // unreadable headers, like of entries that are being written
+    fs::write(target.join("partial.txt-0.raw"), [0u8; 4]).unwrap();
+
+    fs::remove_file(&removed).unwrap();
+    fs::remove_dir_all(&relative_dir).unwrap();
+    let pruned = am.prune_cache(None).unwrap();
+    assert_eq!(pruned.len(), 1);
+    assert_eq!(pruned[0].source.as_ref(), Some(&removed));
+    assert_eq!(am.list_cache().unwrap().len(), 3);
+
+    // everything with a readable header is old enough
+    let pruned = am.prune_cache(Some(std::time::Duration::ZERO)).unwrap();
+    assert_eq!(pruned.len(), 2);
+    let entries = am.list_cache().unwrap();
+    assert_eq!(entries.len(), 1);
+    assert!(entries[0].source.is_none());
+}
+
+#[test]
+fn test_progress() {
+    let choir = choir::Choir::new();
+    let _w1 = choir.add_worker("main");
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let dir = TestDir::new("progress");
+    let am = blade_asset::AssetManager::<Baker>::new(
+        &dir.join("cooked"),
+        &choir,
+        Baker {
+            allow_cooking: AtomicBool::new(true),
+        },
+    );
+    let (good, task) = am.load(root.join("Cargo.toml"), 1);
+    task.join();
+    let (bad, task) = am.load(root.join("Cargo.toml"), FAILING_META);
+    task.join();
+
+    let status = am.status(good).unwrap();
+    assert_eq!(status.state, blade_asset::LoadState::Ready);
+    assert_eq!(status.path, root.join("Cargo.toml"));
+    let status = am.status(bad).unwrap();
+    assert_eq!(status.state, blade_asset::LoadState::Failed);
+    assert_eq!(status.error.unwrap().message(), "Unlucky number");
+
+    let progress = am.progress();
+    assert_eq!((progress.ready, progress.failed), (1, 1));
+    assert!(progress.is_finished());
+}
+
+#[test]
+fn test_dependencies() {
+    let choir = choir::Choir::new();
+    let _w1 = choir.add_worker("main");
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let dir = TestDir::new("dependencies");
+    let base = Arc::new(blade_asset::AssetManager::<Baker>::new(
+        &dir.join("cooked"),
+        &choir,
+        Baker {
+            allow_cooking: AtomicBool::new(true),
+        },
+    ));
+    let dependent = blade_asset::AssetManager::<DependentBaker>::new(
+        &dir.join("cooked"),
+        &choir,
+        DependentBaker {
+            root: root.clone(),
+            base: Arc::clone(&base),
+        },
+    );
+    let (mut handle, task) = dependent.load(root.join("README.md"), 21);
+    task.join();
+    let base_handle = *dependent[handle];
+    assert_eq!(base[base_handle], 21);
+
+    // nothing this asset depends on has changed
+    let mut changes = blade_asset::ChangeSet::default();
+    let (other, other_task) = base.load(root.join("Cargo.toml"), 22);
+    changes.add(other, other_task);
+    assert!(dependent
+        .hot_reload_dependent(&mut handle, &changes)
+        .is_none());
+
+    let (_, base_task) = base.load(root.join("Cargo.toml"), 21);
+    changes.add(base_handle, base_task);
+    let old_handle = handle;
+    let task = dependent
+        .hot_reload_dependent(&mut handle, &changes)
+        .unwrap()
+        .clone();
+    task.join();
+    assert_ne!(handle, old_handle);
+    assert_eq!(*dependent[handle], base_handle);
+}
+
+#[test]
+fn test_collect_dependencies() {
+    let choir = choir::Choir::new();
+    let _w1 = choir.add_worker("main");
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let dir = TestDir::new("collect-dependencies");
+    let base = Arc::new(blade_asset::AssetManager::<Baker>::new(
+        &dir.join("cooked"),
+        &choir,
+        Baker {
+            allow_cooking: AtomicBool::new(true),
+        },
+    ));
+    let dependent = blade_asset::AssetManager::<DependentBaker>::new(
+        &dir.join("cooked"),
+        &choir,
+        DependentBaker {
+            root: root.clone(),
+            base: Arc::clone(&base),
+        },
+    );
+    let (handle, task) = dependent.load_strong(root.join("README.md"), 23);
+    task.join();
+    let base_handle = *dependent[*handle];
+
+    // the dependency is alive for as long as the dependent asset is
+    drop(handle);
+    assert_eq!(base.collect_unused(), 0);
+    assert_eq!(base[base_handle], 23);
+    assert_eq!(dependent.collect_unused(), 1);
+    assert_eq!(base.collect_unused(), 1);
+    assert!(base.get(base_handle).is_none());
+}
+
+#[cfg(feature = "watch")]
+#[test]
+fn test_watch() {
+    use std::{thread, time::Duration};
+
+    let is_changed = |am: &blade_asset::AssetManager<IncludeBaker>, handle| {
+        am.changed().unwrap().contains(&handle)
+    };
+    // Wait until the change is reported, and then for the rest of its events.
+    let wait_for_change = |am: &blade_asset::AssetManager<IncludeBaker>, handle| {
+        for _ in 0..100 {
+            if is_changed(am, handle) {
+                thread::sleep(Duration::from_millis(100));
+                return true;
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+        false
+    };
+
+    let choir = choir::Choir::new();
+    let _w1 = choir.add_worker("main");
+    let root = TestDir::new("watch");
+    let source_dir = root.join("sources");
+    fs::create_dir_all(&source_dir).unwrap();
+    let source = source_dir.join("asset.txt");
+    let first = source_dir.join("first.txt");
+    let second = source_dir.join("second.txt");
+    fs::write(&first, "1").unwrap();
+    fs::write(&second, "2").unwrap();
+    fs::write(&source, "first.txt").unwrap();
+
+    let am =
+        blade_asset::AssetManager::<IncludeBaker>::new(&root.join("cooked"), &choir, IncludeBaker);
+    am.watch().unwrap();
+    let (mut handle, task) = am.load(&source, 1);
+    task.join();
+    assert_eq!(am.changed(), Some(Vec::new()));
+
+    // the change stays queued until the asset is reloaded
+    fs::write(&first, "1+").unwrap();
+    assert!(wait_for_change(&am, handle));
+    assert!(is_changed(&am, handle));
+    am.hot_reload(&mut handle).unwrap().join();
+    assert!(!is_changed(&am, handle));
+
+    // switch the dependency to another file
+    fs::write(&source, "second.txt").unwrap();
+    assert!(wait_for_change(&am, handle));
+    am.hot_reload(&mut handle).unwrap().join();
+    assert!(!is_changed(&am, handle));
+
+    // the old dependency is no longer watched
+    fs::write(&first, "1++").unwrap();
+    thread::sleep(Duration::from_millis(300));
+    assert!(!is_changed(&am, handle));
+    fs::write(&second, "2+").unwrap();
+    assert!(wait_for_change(&am, handle));
+    am.hot_reload(&mut handle).unwrap().join();
+    drop(am);
+
+    // the dependencies are known when loading from a warm cache
+    let am =
+        blade_asset::AssetManager::<IncludeBaker>::new(&root.join("cooked"), &choir, IncludeBaker);
+    am.watch().unwrap();
+    let (handle, task) = am.load(&source, 1);
+    task.join();
+    assert_eq!(am[handle], 1);
+    assert_eq!(am.changed(), Some(Vec::new()));
+    fs::write(&second, "2++").unwrap();
+    assert!(wait_for_change(&am, handle));
+}
+
 fn flat_roundtrip<F: blade_asset::Flat + PartialEq + fmt::Debug>(data: F) {
     let mut vec = vec![0u8; data.size()];
     unsafe { data.write(vec.as_mut_ptr()) };
@@ -69,4 +776,9 @@ fn test_flatten() {
     flat_roundtrip([0u32, 1u32, 2u32]);
     flat_roundtrip(&[2u32, 4u32, 6u32][..]);
     flat_roundtrip(vec![1u32, 2, 3]);
+    flat_roundtrip(vec![vec![1u8, 2, 3], vec![], vec![4]]);
+    flat_roundtrip(Some(5u64));
+    flat_roundtrip(None::<u32>);
+    flat_roundtrip("flat".to_string());
+    flat_roundtrip(vec![Some("a".to_string()), None]);
 }
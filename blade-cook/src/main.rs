@@ -0,0 +1,249 @@
+//! Cook the assets ahead of time, as listed in a manifest.
+//!
+//! Usage: blade-cook <manifest.ron> [--target <directory>] [--source-root <directory>] [--content-hash]
+//!
+//! Cooking doesn't need a GPU, so this can run on a headless machine.
+//! The textures referenced by the models are cooked as well.
+//!
+//! The manifest is a RON file with lists of textures, models, and shaders:
+//! ```ron
+//! (
+//!     textures: [
+//!         (path: "data/albedo.png", format: "Bc1UnormSrgb", generate_mips: true),
+//!     ],
+//!     models: [
+//!         (path: "data/scene.gltf", generate_tangents: true, front_face: Clockwise),
+//!     ],
+//!     shaders: ["blade-render/code/ray-trace.wgsl"],
+//! )
+//! ```
+
+use std::{
+    collections::HashSet,
+    env, fs,
+    path::PathBuf,
+    process,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+#[derive(serde::Deserialize)]
+struct TextureEntry {
+    path: PathBuf,
+    format: String,
+    #[serde(default)]
+    generate_mips: bool,
+    #[serde(default)]
+    y_flip: bool,
+}
+
+#[derive(serde::Deserialize)]
+struct ModelEntry {
+    path: PathBuf,
+    #[serde(default)]
+    generate_tangents: bool,
+    #[serde(default)]
+    front_face: blade_render::model::FrontFace,
+}
+
+#[derive(serde::Deserialize)]
+struct Manifest {
+    #[serde(default)]
+    textures: Vec<TextureEntry>,
+    #[serde(default)]
+    models: Vec<ModelEntry>,
+    #[serde(default)]
+    shaders: Vec<PathBuf>,
+}
+
+fn parse_texture_format(name: &str) -> Option<blade_graphics::TextureFormat> {
+    blade_render::texture::COOKED_FORMATS
+        .iter()
+        .cloned()
+        .find(|format| format!("{:?}", format) == name)
+}
+
+struct Job {
+    kind: &'static str,
+    path: PathBuf,
+    task: Result<Option<blade_asset::BakeTask>, blade_asset::Error>,
+}
+
+#[derive(Default)]
+struct Stats {
+    num_cooked: usize,
+    num_skipped: usize,
+    num_failed: usize,
+}
+
+impl Stats {
+    /// Wait for the jobs to finish, and report each of them.
+    fn finish(&mut self, jobs: Vec<Job>) {
+        for job in jobs {
+            let result = match job.task {
+                Ok(Some(task)) => task.join(),
+                Ok(None) => {
+                    println!("\t{} {}: up to date", job.kind, job.path.display());
+                    self.num_skipped += 1;
+                    continue;
+                }
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(duration) => {
+                    println!(
+                        "\t{} {}: cooked in {}",
+                        job.kind,
+                        job.path.display(),
+                        format_duration(duration)
+                    );
+                    self.num_cooked += 1;
+                }
+                Err(e) => {
+                    eprintln!("\t{} {}: {}", job.kind, job.path.display(), e);
+                    self.num_failed += 1;
+                }
+            }
+        }
+    }
+}
+
+const USAGE: &str = "Usage: blade-cook <manifest.ron> [--target <directory>] [--source-root <directory>] [--content-hash]";
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(1);
+}
+
+fn main() {
+    env_logger::init();
+
+    let mut manifest_path = None;
+    let mut target = PathBuf::from("asset-cache");
+    let mut cache_config = blade_asset::CacheConfig::default();
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--target" => target = PathBuf::from(args.next().unwrap_or_else(|| usage())),
+            "--source-root" => {
+                cache_config.source_root =
+                    Some(PathBuf::from(args.next().unwrap_or_else(|| usage())));
+            }
+            "--content-hash" => {
+                cache_config.invalidation = blade_asset::Invalidation::Content;
+            }
+            _ if manifest_path.is_none() => manifest_path = Some(PathBuf::from(arg)),
+            _ => usage(),
+        }
+    }
+    let manifest_path = manifest_path.unwrap_or_else(|| usage());
+
+    let manifest: Manifest = fs::read_to_string(&manifest_path)
+        .map_err(|e| e.to_string())
+        .and_then(|text| ron::de::from_str(&text).map_err(|e| e.to_string()))
+        .unwrap_or_else(|e| {
+            eprintln!("Unable to read {}: {}", manifest_path.display(), e);
+            process::exit(1);
+        });
+
+    let num_workers = std::thread::available_parallelism().map_or(1, |n| n.get());
+    log::info!("Initializing Choir with {} workers", num_workers);
+    let choir = Arc::new(choir::Choir::new());
+    let _workers = (0..num_workers)
+        .map(|i| choir.add_worker(&format!("Worker-{}", i)))
+        .collect::<Vec<_>>();
+    let asset_hub = blade_render::AssetHub::offline(&target, &choir, cache_config);
+
+    let start = Instant::now();
+    let mut jobs = Vec::new();
+    let mut stats = Stats::default();
+    let mut visited_textures = HashSet::new();
+    for entry in manifest.textures {
+        let format = match parse_texture_format(&entry.format) {
+            Some(format) => format,
+            None => {
+                eprintln!(
+                    "Unsupported texture format '{}' for {}",
+                    entry.format,
+                    entry.path.display()
+                );
+                stats.num_failed += 1;
+                continue;
+            }
+        };
+        let meta = blade_render::texture::Meta {
+            format,
+            generate_mips: entry.generate_mips,
+            y_flip: entry.y_flip,
+        };
+        visited_textures.insert((entry.path.clone(), meta.clone()));
+        jobs.push(Job {
+            kind: "texture",
+            task: asset_hub.textures.bake(&entry.path, meta),
+            path: entry.path,
+        });
+    }
+    let mut models = Vec::new();
+    for entry in manifest.models {
+        let meta = blade_render::model::Meta {
+            generate_tangents: entry.generate_tangents,
+            front_face: entry.front_face,
+        };
+        jobs.push(Job {
+            kind: "model",
+            task: asset_hub.models.bake(&entry.path, meta.clone()),
+            path: entry.path.clone(),
+        });
+        models.push((entry.path, meta));
+    }
+    for path in manifest.shaders {
+        jobs.push(Job {
+            kind: "shader",
+            task: asset_hub.shaders.bake(&path, blade_render::shader::Meta),
+            path,
+        });
+    }
+
+    stats.finish(jobs);
+
+    // The models refer to their textures by path, which are loaded separately.
+    let mut texture_jobs = Vec::new();
+    for (path, meta) in models {
+        let textures = match asset_hub
+            .models
+            .read_cooked(&path, &meta, |model| model.texture_paths())
+        {
+            Ok(textures) => textures,
+            // The failure has already been reported
+            Err(_) => continue,
+        };
+        for (texture_path, texture_meta) in textures {
+            let texture_path = PathBuf::from(texture_path);
+            if !visited_textures.insert((texture_path.clone(), texture_meta.clone())) {
+                continue;
+            }
+            texture_jobs.push(Job {
+                kind: "model texture",
+                task: asset_hub.textures.bake(&texture_path, texture_meta),
+                path: texture_path,
+            });
+        }
+    }
+    stats.finish(texture_jobs);
+
+    println!(
+        "Cooked {} assets into {} in {} ({} up to date, {} failed)",
+        stats.num_cooked,
+        target.display(),
+        format_duration(start.elapsed()),
+        stats.num_skipped,
+        stats.num_failed,
+    );
+    if stats.num_failed != 0 {
+        process::exit(1);
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    format!("{:.2}s", duration.as_secs_f32())
+}
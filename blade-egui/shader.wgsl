@@ -50,9 +50,11 @@ var r_sampler: sampler;
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    //Note: we always assume rendering to linear color space,
-    // but Egui wants to blend in gamma space, see
+    //Note: Egui wants to blend in gamma space, see
     // https://github.com/emilk/egui/pull/2071
     let blended = in.color * textureSample(r_texture, r_sampler, in.tex_coord);
-    return vec4f(linear_from_gamma(blended.xyz), blended.a);
+    if (r_uniforms.convert_to_linear > 0.0) {
+        return vec4f(linear_from_gamma(blended.xyz), blended.a);
+    }
+    return blended;
 }
@@ -13,6 +13,12 @@
     clippy::pattern_type_mismatch,
 )]
 
+#[cfg(feature = "winit")]
+mod window;
+
+#[cfg(feature = "winit")]
+pub use window::GuiWindow;
+
 const SHADER_SOURCE: &'static str = include_str!("../shader.wgsl");
 
 use blade_util::{BufferBelt, BufferBeltDescriptor};
@@ -20,13 +26,15 @@ use std::{
     collections::hash_map::{Entry, HashMap},
     mem::size_of,
     ptr,
+    sync::Arc,
 };
 
 #[repr(C)]
 #[derive(Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)]
 struct Uniforms {
     screen_size: [f32; 2],
-    padding: [f32; 2],
+    convert_to_linear: f32,
+    padding: f32,
 }
 
 #[derive(blade_macros::ShaderData)]
@@ -53,6 +61,120 @@ impl ScreenDescriptor {
         let logical_height = self.physical_size.1 as f32 / self.scale_factor;
         (logical_width, logical_height)
     }
+
+    /// Convert a clip rectangle in points into a scissor rectangle in pixels.
+    /// Returns `None` if the result is empty.
+    fn clip_to_scissor(&self, clip_rect: &egui::Rect) -> Option<blade_graphics::ScissorRect> {
+        // Make sure clip rect can fit within an `u32`.
+        let clip_min_x = (self.scale_factor * clip_rect.min.x)
+            .clamp(0.0, self.physical_size.0 as f32)
+            .trunc() as i32;
+        let clip_min_y = (self.scale_factor * clip_rect.min.y)
+            .clamp(0.0, self.physical_size.1 as f32)
+            .trunc() as i32;
+        let clip_max_x = (self.scale_factor * clip_rect.max.x)
+            .clamp(0.0, self.physical_size.0 as f32)
+            .ceil() as i32;
+        let clip_max_y = (self.scale_factor * clip_rect.max.y)
+            .clamp(0.0, self.physical_size.1 as f32)
+            .ceil() as i32;
+
+        if clip_max_x <= clip_min_x || clip_max_y <= clip_min_y {
+            return None;
+        }
+
+        Some(blade_graphics::ScissorRect {
+            x: clip_min_x,
+            y: clip_min_y,
+            w: (clip_max_x - clip_min_x) as u32,
+            h: (clip_max_y - clip_min_y) as u32,
+        })
+    }
+
+    fn full_viewport(&self) -> blade_graphics::Viewport {
+        blade_graphics::Viewport {
+            x: 0.0,
+            y: 0.0,
+            w: self.physical_size.0 as f32,
+            h: self.physical_size.1 as f32,
+            depth: 0.0..1.0,
+        }
+    }
+}
+
+/// Information about the region of a paint callback.
+#[derive(Debug)]
+pub struct PaintCallbackInfo {
+    /// Viewport of the callback rectangle, in pixels.
+    /// It's already set on the render pass.
+    pub viewport: blade_graphics::Viewport,
+    /// Clip rectangle, in pixels. It's already set on the render pass.
+    pub clip_rect: blade_graphics::ScissorRect,
+    /// Number of pixels per egui point.
+    pub pixels_per_point: f32,
+    /// Size of the whole render target, in pixels.
+    pub screen_size: (u32, u32),
+}
+
+/// Custom rendering inside of an egui region.
+///
+/// The render pass has to be compatible with the pipelines used by
+/// the callback, which is the same color target as for the `GuiPainter`.
+pub trait GuiCallback: Send + Sync {
+    /// Record the rendering commands into the pass.
+    ///
+    /// The state of the pass is restored for egui afterwards.
+    fn paint(&self, info: &PaintCallbackInfo, pass: &mut blade_graphics::RenderCommandEncoder);
+}
+
+/// Create an egui paint callback for custom rendering within the `rect`,
+/// to be added with `egui::Painter::add`.
+pub fn paint_callback(
+    rect: egui::Rect,
+    callback: impl GuiCallback + 'static,
+) -> egui::PaintCallback {
+    let boxed: Box<dyn GuiCallback> = Box::new(callback);
+    egui::PaintCallback {
+        rect,
+        callback: Arc::new(boxed),
+    }
+}
+
+fn paint_user_callback(
+    pass: &mut blade_graphics::RenderCommandEncoder,
+    callback: &egui::PaintCallback,
+    clip_rect: &egui::Rect,
+    sd: &ScreenDescriptor,
+) {
+    let user = match callback.callback.downcast_ref::<Box<dyn GuiCallback>>() {
+        Some(user) => user,
+        None => return,
+    };
+    let scissor = match sd.clip_to_scissor(clip_rect) {
+        Some(scissor) => scissor,
+        None => return,
+    };
+    let viewport = blade_graphics::Viewport {
+        x: sd.scale_factor * callback.rect.min.x,
+        y: sd.scale_factor * callback.rect.min.y,
+        w: sd.scale_factor * callback.rect.width(),
+        h: sd.scale_factor * callback.rect.height(),
+        depth: 0.0..1.0,
+    };
+    if viewport.w <= 0.0 || viewport.h <= 0.0 {
+        return;
+    }
+
+    pass.set_viewport(&viewport);
+    pass.set_scissor_rect(&scissor);
+    let info = PaintCallbackInfo {
+        viewport,
+        clip_rect: scissor,
+        pixels_per_point: sd.scale_factor,
+        screen_size: sd.physical_size,
+    };
+    user.paint(&info, pass);
+    pass.set_viewport(&sd.full_viewport());
 }
 
 struct GuiTexture {
@@ -132,25 +254,89 @@ impl GuiTexture {
     }
 }
 
+/// Texture owned by the user, registered for use by egui.
+#[derive(Clone, Copy)]
+struct NativeTexture {
+    view: blade_graphics::TextureView,
+    sampler: blade_graphics::Sampler,
+}
+
+/// Render target of the GUI.
+#[derive(Clone, Copy, Debug, Hash, Eq, PartialEq)]
+pub struct GuiTarget {
+    pub format: blade_graphics::TextureFormat,
+    /// The color space that output colors are expected to be in,
+    /// similar to `SurfaceConfig::color_space`.
+    ///
+    /// For `Linear`, the colors are converted from the gamma space of egui,
+    /// which is what HDR targets like `Rgba16Float` and `*Srgb` formats need.
+    /// For `Srgb`, the colors are written as is.
+    pub color_space: blade_graphics::ColorSpace,
+}
+
+fn create_pipeline(
+    shader: &blade_graphics::Shader,
+    format: blade_graphics::TextureFormat,
+    context: &blade_graphics::Context,
+) -> blade_graphics::RenderPipeline {
+    let globals_layout = <Globals as blade_graphics::ShaderData>::layout();
+    let locals_layout = <Locals as blade_graphics::ShaderData>::layout();
+    context.create_render_pipeline(blade_graphics::RenderPipelineDesc {
+        name: "gui",
+        data_layouts: &[&globals_layout, &locals_layout],
+        vertex: shader.at("vs_main"),
+        vertex_fetches: &[],
+        primitive: blade_graphics::PrimitiveState {
+            topology: blade_graphics::PrimitiveTopology::TriangleList,
+            ..Default::default()
+        },
+        depth_stencil: None, //TODO?
+        fragment: Some(shader.at("fs_main")),
+        color_targets: &[blade_graphics::ColorTargetState {
+            format,
+            blend: Some(blade_graphics::BlendState {
+                color: blade_graphics::BlendComponent {
+                    src_factor: blade_graphics::BlendFactor::One,
+                    dst_factor: blade_graphics::BlendFactor::OneMinusSrcAlpha,
+                    operation: blade_graphics::BlendOperation::Add,
+                },
+                alpha: blade_graphics::BlendComponent {
+                    src_factor: blade_graphics::BlendFactor::OneMinusDstAlpha,
+                    dst_factor: blade_graphics::BlendFactor::One,
+                    operation: blade_graphics::BlendOperation::Add,
+                },
+            }),
+            write_mask: blade_graphics::ColorWrites::all(),
+        }],
+        multisample_state: Default::default(),
+    })
+}
+
 //TODO: scissor test
 
 /// GUI painter based on egui.
 ///
 /// It can render egui primitives into a render pass.
 pub struct GuiPainter {
-    pipeline: blade_graphics::RenderPipeline,
+    shader: blade_graphics::Shader,
+    pipelines: HashMap<blade_graphics::TextureFormat, blade_graphics::RenderPipeline>,
+    default_target: GuiTarget,
     //TODO: find a better way to allocate temporary buffers.
     belt: BufferBelt,
     textures: HashMap<egui::TextureId, GuiTexture>,
     //TODO: this could also look better
     textures_dropped: Vec<GuiTexture>,
     textures_to_delete: Vec<(GuiTexture, blade_graphics::SyncPoint)>,
+    native_textures: HashMap<u64, NativeTexture>,
+    next_native_texture_id: u64,
 }
 
 impl GuiPainter {
     /// Destroy the contents of the painter.
     pub fn destroy(&mut self, context: &blade_graphics::Context) {
-        context.destroy_render_pipeline(&mut self.pipeline);
+        for (_, mut pipeline) in self.pipelines.drain() {
+            context.destroy_render_pipeline(&mut pipeline);
+        }
         self.belt.destroy(context);
         for (_, gui_texture) in self.textures.drain() {
             gui_texture.delete(context);
@@ -167,55 +353,112 @@ impl GuiPainter {
     ///
     /// It supports renderpasses with only a color attachment,
     /// and this attachment format must be The `output_format`.
+    /// The output is expected in linear color space.
     #[profiling::function]
     pub fn new(info: blade_graphics::SurfaceInfo, context: &blade_graphics::Context) -> Self {
+        Self::with_target(
+            GuiTarget {
+                format: info.format,
+                color_space: blade_graphics::ColorSpace::Linear,
+            },
+            context,
+        )
+    }
+
+    /// Create a new painter with a given GPU context,
+    /// using `target` for [`GuiPainter::paint`].
+    ///
+    /// Other targets can be painted into with [`GuiPainter::paint_to`].
+    #[profiling::function]
+    pub fn with_target(target: GuiTarget, context: &blade_graphics::Context) -> Self {
         let shader = context.create_shader(blade_graphics::ShaderDesc {
             source: SHADER_SOURCE,
         });
-        let globals_layout = <Globals as blade_graphics::ShaderData>::layout();
-        let locals_layout = <Locals as blade_graphics::ShaderData>::layout();
-        let pipeline = context.create_render_pipeline(blade_graphics::RenderPipelineDesc {
-            name: "gui",
-            data_layouts: &[&globals_layout, &locals_layout],
-            vertex: shader.at("vs_main"),
-            vertex_fetches: &[],
-            primitive: blade_graphics::PrimitiveState {
-                topology: blade_graphics::PrimitiveTopology::TriangleList,
-                ..Default::default()
-            },
-            depth_stencil: None, //TODO?
-            fragment: Some(shader.at("fs_main")),
-            color_targets: &[blade_graphics::ColorTargetState {
-                format: info.format,
-                blend: Some(blade_graphics::BlendState {
-                    color: blade_graphics::BlendComponent {
-                        src_factor: blade_graphics::BlendFactor::One,
-                        dst_factor: blade_graphics::BlendFactor::OneMinusSrcAlpha,
-                        operation: blade_graphics::BlendOperation::Add,
-                    },
-                    alpha: blade_graphics::BlendComponent {
-                        src_factor: blade_graphics::BlendFactor::OneMinusDstAlpha,
-                        dst_factor: blade_graphics::BlendFactor::One,
-                        operation: blade_graphics::BlendOperation::Add,
-                    },
-                }),
-                write_mask: blade_graphics::ColorWrites::all(),
-            }],
-            multisample_state: Default::default(),
-        });
+        let mut pipelines = HashMap::default();
+        pipelines.insert(
+            target.format,
+            create_pipeline(&shader, target.format, context),
+        );
 
         let belt = BufferBelt::new(BufferBeltDescriptor {
             memory: blade_graphics::Memory::Shared,
             min_chunk_size: 0x1000,
             alignment: blade_graphics::limits::STORAGE_BUFFER_ALIGNMENT,
+            ..Default::default()
         });
 
         Self {
-            pipeline,
+            shader,
+            pipelines,
+            default_target: target,
             belt,
             textures: Default::default(),
             textures_dropped: Vec::new(),
             textures_to_delete: Vec::new(),
+            native_textures: HashMap::default(),
+            next_native_texture_id: 0,
+        }
+    }
+
+    /// Register a texture owned by the user, so that it can be displayed by egui,
+    /// for example with `egui::Image`.
+    ///
+    /// The texture view and the sampler have to stay alive for as long as
+    /// they are used by the submitted GPU work.
+    pub fn register_native_texture(
+        &mut self,
+        view: blade_graphics::TextureView,
+        sampler: blade_graphics::Sampler,
+    ) -> egui::TextureId {
+        let id = self.next_native_texture_id;
+        self.next_native_texture_id += 1;
+        self.native_textures
+            .insert(id, NativeTexture { view, sampler });
+        egui::TextureId::User(id)
+    }
+
+    /// Replace the view and the sampler of a registered user texture,
+    /// for example after the texture got re-created with a new size.
+    ///
+    /// Returns false if the texture is not registered.
+    pub fn update_native_texture(
+        &mut self,
+        id: egui::TextureId,
+        view: blade_graphics::TextureView,
+        sampler: blade_graphics::Sampler,
+    ) -> bool {
+        let native = match id {
+            egui::TextureId::User(u) => self.native_textures.get_mut(&u),
+            egui::TextureId::Managed(_) => None,
+        };
+        match native {
+            Some(native) => {
+                *native = NativeTexture { view, sampler };
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Stop displaying a registered user texture.
+    ///
+    /// The texture itself is not destroyed, since it's owned by the user.
+    /// Returns false if the texture is not registered.
+    pub fn free_native_texture(&mut self, id: egui::TextureId) -> bool {
+        let native = match id {
+            egui::TextureId::User(u) => self.native_textures.remove(&u),
+            egui::TextureId::Managed(_) => None,
+        };
+        native.is_some()
+    }
+
+    fn find_texture(&self, id: egui::TextureId) -> Option<NativeTexture> {
+        match id {
+            egui::TextureId::User(u) => self.native_textures.get(&u).copied(),
+            egui::TextureId::Managed(_) => self.textures.get(&id).map(|texture| NativeTexture {
+                view: texture.view,
+                sampler: texture.sampler,
+            }),
         }
     }
 
@@ -323,9 +566,12 @@ impl GuiPainter {
         self.triage_deletions(context);
     }
 
-    /// Render the set of clipped primitives into a render pass.
+    /// Render the set of clipped primitives into a render pass
+    /// of the default target of the painter.
     /// The `sd` must contain dimensions of the render target.
-    #[profiling::function]
+    ///
+    /// Paint callbacks created with [`paint_callback`] are invoked in order
+    /// with the primitives, other callbacks are ignored.
     pub fn paint(
         &mut self,
         pass: &mut blade_graphics::RenderCommandEncoder,
@@ -333,68 +579,92 @@ impl GuiPainter {
         sd: &ScreenDescriptor,
         context: &blade_graphics::Context,
     ) {
+        let target = self.default_target;
+        self.paint_to(pass, paint_jobs, sd, &target, context);
+    }
+
+    /// Render the set of clipped primitives into a render pass of any target,
+    /// such as an offscreen texture or an HDR surface.
+    /// The `sd` must contain dimensions of the render target.
+    #[profiling::function]
+    pub fn paint_to(
+        &mut self,
+        pass: &mut blade_graphics::RenderCommandEncoder,
+        paint_jobs: &[egui::epaint::ClippedPrimitive],
+        sd: &ScreenDescriptor,
+        target: &GuiTarget,
+        context: &blade_graphics::Context,
+    ) {
+        if !self.pipelines.contains_key(&target.format) {
+            let pipeline = create_pipeline(&self.shader, target.format, context);
+            self.pipelines.insert(target.format, pipeline);
+        }
+        let pipeline = &self.pipelines[&target.format];
+
         let logical_size = sd.logical_size();
-        let mut pc = pass.with(&self.pipeline);
-        pc.bind(
-            0,
-            &Globals {
-                r_uniforms: Uniforms {
-                    screen_size: [logical_size.0, logical_size.1],
-                    padding: [0.0; 2],
+        let globals = Globals {
+            r_uniforms: Uniforms {
+                screen_size: [logical_size.0, logical_size.1],
+                convert_to_linear: match target.color_space {
+                    blade_graphics::ColorSpace::Linear => 1.0,
+                    blade_graphics::ColorSpace::Srgb => 0.0,
                 },
+                padding: 0.0,
             },
-        );
-
-        for clipped_prim in paint_jobs {
-            let clip_rect = &clipped_prim.clip_rect;
-
-            // Make sure clip rect can fit within an `u32`.
-            let clip_min_x = (sd.scale_factor * clip_rect.min.x)
-                .clamp(0.0, sd.physical_size.0 as f32)
-                .trunc() as i32;
-            let clip_min_y = (sd.scale_factor * clip_rect.min.y)
-                .clamp(0.0, sd.physical_size.1 as f32)
-                .trunc() as i32;
-            let clip_max_x = (sd.scale_factor * clip_rect.max.x)
-                .clamp(0.0, sd.physical_size.0 as f32)
-                .ceil() as i32;
-            let clip_max_y = (sd.scale_factor * clip_rect.max.y)
-                .clamp(0.0, sd.physical_size.1 as f32)
-                .ceil() as i32;
-
-            if clip_max_x <= clip_min_x || clip_max_y == clip_min_y {
-                continue;
+        };
+
+        let mut prims = paint_jobs.iter().peekable();
+        while prims.peek().is_some() {
+            // Draw all the meshes up to the next callback with our pipeline.
+            {
+                let mut pc = pass.with(pipeline);
+                pc.bind(0, &globals);
+
+                while let Some(clipped_prim) =
+                    prims.next_if(|cp| matches!(cp.primitive, egui::epaint::Primitive::Mesh(_)))
+                {
+                    let scissor = match sd.clip_to_scissor(&clipped_prim.clip_rect) {
+                        Some(scissor) => scissor,
+                        None => continue,
+                    };
+                    pc.set_scissor_rect(&scissor);
+
+                    if let egui::epaint::Primitive::Mesh(ref mesh) = clipped_prim.primitive {
+                        let texture = match self.find_texture(mesh.texture_id) {
+                            Some(texture) => texture,
+                            None => {
+                                log::warn!("Texture {:?} is not registered", mesh.texture_id);
+                                continue;
+                            }
+                        };
+                        let index_buf = self.belt.alloc_pod(&mesh.indices, context);
+                        let vertex_buf = self.belt.alloc_pod(&mesh.vertices, context);
+
+                        pc.bind(
+                            1,
+                            &Locals {
+                                r_vertex_data: vertex_buf,
+                                r_texture: texture.view,
+                                r_sampler: texture.sampler,
+                            },
+                        );
+
+                        pc.draw_indexed(
+                            index_buf,
+                            blade_graphics::IndexType::U32,
+                            mesh.indices.len() as u32,
+                            0,
+                            0,
+                            1,
+                        );
+                    }
+                }
             }
 
-            pc.set_scissor_rect(&blade_graphics::ScissorRect {
-                x: clip_min_x,
-                y: clip_min_y,
-                w: (clip_max_x - clip_min_x) as u32,
-                h: (clip_max_y - clip_min_y) as u32,
-            });
-
-            if let egui::epaint::Primitive::Mesh(ref mesh) = clipped_prim.primitive {
-                let texture = self.textures.get(&mesh.texture_id).unwrap();
-                let index_buf = self.belt.alloc_pod(&mesh.indices, context);
-                let vertex_buf = self.belt.alloc_pod(&mesh.vertices, context);
-
-                pc.bind(
-                    1,
-                    &Locals {
-                        r_vertex_data: vertex_buf,
-                        r_texture: texture.view,
-                        r_sampler: texture.sampler,
-                    },
-                );
-
-                pc.draw_indexed(
-                    index_buf,
-                    blade_graphics::IndexType::U32,
-                    mesh.indices.len() as u32,
-                    0,
-                    0,
-                    1,
-                );
+            if let Some(clipped_prim) = prims.next() {
+                if let egui::epaint::Primitive::Callback(ref callback) = clipped_prim.primitive {
+                    paint_user_callback(pass, callback, &clipped_prim.clip_rect, sd);
+                }
             }
         }
     }
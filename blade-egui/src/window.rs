@@ -0,0 +1,218 @@
+use std::time::Duration;
+
+/// UI of a frame, ready to be painted.
+struct FrameOutput {
+    primitives: Vec<egui::ClippedPrimitive>,
+    textures_delta: egui::TexturesDelta,
+    screen_desc: super::ScreenDescriptor,
+}
+
+/// egui attached to a winit window.
+///
+/// It forwards the window input into egui, handles the platform output,
+/// such as the clipboard and the cursor icon, and paints the UI.
+///
+/// The UI can be painted by [`GuiWindow::render_frame`], which records,
+/// submits, and presents the whole frame, rendering one frame at a time.
+/// Alternatively, apps with their own command encoders can call
+/// `update_textures`, `paint`, and `after_submit` directly.
+pub struct GuiWindow {
+    state: egui_winit::State,
+    viewport_id: egui::ViewportId,
+    painter: super::GuiPainter,
+    output: Option<FrameOutput>,
+    /// Only used by `render_frame`, so it's created on demand.
+    command_encoder: Option<blade_graphics::CommandEncoder>,
+    prev_sync_point: Option<blade_graphics::SyncPoint>,
+}
+
+impl GuiWindow {
+    /// Create the GUI for a window, which surface is described by `surface_info`.
+    pub fn new(
+        window: &winit::window::Window,
+        surface_info: blade_graphics::SurfaceInfo,
+        context: &blade_graphics::Context,
+    ) -> Self {
+        let egui_ctx = egui::Context::default();
+        let viewport_id = egui_ctx.viewport_id();
+        let state = egui_winit::State::new(egui_ctx, viewport_id, window, None, None, None);
+        Self {
+            state,
+            viewport_id,
+            painter: super::GuiPainter::new(surface_info, context),
+            output: None,
+            command_encoder: None,
+            prev_sync_point: None,
+        }
+    }
+
+    /// Destroy the contents of the GUI.
+    pub fn destroy(&mut self, context: &blade_graphics::Context) {
+        if let Some(sp) = self.prev_sync_point.take() {
+            context.wait_for(&sp, !0);
+        }
+        if let Some(mut command_encoder) = self.command_encoder.take() {
+            context.destroy_command_encoder(&mut command_encoder);
+        }
+        self.painter.destroy(context);
+    }
+
+    pub fn egui_ctx(&self) -> &egui::Context {
+        self.state.egui_ctx()
+    }
+
+    /// Access the painter, for example to register native textures.
+    pub fn painter_mut(&mut self) -> &mut super::GuiPainter {
+        &mut self.painter
+    }
+
+    /// Forward a window event into egui.
+    ///
+    /// The event should not be processed further if it's consumed.
+    pub fn on_window_event(
+        &mut self,
+        window: &winit::window::Window,
+        event: &winit::event::WindowEvent,
+    ) -> egui_winit::EventResponse {
+        self.state.on_window_event(window, event)
+    }
+
+    /// Run the UI logic for a new frame and tessellate the result.
+    ///
+    /// Returns the delay after which the UI needs to be repainted.
+    #[profiling::function]
+    pub fn run(
+        &mut self,
+        window: &winit::window::Window,
+        run_ui: impl FnMut(&egui::Context),
+    ) -> Duration {
+        let raw_input = self.state.take_egui_input(window);
+        let egui_output = self.state.egui_ctx().run(raw_input, run_ui);
+        self.state
+            .handle_platform_output(window, egui_output.platform_output);
+        let repaint_delay = egui_output
+            .viewport_output
+            .get(&self.viewport_id)
+            .map_or(Duration::MAX, |output| output.repaint_delay);
+
+        let pixels_per_point = egui_winit::pixels_per_point(self.state.egui_ctx(), window);
+        let primitives = self
+            .state
+            .egui_ctx()
+            .tessellate(egui_output.shapes, pixels_per_point);
+        let window_size = window.inner_size();
+        let screen_desc = super::ScreenDescriptor {
+            physical_size: (window_size.width, window_size.height),
+            scale_factor: pixels_per_point,
+        };
+
+        // Texture updates of a frame that was never painted still need to be applied.
+        let mut textures_delta = match self.output.take() {
+            Some(output) => output.textures_delta,
+            None => egui::TexturesDelta::default(),
+        };
+        textures_delta.append(egui_output.textures_delta);
+        self.output = Some(FrameOutput {
+            primitives,
+            textures_delta,
+            screen_desc,
+        });
+        repaint_delay
+    }
+
+    /// Dimensions of the last frame produced by [`GuiWindow::run`].
+    pub fn screen_descriptor(&self) -> Option<&super::ScreenDescriptor> {
+        self.output.as_ref().map(|output| &output.screen_desc)
+    }
+
+    /// Upload the texture changes of the last frame.
+    /// Needs to be called before the `paint()`.
+    pub fn update_textures(
+        &mut self,
+        command_encoder: &mut blade_graphics::CommandEncoder,
+        context: &blade_graphics::Context,
+    ) {
+        if let Some(ref mut output) = self.output {
+            let textures_delta = std::mem::take(&mut output.textures_delta);
+            self.painter
+                .update_textures(command_encoder, &textures_delta, context);
+        }
+    }
+
+    /// Paint the last frame into a render pass of the window surface.
+    pub fn paint(
+        &mut self,
+        pass: &mut blade_graphics::RenderCommandEncoder,
+        context: &blade_graphics::Context,
+    ) {
+        if let Some(ref output) = self.output {
+            self.painter
+                .paint(pass, &output.primitives, &output.screen_desc, context);
+        }
+    }
+
+    /// Call this after submitting work at the given `sync_point`.
+    pub fn after_submit(&mut self, sync_point: &blade_graphics::SyncPoint) {
+        self.painter.after_submit(sync_point);
+    }
+
+    /// Render a frame into the `surface`, with the UI on top.
+    ///
+    /// The `record` callback is given the command encoder and the view of
+    /// the frame to fill, for example by a render pass clearing it.
+    /// It's called after the UI textures are updated.
+    ///
+    /// Waits for the previous frame to finish on GPU, so that only one frame
+    /// is processed at a time.
+    #[profiling::function]
+    pub fn render_frame(
+        &mut self,
+        surface: &mut blade_graphics::Surface,
+        context: &blade_graphics::Context,
+        record: impl FnOnce(&mut blade_graphics::CommandEncoder, blade_graphics::TextureView),
+    ) -> blade_graphics::SyncPoint {
+        let command_encoder = self.command_encoder.get_or_insert_with(|| {
+            context.create_command_encoder(blade_graphics::CommandEncoderDesc {
+                name: "gui",
+                buffer_count: 2,
+            })
+        });
+        let frame = surface.acquire_frame();
+        command_encoder.start();
+        command_encoder.init_texture(frame.texture());
+
+        if let Some(ref mut output) = self.output {
+            let textures_delta = std::mem::take(&mut output.textures_delta);
+            self.painter
+                .update_textures(command_encoder, &textures_delta, context);
+        }
+        record(command_encoder, frame.texture_view());
+
+        if let Some(ref output) = self.output {
+            if let mut pass = command_encoder.render(
+                "gui",
+                blade_graphics::RenderTargetSet {
+                    colors: &[blade_graphics::RenderTarget {
+                        view: frame.texture_view(),
+                        init_op: blade_graphics::InitOp::Load,
+                        finish_op: blade_graphics::FinishOp::Store,
+                    }],
+                    depth_stencil: None,
+                },
+            ) {
+                self.painter
+                    .paint(&mut pass, &output.primitives, &output.screen_desc, context);
+            }
+        }
+
+        command_encoder.present(frame);
+        let sync_point = context.submit(command_encoder);
+        self.painter.after_submit(&sync_point);
+
+        if let Some(sp) = self.prev_sync_point.take() {
+            context.wait_for(&sp, !0);
+        }
+        self.prev_sync_point = Some(sync_point.clone());
+        sync_point
+    }
+}
@@ -2,9 +2,61 @@ use super::ExposeHud;
 
 const MAX_FLY_SPEED: f32 = 1000000.0;
 
+/// Key bindings for moving a camera.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CameraKeys {
+    pub forward: winit::keyboard::KeyCode,
+    pub backward: winit::keyboard::KeyCode,
+    pub left: winit::keyboard::KeyCode,
+    pub right: winit::keyboard::KeyCode,
+    pub down: winit::keyboard::KeyCode,
+    pub up: winit::keyboard::KeyCode,
+    pub roll_left: winit::keyboard::KeyCode,
+    pub roll_right: winit::keyboard::KeyCode,
+}
+
+impl Default for CameraKeys {
+    fn default() -> Self {
+        use winit::keyboard::KeyCode as Kc;
+        Self {
+            forward: Kc::KeyW,
+            backward: Kc::KeyS,
+            left: Kc::KeyA,
+            right: Kc::KeyD,
+            down: Kc::KeyZ,
+            up: Kc::KeyX,
+            roll_left: Kc::KeyQ,
+            roll_right: Kc::KeyE,
+        }
+    }
+}
+
+impl CameraKeys {
+    /// Direction of the movement in camera space, if the key is bound to it.
+    pub fn direction(&self, code: winit::keyboard::KeyCode) -> Option<glam::Vec3> {
+        Some(if code == self.forward {
+            glam::Vec3::NEG_Z
+        } else if code == self.backward {
+            glam::Vec3::Z
+        } else if code == self.left {
+            glam::Vec3::NEG_X
+        } else if code == self.right {
+            glam::Vec3::X
+        } else if code == self.down {
+            glam::Vec3::NEG_Y
+        } else if code == self.up {
+            glam::Vec3::Y
+        } else {
+            return None;
+        })
+    }
+}
+
+/// Fly camera, moved by the keys relative to its orientation.
 pub struct ControlledCamera {
     pub inner: blade_render::Camera,
     pub fly_speed: f32,
+    pub keys: CameraKeys,
 }
 
 impl Default for ControlledCamera {
@@ -28,11 +80,21 @@ impl Default for ControlledCamera {
                 depth: 0.0,
             },
             fly_speed: 0.0,
+            keys: CameraKeys::default(),
         }
     }
 }
 
 impl ControlledCamera {
+    /// Create a camera with the default key bindings.
+    pub fn new(inner: blade_render::Camera, fly_speed: f32) -> Self {
+        Self {
+            inner,
+            fly_speed,
+            keys: CameraKeys::default(),
+        }
+    }
+
     pub fn get_view_matrix(&self) -> glam::Mat4 {
         glam::Mat4::from_rotation_translation(self.inner.rot.into(), self.inner.pos.into())
             .inverse()
@@ -54,36 +116,16 @@ impl ControlledCamera {
     }
 
     pub fn on_key(&mut self, code: winit::keyboard::KeyCode, delta: f32) -> bool {
-        use winit::keyboard::KeyCode as Kc;
-
         let move_offset = self.fly_speed * delta;
         let rotate_offset_z = 1000.0 * delta;
-        match code {
-            Kc::KeyW => {
-                self.move_by(glam::Vec3::new(0.0, 0.0, -move_offset));
-            }
-            Kc::KeyS => {
-                self.move_by(glam::Vec3::new(0.0, 0.0, move_offset));
-            }
-            Kc::KeyA => {
-                self.move_by(glam::Vec3::new(-move_offset, 0.0, 0.0));
-            }
-            Kc::KeyD => {
-                self.move_by(glam::Vec3::new(move_offset, 0.0, 0.0));
-            }
-            Kc::KeyZ => {
-                self.move_by(glam::Vec3::new(0.0, -move_offset, 0.0));
-            }
-            Kc::KeyX => {
-                self.move_by(glam::Vec3::new(0.0, move_offset, 0.0));
-            }
-            Kc::KeyQ => {
-                self.rotate_z_by(rotate_offset_z);
-            }
-            Kc::KeyE => {
-                self.rotate_z_by(-rotate_offset_z);
-            }
-            _ => return false,
+        if let Some(dir) = self.keys.direction(code) {
+            self.move_by(dir * move_offset);
+        } else if code == self.keys.roll_left {
+            self.rotate_z_by(rotate_offset_z);
+        } else if code == self.keys.roll_right {
+            self.rotate_z_by(-rotate_offset_z);
+        } else {
+            return false;
         }
 
         true
@@ -0,0 +1,318 @@
+use super::{CameraKeys, ExposeHud};
+use std::{collections::HashSet, f32::consts};
+
+/// Rotation of a camera looking along -Z, given yaw around Y and pitch around X.
+fn yaw_pitch_rotation(yaw: f32, pitch: f32) -> glam::Quat {
+    glam::Quat::from_rotation_y(yaw) * glam::Quat::from_rotation_x(pitch)
+}
+
+/// Rotation of a camera looking along the direction, keeping the up direction vertical.
+fn look_rotation(dir: glam::Vec3, up: glam::Vec3) -> glam::Quat {
+    // The direction is degenerate if the camera is at the point to look at.
+    let forward = dir.try_normalize().unwrap_or(glam::Vec3::NEG_Z);
+    if forward.cross(up).length_squared() < 1e-6 {
+        // Looking along the up direction, so there is no preferred roll.
+        glam::Quat::from_rotation_arc(glam::Vec3::NEG_Z, forward)
+    } else {
+        let view = glam::Mat4::look_to_rh(glam::Vec3::ZERO, forward, up);
+        glam::Quat::from_mat4(&view).inverse()
+    }
+}
+
+fn wheel_lines(delta: winit::event::MouseScrollDelta) -> f32 {
+    match delta {
+        winit::event::MouseScrollDelta::LineDelta(_, lines) => lines,
+        //Note: assuming about 20 pixels per line
+        winit::event::MouseScrollDelta::PixelDelta(position) => position.y as f32 / 20.0,
+    }
+}
+
+/// Camera orbiting around a target point, rotated by dragging the mouse
+/// and zoomed by the mouse wheel.
+pub struct OrbitCamera {
+    pub target: mint::Vector3<f32>,
+    pub distance: f32,
+    /// Rotation around the vertical axis, in radians.
+    pub yaw: f32,
+    /// Rotation around the horizontal axis, in radians.
+    pub pitch: f32,
+    pub fov_y: f32,
+    pub depth: f32,
+    /// Mouse button that rotates the camera while held.
+    pub drag_button: winit::event::MouseButton,
+    /// Rotation angle per pixel of mouse movement, in radians.
+    pub rotate_speed: f32,
+    /// Relative change of the distance per line of mouse wheel.
+    pub zoom_speed: f32,
+    pub min_distance: f32,
+    pub max_distance: f32,
+    is_dragging: bool,
+    last_cursor: Option<glam::Vec2>,
+}
+
+impl Default for OrbitCamera {
+    fn default() -> Self {
+        Self {
+            target: glam::Vec3::ZERO.into(),
+            distance: 10.0,
+            yaw: 0.0,
+            pitch: -0.5,
+            fov_y: 1.0,
+            depth: 0.0,
+            drag_button: winit::event::MouseButton::Left,
+            rotate_speed: 0.01,
+            zoom_speed: 0.1,
+            min_distance: 0.1,
+            max_distance: 10000.0,
+            is_dragging: false,
+            last_cursor: None,
+        }
+    }
+}
+
+impl OrbitCamera {
+    pub fn camera(&self) -> blade_render::Camera {
+        let rot = yaw_pitch_rotation(self.yaw, self.pitch);
+        let pos = glam::Vec3::from(self.target) + rot * glam::Vec3::new(0.0, 0.0, self.distance);
+        blade_render::Camera {
+            pos: pos.into(),
+            rot: rot.into(),
+            fov_y: self.fov_y,
+            depth: self.depth,
+        }
+    }
+
+    /// Returns true if the button is used by the camera.
+    pub fn on_mouse_button(
+        &mut self,
+        button: winit::event::MouseButton,
+        state: winit::event::ElementState,
+    ) -> bool {
+        if button != self.drag_button {
+            return false;
+        }
+        self.is_dragging = state == winit::event::ElementState::Pressed;
+        true
+    }
+
+    pub fn on_cursor_moved(&mut self, position: winit::dpi::PhysicalPosition<f64>) {
+        let cursor = glam::Vec2::new(position.x as f32, position.y as f32);
+        if let Some(last) = self.last_cursor {
+            if self.is_dragging {
+                let delta = cursor - last;
+                self.yaw -= delta.x * self.rotate_speed;
+                self.pitch = (self.pitch - delta.y * self.rotate_speed)
+                    .clamp(-consts::FRAC_PI_2 + 0.01, consts::FRAC_PI_2 - 0.01);
+            }
+        }
+        self.last_cursor = Some(cursor);
+    }
+
+    pub fn on_wheel(&mut self, delta: winit::event::MouseScrollDelta) {
+        let scale = (-wheel_lines(delta) * self.zoom_speed).exp();
+        self.distance = (self.distance * scale).clamp(self.min_distance, self.max_distance);
+    }
+}
+
+impl ExposeHud for OrbitCamera {
+    fn populate_hud(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Target:");
+            ui.add(egui::DragValue::new(&mut self.target.x));
+            ui.add(egui::DragValue::new(&mut self.target.y));
+            ui.add(egui::DragValue::new(&mut self.target.z));
+        });
+        ui.add(
+            egui::Slider::new(&mut self.distance, self.min_distance..=self.max_distance)
+                .text("Distance")
+                .logarithmic(true),
+        );
+        ui.add(egui::Slider::new(&mut self.fov_y, 0.5f32..=2.0f32).text("FOV"));
+    }
+}
+
+/// First-person camera, looking around with the mouse,
+/// and moving with the keys while they are held.
+pub struct FpsCamera {
+    pub pos: mint::Vector3<f32>,
+    /// Rotation around the vertical axis, in radians.
+    pub yaw: f32,
+    /// Rotation around the horizontal axis, in radians.
+    pub pitch: f32,
+    /// Maximum absolute value of the pitch.
+    pub max_pitch: f32,
+    pub fov_y: f32,
+    pub depth: f32,
+    /// Movement speed, in units per second.
+    pub move_speed: f32,
+    /// Rotation angle per unit of mouse motion, in radians.
+    pub mouse_sensitivity: f32,
+    pub keys: CameraKeys,
+    pressed: HashSet<winit::keyboard::KeyCode>,
+}
+
+impl Default for FpsCamera {
+    fn default() -> Self {
+        Self {
+            pos: glam::Vec3::ZERO.into(),
+            yaw: 0.0,
+            pitch: 0.0,
+            max_pitch: consts::FRAC_PI_2 - 0.01,
+            fov_y: 1.0,
+            depth: 0.0,
+            move_speed: 5.0,
+            mouse_sensitivity: 0.002,
+            keys: CameraKeys::default(),
+            pressed: HashSet::default(),
+        }
+    }
+}
+
+impl FpsCamera {
+    pub fn camera(&self) -> blade_render::Camera {
+        blade_render::Camera {
+            pos: self.pos,
+            rot: yaw_pitch_rotation(self.yaw, self.pitch).into(),
+            fov_y: self.fov_y,
+            depth: self.depth,
+        }
+    }
+
+    /// Returns true if the key is used by the camera.
+    pub fn on_key(
+        &mut self,
+        code: winit::keyboard::KeyCode,
+        state: winit::event::ElementState,
+    ) -> bool {
+        if self.keys.direction(code).is_none() {
+            return false;
+        }
+        match state {
+            winit::event::ElementState::Pressed => self.pressed.insert(code),
+            winit::event::ElementState::Released => self.pressed.remove(&code),
+        };
+        true
+    }
+
+    /// Rotate the camera by the raw mouse motion,
+    /// as given by `DeviceEvent::MouseMotion`.
+    pub fn on_mouse_motion(&mut self, delta: (f64, f64)) {
+        self.yaw -= delta.0 as f32 * self.mouse_sensitivity;
+        self.pitch = (self.pitch - delta.1 as f32 * self.mouse_sensitivity)
+            .clamp(-self.max_pitch, self.max_pitch);
+    }
+
+    /// Forget the held keys, for example when the window loses focus.
+    pub fn release_keys(&mut self) {
+        self.pressed.clear();
+    }
+
+    /// Move the camera according to the held keys.
+    ///
+    /// Horizontal movement ignores the pitch, so that looking up or down
+    /// doesn't change the speed of walking.
+    pub fn update(&mut self, delta: f32) {
+        let dir = self
+            .pressed
+            .iter()
+            .filter_map(|&code| self.keys.direction(code))
+            .sum::<glam::Vec3>();
+        if dir == glam::Vec3::ZERO {
+            return;
+        }
+        let offset = glam::Quat::from_rotation_y(self.yaw) * dir.normalize();
+        self.pos = (glam::Vec3::from(self.pos) + offset * self.move_speed * delta).into();
+    }
+}
+
+impl ExposeHud for FpsCamera {
+    fn populate_hud(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Position:");
+            ui.add(egui::DragValue::new(&mut self.pos.x));
+            ui.add(egui::DragValue::new(&mut self.pos.y));
+            ui.add(egui::DragValue::new(&mut self.pos.z));
+        });
+        ui.add(egui::Slider::new(&mut self.fov_y, 0.5f32..=2.0f32).text("FOV"));
+        ui.add(
+            egui::Slider::new(&mut self.move_speed, 0.1f32..=1000.0f32)
+                .text("Speed")
+                .logarithmic(true),
+        );
+    }
+}
+
+/// Camera smoothly following a moving target, such as an object transform.
+pub struct FollowCamera {
+    /// Position of the camera relative to the target, in the target space.
+    pub offset: mint::Vector3<f32>,
+    /// Point to look at, relative to the target, in the target space.
+    pub look_offset: mint::Vector3<f32>,
+    /// Up direction of the world.
+    pub up: mint::Vector3<f32>,
+    /// How fast the camera catches up with the target, per second.
+    /// Higher values make the camera follow more tightly.
+    pub stiffness: f32,
+    pub fov_y: f32,
+    pub depth: f32,
+    pos: glam::Vec3,
+    look_at: glam::Vec3,
+    is_initialized: bool,
+}
+
+impl Default for FollowCamera {
+    fn default() -> Self {
+        Self {
+            offset: glam::Vec3::new(0.0, 2.0, 6.0).into(),
+            look_offset: glam::Vec3::ZERO.into(),
+            up: glam::Vec3::Y.into(),
+            stiffness: 5.0,
+            fov_y: 1.0,
+            depth: 0.0,
+            pos: glam::Vec3::ZERO,
+            look_at: glam::Vec3::NEG_Z,
+            is_initialized: false,
+        }
+    }
+}
+
+impl FollowCamera {
+    /// Move the camera towards the target with the given position and orientation.
+    /// The first update snaps the camera to the target.
+    pub fn update(
+        &mut self,
+        target_pos: mint::Vector3<f32>,
+        target_rot: mint::Quaternion<f32>,
+        delta: f32,
+    ) {
+        let pos = glam::Vec3::from(target_pos);
+        let rot = glam::Quat::from(target_rot);
+        let desired_pos = pos + rot * glam::Vec3::from(self.offset);
+        let desired_look_at = pos + rot * glam::Vec3::from(self.look_offset);
+        if self.is_initialized {
+            // Exponential smoothing, independent of the frame rate.
+            let t = 1.0 - (-self.stiffness * delta).exp();
+            self.pos = self.pos.lerp(desired_pos, t);
+            self.look_at = self.look_at.lerp(desired_look_at, t);
+        } else {
+            self.pos = desired_pos;
+            self.look_at = desired_look_at;
+            self.is_initialized = true;
+        }
+    }
+
+    /// Snap to the target on the next update, for example after teleporting.
+    pub fn reset(&mut self) {
+        self.is_initialized = false;
+    }
+
+    pub fn camera(&self) -> blade_render::Camera {
+        let rot = look_rotation(self.look_at - self.pos, self.up.into());
+        blade_render::Camera {
+            pos: self.pos.into(),
+            rot: rot.into(),
+            fov_y: self.fov_y,
+            depth: self.depth,
+        }
+    }
+}
@@ -164,3 +164,60 @@ pub fn populate_debug_selection(
         });
     });
 }
+
+/// Show the progress of loading the assets of the hub,
+/// listing the ones that are not ready yet, or failed.
+pub fn populate_load_progress(asset_hub: &blade_render::AssetHub, ui: &mut egui::Ui) {
+    use blade_asset::LoadState as Ls;
+
+    let progress = asset_hub.progress();
+    let total = progress.total();
+    ui.add(egui::ProgressBar::new(total.fraction()).text(format!(
+        "{}/{}",
+        total.finished(),
+        total.total()
+    )));
+
+    egui::Grid::new("load progress").show(ui, |ui| {
+        for title in ["", "Queued", "Cooking", "Serving", "Ready", "Failed"] {
+            ui.label(title);
+        }
+        ui.end_row();
+        for (name, p) in [
+            ("Textures", progress.textures),
+            ("Models", progress.models),
+            ("Shaders", progress.shaders),
+        ] {
+            ui.label(name);
+            for count in [p.queued, p.cooking, p.serving, p.ready, p.failed] {
+                ui.label(count.to_string());
+            }
+            ui.end_row();
+        }
+    });
+
+    let mut list = asset_hub.list_status();
+    list.retain(|status| status.state != Ls::Ready);
+    list.sort_by_key(|status| status.timings.total());
+    for status in list.iter().rev() {
+        let color = match status.state {
+            Ls::Failed => egui::Color32::RED,
+            Ls::Cooking | Ls::Serving => egui::Color32::WHITE,
+            Ls::Queued | Ls::Ready => egui::Color32::GRAY,
+        };
+        let timings = &status.timings;
+        ui.horizontal(|ui| {
+            ui.colored_label(color, format!("{:?}", status.state));
+            ui.label(status.path.display().to_string())
+                .on_hover_text(format!(
+                    "Queued: {:.2}s\nCooking: {:.2}s\nServing: {:.2}s",
+                    timings.queued.as_secs_f32(),
+                    timings.cooking.as_secs_f32(),
+                    timings.serving.as_secs_f32(),
+                ));
+        });
+        if let Some(ref error) = status.error {
+            ui.colored_label(egui::Color32::RED, error.message());
+        }
+    }
+}
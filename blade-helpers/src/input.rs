@@ -0,0 +1,255 @@
+//! Mapping of the window input into named actions and axes.
+//!
+//! Bindings are described by [`InputConfig`], for example in RON:
+//! ```ron
+//! (
+//!     actions: {
+//!         "jump": [Key(Space), Mouse(Right)],
+//!     },
+//!     axes: {
+//!         "move_x": [(source: Buttons(negative: Key(KeyA), positive: Key(KeyD)))],
+//!         "look_x": [(source: MouseX, scale: 0.01), (source: Analog("stick_x"))],
+//!     },
+//! )
+//! ```
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Mutex,
+};
+
+/// Physical button that can be bound to an action.
+#[derive(Clone, Copy, Debug, Hash, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+pub enum Button {
+    Key(winit::keyboard::KeyCode),
+    Mouse(winit::event::MouseButton),
+}
+
+/// Source of the values of an axis.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub enum AxisSource {
+    /// Pair of buttons, producing -1, 0, or 1.
+    Buttons { negative: Button, positive: Button },
+    /// Horizontal mouse motion within the frame.
+    MouseX,
+    /// Vertical mouse motion within the frame.
+    MouseY,
+    /// Mouse wheel lines scrolled within the frame.
+    MouseWheel,
+    /// Analog value fed by the application, such as a gamepad stick.
+    Analog(String),
+}
+
+fn default_scale() -> f32 {
+    1.0
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct AxisBinding {
+    pub source: AxisSource,
+    #[serde(default = "default_scale")]
+    pub scale: f32,
+}
+
+/// Bindings of the named actions and axes.
+#[derive(Clone, Debug, Default, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct InputConfig {
+    /// Actions, triggered by any of the bound buttons.
+    #[serde(default)]
+    pub actions: HashMap<String, Vec<Button>>,
+    /// Axes, summing up the values of all the bindings.
+    #[serde(default)]
+    pub axes: HashMap<String, Vec<AxisBinding>>,
+}
+
+impl InputConfig {
+    pub fn from_ron(text: &str) -> Result<Self, ron::de::SpannedError> {
+        ron::de::from_str(text)
+    }
+}
+
+/// State of the input, tracked per frame and queried by the action names.
+///
+/// Feed it the window and device events, query the actions during the frame,
+/// and call [`InputMap::end_frame`] at the end of it.
+#[derive(Default)]
+pub struct InputMap {
+    pub config: InputConfig,
+    down: HashSet<Button>,
+    pressed: HashSet<Button>,
+    released: HashSet<Button>,
+    mouse_delta: glam::Vec2,
+    last_cursor: Option<glam::Vec2>,
+    has_raw_motion: bool,
+    wheel: f32,
+    analog: HashMap<String, f32>,
+    /// Unknown names that are already reported, to avoid flooding the log.
+    unknown_actions: Mutex<HashSet<String>>,
+    unknown_axes: Mutex<HashSet<String>>,
+}
+
+fn report_unknown(reported: &Mutex<HashSet<String>>, kind: &str, name: &str) {
+    let mut reported = reported.lock().unwrap();
+    if !reported.contains(name) {
+        log::warn!("Unknown {} '{}'", kind, name);
+        reported.insert(name.to_string());
+    }
+}
+
+impl InputMap {
+    pub fn new(config: InputConfig) -> Self {
+        Self {
+            config,
+            ..Default::default()
+        }
+    }
+
+    fn on_button(&mut self, button: Button, state: winit::event::ElementState) {
+        match state {
+            winit::event::ElementState::Pressed => {
+                // Ignore the key repeats
+                if self.down.insert(button) {
+                    self.pressed.insert(button);
+                }
+            }
+            winit::event::ElementState::Released => {
+                if self.down.remove(&button) {
+                    self.released.insert(button);
+                }
+            }
+        }
+    }
+
+    /// Process a window event. Returns true if it's relevant to the input.
+    pub fn on_window_event(&mut self, event: &winit::event::WindowEvent) -> bool {
+        match *event {
+            winit::event::WindowEvent::KeyboardInput {
+                event:
+                    winit::event::KeyEvent {
+                        physical_key: winit::keyboard::PhysicalKey::Code(key_code),
+                        state,
+                        ..
+                    },
+                ..
+            } => {
+                self.on_button(Button::Key(key_code), state);
+            }
+            winit::event::WindowEvent::MouseInput { state, button, .. } => {
+                self.on_button(Button::Mouse(button), state);
+            }
+            winit::event::WindowEvent::CursorMoved { position, .. } => {
+                let cursor = glam::Vec2::new(position.x as f32, position.y as f32);
+                // Raw motion is preferred, since it's not limited by the window.
+                if let Some(last) = self.last_cursor {
+                    if !self.has_raw_motion {
+                        self.mouse_delta += cursor - last;
+                    }
+                }
+                self.last_cursor = Some(cursor);
+            }
+            winit::event::WindowEvent::MouseWheel { delta, .. } => {
+                self.wheel += match delta {
+                    winit::event::MouseScrollDelta::LineDelta(_, lines) => lines,
+                    //Note: assuming about 20 pixels per line
+                    winit::event::MouseScrollDelta::PixelDelta(position) => {
+                        position.y as f32 / 20.0
+                    }
+                };
+            }
+            winit::event::WindowEvent::Focused(false) => {
+                // The releases are not delivered to unfocused windows.
+                let down = std::mem::take(&mut self.down);
+                self.released.extend(down);
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Process a device event, used for the raw mouse motion.
+    pub fn on_device_event(&mut self, event: &winit::event::DeviceEvent) {
+        if let winit::event::DeviceEvent::MouseMotion { delta } = *event {
+            if !self.has_raw_motion {
+                self.has_raw_motion = true;
+                self.mouse_delta = glam::Vec2::ZERO;
+            }
+            self.mouse_delta += glam::Vec2::new(delta.0 as f32, delta.1 as f32);
+        }
+    }
+
+    /// Set the value of an analog source, such as a gamepad stick.
+    /// It persists between frames until changed.
+    pub fn set_analog(&mut self, name: &str, value: f32) {
+        self.analog.insert(name.to_string(), value);
+    }
+
+    /// Finish the frame, resetting the per-frame state.
+    pub fn end_frame(&mut self) {
+        self.pressed.clear();
+        self.released.clear();
+        self.mouse_delta = glam::Vec2::ZERO;
+        self.wheel = 0.0;
+    }
+
+    fn buttons(&self, action: &str) -> &[Button] {
+        match self.config.actions.get(action) {
+            Some(buttons) => buttons,
+            None => {
+                report_unknown(&self.unknown_actions, "action", action);
+                &[]
+            }
+        }
+    }
+
+    /// Check if any button of the action is held down.
+    pub fn is_down(&self, action: &str) -> bool {
+        self.buttons(action)
+            .iter()
+            .any(|button| self.down.contains(button))
+    }
+
+    /// Check if any button of the action got pressed within the frame.
+    pub fn was_pressed(&self, action: &str) -> bool {
+        self.buttons(action)
+            .iter()
+            .any(|button| self.pressed.contains(button))
+    }
+
+    /// Check if any button of the action got released within the frame.
+    pub fn was_released(&self, action: &str) -> bool {
+        self.buttons(action)
+            .iter()
+            .any(|button| self.released.contains(button))
+    }
+
+    fn source_value(&self, source: &AxisSource) -> f32 {
+        match *source {
+            AxisSource::Buttons {
+                ref negative,
+                ref positive,
+            } => {
+                let neg = self.down.contains(negative) as i32;
+                let pos = self.down.contains(positive) as i32;
+                (pos - neg) as f32
+            }
+            AxisSource::MouseX => self.mouse_delta.x,
+            AxisSource::MouseY => self.mouse_delta.y,
+            AxisSource::MouseWheel => self.wheel,
+            AxisSource::Analog(ref name) => self.analog.get(name).cloned().unwrap_or_default(),
+        }
+    }
+
+    /// Get the value of an axis, summed over all of its bindings.
+    pub fn axis(&self, name: &str) -> f32 {
+        match self.config.axes.get(name) {
+            Some(bindings) => bindings
+                .iter()
+                .map(|binding| self.source_value(&binding.source) * binding.scale)
+                .sum(),
+            None => {
+                report_unknown(&self.unknown_axes, "axis", name);
+                0.0
+            }
+        }
+    }
+}
@@ -1,11 +1,19 @@
 #![cfg(not(any(gles, target_arch = "wasm32")))]
 
 mod camera;
+mod controller;
 mod hud;
+pub mod input;
+mod profiler;
 
 pub use blade_render::Camera;
-pub use camera::ControlledCamera;
-pub use hud::{populate_debug_selection, ExposeHud};
+pub use camera::{CameraKeys, ControlledCamera};
+pub use controller::{FollowCamera, FpsCamera, OrbitCamera};
+pub use hud::{populate_debug_selection, populate_load_progress, ExposeHud};
+pub use profiler::{CpuScope, Profiler, TimingSource, TimingStats};
+
+#[doc(hidden)]
+pub use profiling;
 
 pub fn default_ray_config() -> blade_render::RayConfig {
     blade_render::RayConfig {
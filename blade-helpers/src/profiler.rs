@@ -0,0 +1,423 @@
+use super::ExposeHud;
+use std::{
+    borrow::Cow,
+    collections::{HashMap, VecDeque},
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Where a timing is measured.
+#[derive(Clone, Copy, Debug, Hash, Eq, PartialEq)]
+pub enum TimingSource {
+    Cpu,
+    Gpu,
+}
+
+/// Statistics of a CPU scope or a GPU pass over the profiler history.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TimingStats {
+    pub name: String,
+    pub source: TimingSource,
+    /// Number of frames the timing is present in.
+    pub count: usize,
+    pub average: Duration,
+    pub p50: Duration,
+    pub p95: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+}
+
+struct CpuSpan {
+    name: Cow<'static, str>,
+    thread: usize,
+    start: Instant,
+    duration: Duration,
+}
+
+#[derive(Default)]
+struct Recorder {
+    spans: Vec<CpuSpan>,
+    threads: HashMap<thread::ThreadId, usize>,
+    thread_names: Vec<String>,
+}
+
+struct Frame {
+    start: Instant,
+    duration: Duration,
+    cpu_spans: Vec<CpuSpan>,
+    gpu_passes: Vec<(String, Duration)>,
+}
+
+impl Frame {
+    fn gpu_total(&self) -> Duration {
+        self.gpu_passes.iter().map(|&(_, duration)| duration).sum()
+    }
+}
+
+/// CPU scope of the profiler, recorded when dropped.
+pub struct CpuScope {
+    recorder: Arc<Mutex<Recorder>>,
+    name: Cow<'static, str>,
+    start: Instant,
+}
+
+impl Drop for CpuScope {
+    fn drop(&mut self) {
+        let duration = self.start.elapsed();
+        let mut recorder = self.recorder.lock().unwrap();
+        let current = thread::current();
+        let next_index = recorder.thread_names.len();
+        let thread_index = *recorder.threads.entry(current.id()).or_insert(next_index);
+        if thread_index == next_index {
+            let name = current.name().unwrap_or("unnamed").to_string();
+            recorder.thread_names.push(name);
+        }
+        recorder.spans.push(CpuSpan {
+            name: std::mem::take(&mut self.name),
+            thread: thread_index,
+            start: self.start,
+            duration,
+        });
+    }
+}
+
+/// Open a CPU scope, reported to both the `profiling` crate and the profiler,
+/// lasting until the end of the enclosing block.
+#[macro_export]
+macro_rules! profile_scope {
+    ($profiler:expr, $name:expr) => {
+        $crate::profiling::scope!($name);
+        let _profile_scope = $profiler.scope($name);
+    };
+}
+
+/// Profiler of the frames, keeping the history of the CPU scopes and the GPU passes.
+///
+/// Scopes are recorded by [`Profiler::scope`], possibly on other threads.
+/// Each frame is finished by [`Profiler::end_frame`] with the GPU timings,
+/// such as the ones given by `FramePacer::timings`.
+pub struct Profiler {
+    /// Number of frames kept in the history.
+    pub history_size: usize,
+    /// Stop recording the frames, keeping the history for inspection.
+    pub paused: bool,
+    /// Path of the Chrome trace saved from the HUD.
+    pub trace_path: PathBuf,
+    recorder: Arc<Mutex<Recorder>>,
+    frame_start: Instant,
+    frames: VecDeque<Frame>,
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self::new(300)
+    }
+}
+
+fn percentile(sorted: &[Duration], fraction: f32) -> Duration {
+    let index = ((sorted.len() - 1) as f32 * fraction).round() as usize;
+    sorted[index]
+}
+
+/// Value of a frame shown on the graph.
+type FrameValue = dyn Fn(&Frame) -> Duration;
+
+fn to_millis(duration: Duration) -> f32 {
+    duration.as_secs_f32() * 1000.0
+}
+
+fn to_micros(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1_000_000.0
+}
+
+fn write_json_string(out: &mut dyn io::Write, value: &str) -> io::Result<()> {
+    write!(out, "\"")?;
+    for c in value.chars() {
+        match c {
+            '"' => write!(out, "\\\"")?,
+            '\\' => write!(out, "\\\\")?,
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32)?,
+            c => write!(out, "{}", c)?,
+        }
+    }
+    write!(out, "\"")
+}
+
+impl Profiler {
+    pub fn new(history_size: usize) -> Self {
+        Self {
+            history_size,
+            paused: false,
+            trace_path: PathBuf::from("trace.json"),
+            recorder: Arc::default(),
+            frame_start: Instant::now(),
+            frames: VecDeque::with_capacity(history_size),
+        }
+    }
+
+    /// Start a CPU scope, lasting until the returned object is dropped.
+    pub fn scope(&self, name: impl Into<Cow<'static, str>>) -> CpuScope {
+        CpuScope {
+            recorder: Arc::clone(&self.recorder),
+            name: name.into(),
+            start: Instant::now(),
+        }
+    }
+
+    /// Finish the current frame, starting a new one.
+    ///
+    /// The CPU scopes finished since the last call are included into the frame.
+    pub fn end_frame(&mut self, gpu_timings: &[(String, Duration)]) {
+        let now = Instant::now();
+        let mut cpu_spans = std::mem::take(&mut self.recorder.lock().unwrap().spans);
+        cpu_spans.sort_by_key(|span| span.start);
+        if !self.paused {
+            while self.frames.len() >= self.history_size.max(1) {
+                self.frames.pop_front();
+            }
+            self.frames.push_back(Frame {
+                start: self.frame_start,
+                duration: now - self.frame_start,
+                cpu_spans,
+                gpu_passes: gpu_timings.to_vec(),
+            });
+        }
+        self.frame_start = now;
+    }
+
+    /// Forget all the recorded frames.
+    pub fn clear(&mut self) {
+        self.frames.clear();
+    }
+
+    /// Compute the statistics of all the CPU scopes and GPU passes in the history.
+    pub fn stats(&self) -> Vec<TimingStats> {
+        // Samples are summed up within a frame, keeping the order of appearance.
+        let mut order = Vec::<(TimingSource, &str)>::new();
+        let mut samples = HashMap::<(TimingSource, &str), Vec<Duration>>::new();
+        for frame in self.frames.iter() {
+            let mut frame_sums = Vec::<((TimingSource, &str), Duration)>::new();
+            let cpu = frame
+                .cpu_spans
+                .iter()
+                .map(|span| ((TimingSource::Cpu, &*span.name), span.duration));
+            let gpu = frame
+                .gpu_passes
+                .iter()
+                .map(|&(ref name, duration)| ((TimingSource::Gpu, name.as_str()), duration));
+            for (key, duration) in cpu.chain(gpu) {
+                match frame_sums.iter_mut().find(|&&mut (other, _)| other == key) {
+                    Some(&mut (_, ref mut sum)) => *sum += duration,
+                    None => frame_sums.push((key, duration)),
+                }
+            }
+            for (key, duration) in frame_sums {
+                samples
+                    .entry(key)
+                    .or_insert_with(|| {
+                        order.push(key);
+                        Vec::new()
+                    })
+                    .push(duration);
+            }
+        }
+
+        order.sort_by_key(|&(source, _)| source == TimingSource::Cpu);
+        order
+            .into_iter()
+            .map(|key| {
+                let mut list = samples.remove(&key).unwrap();
+                list.sort();
+                TimingStats {
+                    name: key.1.to_string(),
+                    source: key.0,
+                    count: list.len(),
+                    average: list.iter().sum::<Duration>() / list.len() as u32,
+                    p50: percentile(&list, 0.5),
+                    p95: percentile(&list, 0.95),
+                    p99: percentile(&list, 0.99),
+                    max: *list.last().unwrap(),
+                }
+            })
+            .collect()
+    }
+
+    /// Write the history in the Chrome trace JSON format,
+    /// which can be opened by "chrome://tracing" or Perfetto.
+    ///
+    /// GPU passes are laid out one after another from the start of their frame,
+    /// since only their durations are known.
+    pub fn write_chrome_trace(&self, out: &mut impl io::Write) -> io::Result<()> {
+        const CPU_PID: u32 = 0;
+        const GPU_PID: u32 = 1;
+        let epoch = match self.frames.front() {
+            Some(frame) => frame.start,
+            None => self.frame_start,
+        };
+        let mut separator = "";
+        let mut write_event = |out: &mut dyn io::Write,
+                               name: &str,
+                               pid: u32,
+                               tid: usize,
+                               start: f64,
+                               duration: f64|
+         -> io::Result<()> {
+            write!(out, "{}\n{{\"name\":", separator)?;
+            write_json_string(out, name)?;
+            write!(
+                out,
+                ",\"ph\":\"X\",\"pid\":{},\"tid\":{},\"ts\":{:.3},\"dur\":{:.3}}}",
+                pid, tid, start, duration
+            )?;
+            separator = ",";
+            Ok(())
+        };
+
+        write!(out, "{{\"traceEvents\":[")?;
+        for frame in self.frames.iter() {
+            let frame_start = to_micros(frame.start - epoch);
+            write_event(
+                out,
+                "frame",
+                CPU_PID,
+                0,
+                frame_start,
+                to_micros(frame.duration),
+            )?;
+            for span in frame.cpu_spans.iter() {
+                write_event(
+                    out,
+                    &span.name,
+                    CPU_PID,
+                    span.thread + 1,
+                    to_micros(span.start.saturating_duration_since(epoch)),
+                    to_micros(span.duration),
+                )?;
+            }
+            let mut gpu_offset = frame_start;
+            for &(ref name, duration) in frame.gpu_passes.iter() {
+                write_event(out, name, GPU_PID, 0, gpu_offset, to_micros(duration))?;
+                gpu_offset += to_micros(duration);
+            }
+        }
+
+        let recorder = self.recorder.lock().unwrap();
+        let thread_names = ["frames"]
+            .into_iter()
+            .map(|name| (CPU_PID, 0, name))
+            .chain(
+                recorder
+                    .thread_names
+                    .iter()
+                    .enumerate()
+                    .map(|(index, name)| (CPU_PID, index + 1, name.as_str())),
+            )
+            .chain([(GPU_PID, 0, "GPU")]);
+        for (pid, tid, name) in thread_names {
+            write!(
+                out,
+                "{}\n{{\"name\":\"thread_name\",\"ph\":\"M\",\"pid\":{},\"tid\":{},\"args\":{{\"name\":",
+                separator, pid, tid
+            )?;
+            write_json_string(out, name)?;
+            write!(out, "}}}}")?;
+            separator = ",";
+        }
+        writeln!(out, "\n]}}")
+    }
+
+    /// Save the history as a Chrome trace JSON file.
+    pub fn save_chrome_trace(&self, path: &Path) -> io::Result<()> {
+        let mut file = io::BufWriter::new(fs::File::create(path)?);
+        self.write_chrome_trace(&mut file)
+    }
+
+    fn populate_graph(&self, ui: &mut egui::Ui) {
+        let height = 60.0;
+        let (rect, response) = ui.allocate_exact_size(
+            egui::vec2(ui.available_width(), height),
+            egui::Sense::hover(),
+        );
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 0.0, egui::Color32::from_black_alpha(160));
+
+        // Scale to the slowest frame, but at least to 60 FPS
+        let max_time = self
+            .frames
+            .iter()
+            .map(|frame| frame.duration.max(frame.gpu_total()))
+            .max()
+            .unwrap_or_default()
+            .max(Duration::from_micros(16_667));
+        let step = rect.width() / self.history_size.max(2) as f32;
+        let to_point = |index: usize, duration: Duration| {
+            let y = rect.bottom() - rect.height() * (to_millis(duration) / to_millis(max_time));
+            egui::pos2(rect.left() + index as f32 * step, y)
+        };
+        let series: [(egui::Color32, &FrameValue); 2] = [
+            (egui::Color32::LIGHT_BLUE, &|frame: &Frame| frame.duration),
+            (egui::Color32::LIGHT_GREEN, &|frame: &Frame| {
+                frame.gpu_total()
+            }),
+        ];
+        for (color, value) in series {
+            let points = self
+                .frames
+                .iter()
+                .enumerate()
+                .map(|(index, frame)| to_point(index, value(frame)))
+                .collect::<Vec<_>>();
+            painter.add(egui::Shape::line(points, egui::Stroke::new(1.0, color)));
+        }
+        painter.text(
+            rect.left_top(),
+            egui::Align2::LEFT_TOP,
+            format!("{:.1} ms", to_millis(max_time)),
+            egui::FontId::monospace(10.0),
+            egui::Color32::WHITE,
+        );
+        response.on_hover_text("Blue: CPU frame time\nGreen: GPU time");
+    }
+}
+
+impl ExposeHud for Profiler {
+    fn populate_hud(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.paused, "Pause");
+            if ui.button("Clear").clicked() {
+                self.clear();
+            }
+            if ui.button("Save trace").clicked() {
+                match self.save_chrome_trace(&self.trace_path) {
+                    Ok(()) => log::info!("Saved trace to {}", self.trace_path.display()),
+                    Err(e) => log::error!("Unable to save trace: {:?}", e),
+                }
+            }
+        });
+        self.populate_graph(ui);
+
+        egui::Grid::new("profiler stats")
+            .striped(true)
+            .show(ui, |ui| {
+                for title in ["", "Name", "Avg", "P50", "P95", "P99", "Max"] {
+                    ui.label(title);
+                }
+                ui.end_row();
+                for stats in self.stats() {
+                    ui.label(match stats.source {
+                        TimingSource::Cpu => "CPU",
+                        TimingSource::Gpu => "GPU",
+                    });
+                    ui.label(stats.name);
+                    for value in [stats.average, stats.p50, stats.p95, stats.p99, stats.max] {
+                        ui.colored_label(egui::Color32::WHITE, format!("{:.2}", to_millis(value)));
+                    }
+                    ui.end_row();
+                }
+            });
+        ui.label("Times are in milliseconds");
+    }
+}
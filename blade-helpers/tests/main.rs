@@ -0,0 +1,321 @@
+use blade_helpers::{CameraKeys, ControlledCamera, FollowCamera, FpsCamera, OrbitCamera};
+
+fn view_direction(camera: &blade_helpers::Camera) -> glam::Vec3 {
+    glam::Quat::from(camera.rot) * glam::Vec3::NEG_Z
+}
+
+fn assert_valid(camera: &blade_helpers::Camera) {
+    let rot = glam::Quat::from(camera.rot);
+    assert!(rot.is_finite(), "Rotation {:?} is not finite", rot);
+    assert!(rot.is_normalized(), "Rotation {:?} is not normalized", rot);
+}
+
+#[test]
+fn controlled_camera_keys() {
+    let camera = ControlledCamera::new(ControlledCamera::default().inner, 10.0);
+    assert_eq!(camera.keys, CameraKeys::default());
+    assert_eq!(camera.fly_speed, 10.0);
+}
+
+#[test]
+fn orbit_camera() {
+    let mut orbit = OrbitCamera::default();
+    orbit.target = glam::Vec3::new(1.0, 2.0, 3.0).into();
+    orbit.on_wheel(winit::event::MouseScrollDelta::LineDelta(0.0, 1000.0));
+    assert_eq!(orbit.distance, orbit.min_distance);
+
+    let camera = orbit.camera();
+    assert_valid(&camera);
+    let to_target = glam::Vec3::from(orbit.target) - glam::Vec3::from(camera.pos);
+    assert!((to_target.length() - orbit.distance).abs() < 1e-4);
+    assert!(view_direction(&camera).abs_diff_eq(to_target.normalize(), 1e-4));
+}
+
+#[test]
+fn orbit_camera_drag() {
+    use winit::{dpi::PhysicalPosition, event::ElementState};
+    let mut orbit = OrbitCamera::default();
+    let pitch = orbit.pitch;
+    orbit.on_cursor_moved(PhysicalPosition::new(0.0, 0.0));
+    orbit.on_cursor_moved(PhysicalPosition::new(10.0, 10.0));
+    assert_eq!(orbit.yaw, 0.0);
+
+    assert!(orbit.on_mouse_button(orbit.drag_button, ElementState::Pressed));
+    orbit.on_cursor_moved(PhysicalPosition::new(20.0, 0.0));
+    assert!(orbit.yaw < 0.0);
+    assert!(orbit.pitch > pitch);
+
+    assert!(orbit.on_mouse_button(orbit.drag_button, ElementState::Released));
+    let yaw = orbit.yaw;
+    orbit.on_cursor_moved(PhysicalPosition::new(30.0, 0.0));
+    assert_eq!(orbit.yaw, yaw);
+}
+
+#[test]
+fn fps_camera() {
+    use winit::{event::ElementState, keyboard::KeyCode};
+    let mut fps = FpsCamera::default();
+    assert!(!fps.on_key(KeyCode::KeyP, ElementState::Pressed));
+    assert!(fps.on_key(fps.keys.forward, ElementState::Pressed));
+    fps.update(1.0);
+    let pos = glam::Vec3::from(fps.pos);
+    assert!(pos.abs_diff_eq(glam::Vec3::new(0.0, 0.0, -fps.move_speed), 1e-4));
+
+    // looking down doesn't slow down walking
+    fps.on_mouse_motion((0.0, 1000.0));
+    assert_eq!(fps.pitch, -fps.max_pitch);
+    fps.update(1.0);
+    let distance = glam::Vec3::from(fps.pos).distance(pos);
+    assert!((distance - fps.move_speed).abs() < 1e-4);
+
+    fps.release_keys();
+    let pos = fps.pos;
+    fps.update(1.0);
+    assert_eq!(fps.pos, pos);
+    assert_valid(&fps.camera());
+}
+
+#[test]
+fn follow_camera() {
+    let mut follow = FollowCamera::default();
+    let target_rot = glam::Quat::from_rotation_y(1.0);
+    follow.update(glam::Vec3::X.into(), target_rot.into(), 0.0);
+    let camera = follow.camera();
+    assert_valid(&camera);
+    let expected_pos = glam::Vec3::X + target_rot * glam::Vec3::from(follow.offset);
+    assert!(glam::Vec3::from(camera.pos).abs_diff_eq(expected_pos, 1e-4));
+    let to_target = (glam::Vec3::X - expected_pos).normalize();
+    assert!(view_direction(&camera).abs_diff_eq(to_target, 1e-4));
+
+    // moving slowly towards the new target
+    follow.update(glam::Vec3::ZERO.into(), target_rot.into(), 0.1);
+    let pos = glam::Vec3::from(follow.camera().pos);
+    assert!(pos.distance(expected_pos) > 0.0);
+    assert!(pos.distance(expected_pos) < 1.0);
+    follow.reset();
+    follow.update(glam::Vec3::ZERO.into(), target_rot.into(), 0.1);
+    let pos = glam::Vec3::from(follow.camera().pos);
+    assert!(pos.abs_diff_eq(expected_pos - glam::Vec3::X, 1e-4));
+}
+
+#[test]
+fn follow_camera_degenerate() {
+    let mut follow = FollowCamera::default();
+    // looking at itself
+    follow.look_offset = follow.offset;
+    follow.update(glam::Vec3::ZERO.into(), glam::Quat::IDENTITY.into(), 0.0);
+    assert_valid(&follow.camera());
+
+    // looking straight down
+    follow.reset();
+    follow.offset = glam::Vec3::new(0.0, 5.0, 0.0).into();
+    follow.look_offset = glam::Vec3::ZERO.into();
+    follow.update(glam::Vec3::ZERO.into(), glam::Quat::IDENTITY.into(), 0.0);
+    let camera = follow.camera();
+    assert_valid(&camera);
+    assert!(view_direction(&camera).abs_diff_eq(glam::Vec3::NEG_Y, 1e-4));
+}
+
+const INPUT_CONFIG: &str = r#"(
+    actions: {
+        "jump": [Key(Space), Mouse(Right)],
+        "fire": [Mouse(Left)],
+    },
+    axes: {
+        "move_x": [(source: Buttons(negative: Mouse(Back), positive: Mouse(Forward)))],
+        "look_x": [(source: MouseX, scale: 0.5), (source: Analog("stick_x"))],
+    },
+)"#;
+
+fn mouse_input(
+    button: winit::event::MouseButton,
+    state: winit::event::ElementState,
+) -> winit::event::WindowEvent {
+    winit::event::WindowEvent::MouseInput {
+        device_id: winit::event::DeviceId::dummy(),
+        state,
+        button,
+    }
+}
+
+#[test]
+fn input_config() {
+    use blade_helpers::input::{AxisSource, Button, InputConfig};
+    use winit::{event::MouseButton, keyboard::KeyCode};
+    let config = InputConfig::from_ron(INPUT_CONFIG).unwrap();
+    assert_eq!(
+        config.actions["jump"],
+        [
+            Button::Key(KeyCode::Space),
+            Button::Mouse(MouseButton::Right)
+        ]
+    );
+    let look_x = &config.axes["look_x"];
+    assert_eq!(look_x[0].source, AxisSource::MouseX);
+    assert_eq!(look_x[0].scale, 0.5);
+    assert_eq!(look_x[1].source, AxisSource::Analog("stick_x".to_string()));
+    assert_eq!(look_x[1].scale, 1.0);
+
+    assert_eq!(InputConfig::from_ron("()").unwrap(), InputConfig::default());
+    assert!(InputConfig::from_ron("(actions: { \"jump\": [Key(Nope)] })").is_err());
+}
+
+#[test]
+fn input_edges() {
+    use blade_helpers::input::{InputConfig, InputMap};
+    use winit::event::{ElementState, MouseButton};
+    let mut input = InputMap::new(InputConfig::from_ron(INPUT_CONFIG).unwrap());
+    assert!(input.on_window_event(&mouse_input(MouseButton::Left, ElementState::Pressed)));
+    assert!(input.is_down("fire"));
+    assert!(input.was_pressed("fire"));
+    assert!(!input.was_released("fire"));
+    assert!(!input.is_down("jump"));
+
+    input.end_frame();
+    assert!(input.is_down("fire"));
+    assert!(!input.was_pressed("fire"));
+    // repeated presses are ignored
+    input.on_window_event(&mouse_input(MouseButton::Left, ElementState::Pressed));
+    assert!(!input.was_pressed("fire"));
+
+    input.end_frame();
+    input.on_window_event(&mouse_input(MouseButton::Left, ElementState::Released));
+    assert!(!input.is_down("fire"));
+    assert!(input.was_released("fire"));
+    // releasing a button that isn't down does nothing
+    input.on_window_event(&mouse_input(MouseButton::Right, ElementState::Released));
+    assert!(!input.was_released("jump"));
+
+    // pressed and released within the same frame
+    input.end_frame();
+    input.on_window_event(&mouse_input(MouseButton::Right, ElementState::Pressed));
+    input.on_window_event(&mouse_input(MouseButton::Right, ElementState::Released));
+    assert!(!input.is_down("jump"));
+    assert!(input.was_pressed("jump"));
+    assert!(input.was_released("jump"));
+
+    // unknown actions are never active
+    assert!(!input.is_down("fly"));
+    assert!(!input.was_pressed("fly"));
+}
+
+#[test]
+fn input_focus_lost() {
+    use blade_helpers::input::{InputConfig, InputMap};
+    use winit::event::{ElementState, MouseButton, WindowEvent};
+    let mut input = InputMap::new(InputConfig::from_ron(INPUT_CONFIG).unwrap());
+    input.on_window_event(&mouse_input(MouseButton::Forward, ElementState::Pressed));
+    input.on_window_event(&mouse_input(MouseButton::Left, ElementState::Pressed));
+    assert_eq!(input.axis("move_x"), 1.0);
+    input.end_frame();
+
+    assert!(input.on_window_event(&WindowEvent::Focused(false)));
+    assert!(!input.is_down("fire"));
+    assert!(input.was_released("fire"));
+    assert_eq!(input.axis("move_x"), 0.0);
+    input.end_frame();
+    assert!(!input.was_released("fire"));
+}
+
+#[test]
+fn input_axes() {
+    use blade_helpers::input::{InputConfig, InputMap};
+    use winit::{
+        dpi::PhysicalPosition,
+        event::{DeviceEvent, ElementState, MouseButton, WindowEvent},
+    };
+    let mut input = InputMap::new(InputConfig::from_ron(INPUT_CONFIG).unwrap());
+    input.on_window_event(&mouse_input(MouseButton::Back, ElementState::Pressed));
+    input.on_window_event(&mouse_input(MouseButton::Forward, ElementState::Pressed));
+    assert_eq!(input.axis("move_x"), 0.0);
+    input.on_window_event(&mouse_input(MouseButton::Forward, ElementState::Released));
+    assert_eq!(input.axis("move_x"), -1.0);
+
+    let cursor_moved = |x| WindowEvent::CursorMoved {
+        device_id: winit::event::DeviceId::dummy(),
+        position: PhysicalPosition::new(x, 0.0),
+    };
+    input.on_window_event(&cursor_moved(10.0));
+    input.on_window_event(&cursor_moved(14.0));
+    input.set_analog("stick_x", 0.25);
+    assert_eq!(input.axis("look_x"), 2.25);
+    input.end_frame();
+    assert_eq!(input.axis("look_x"), 0.25);
+
+    // raw motion takes over the cursor
+    input.on_device_event(&DeviceEvent::MouseMotion { delta: (1.0, 0.0) });
+    input.on_window_event(&cursor_moved(100.0));
+    assert_eq!(input.axis("look_x"), 0.75);
+    assert_eq!(input.axis("look_y"), 0.0);
+}
+
+#[test]
+fn profiler_stats() {
+    use blade_helpers::{Profiler, TimingSource};
+    use std::time::Duration;
+    let mut profiler = Profiler::new(4);
+    for millis in 1..=5 {
+        drop(profiler.scope("update"));
+        // summed up within a frame
+        drop(profiler.scope("update"));
+        let timings = [("pass".to_string(), Duration::from_millis(millis))];
+        profiler.end_frame(&timings);
+    }
+
+    let stats = profiler.stats();
+    assert_eq!(stats.len(), 2);
+    let gpu = &stats[0];
+    assert_eq!((gpu.name.as_str(), gpu.source), ("pass", TimingSource::Gpu));
+    // the first frame is out of the history
+    assert_eq!(gpu.count, 4);
+    assert_eq!(gpu.average, Duration::from_micros(3500));
+    assert_eq!(gpu.p50, Duration::from_millis(4));
+    assert_eq!(gpu.p95, Duration::from_millis(5));
+    assert_eq!(gpu.p99, Duration::from_millis(5));
+    assert_eq!(gpu.max, Duration::from_millis(5));
+    let cpu = &stats[1];
+    assert_eq!(
+        (cpu.name.as_str(), cpu.source),
+        ("update", TimingSource::Cpu)
+    );
+    assert_eq!(cpu.count, 4);
+
+    profiler.paused = true;
+    profiler.end_frame(&[]);
+    assert_eq!(profiler.stats()[0].count, 4);
+    profiler.clear();
+    assert!(profiler.stats().is_empty());
+}
+
+#[test]
+fn profiler_percentiles() {
+    use blade_helpers::Profiler;
+    use std::time::Duration;
+    let mut profiler = Profiler::new(100);
+    // shuffled to check the sorting
+    for millis in (1..=100).rev().step_by(2).chain((1..=100).step_by(2)) {
+        let timings = [("pass".to_string(), Duration::from_millis(millis))];
+        profiler.end_frame(&timings);
+    }
+    let stats = &profiler.stats()[0];
+    assert_eq!(stats.count, 100);
+    assert_eq!(stats.p50, Duration::from_millis(51));
+    assert_eq!(stats.p95, Duration::from_millis(95));
+    assert_eq!(stats.p99, Duration::from_millis(99));
+    assert_eq!(stats.max, Duration::from_millis(100));
+}
+
+#[test]
+fn profiler_chrome_trace() {
+    let mut profiler = blade_helpers::Profiler::default();
+    drop(profiler.scope("say \"hi\"\\\n"));
+    profiler.end_frame(&[("gpu\tpass".to_string(), std::time::Duration::from_millis(1))]);
+    let mut out = Vec::new();
+    profiler.write_chrome_trace(&mut out).unwrap();
+    let trace = String::from_utf8(out).unwrap();
+    assert!(trace.starts_with("{\"traceEvents\":["));
+    assert!(trace.ends_with("\n]}\n"));
+    assert!(trace.contains("{\"name\":\"say \\\"hi\\\"\\\\\\u000a\",\"ph\":\"X\""));
+    assert!(trace.contains("{\"name\":\"gpu\\u0009pass\",\"ph\":\"X\",\"pid\":1,\"tid\":0,"));
+    assert!(trace.contains("\"args\":{\"name\":\"GPU\"}"));
+}
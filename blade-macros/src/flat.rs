@@ -1,109 +1,357 @@
 use proc_macro::TokenStream;
-use quote::quote;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+
+struct FieldInfo {
+    ty: syn::Type,
+    /// Stored by the raw bytes, for `Copy` types not implementing `Flat`.
+    is_copy: bool,
+}
+
+impl FieldInfo {
+    fn parse(field: &syn::Field) -> syn::Result<Self> {
+        let mut is_copy = false;
+        for attr in field.attrs.iter() {
+            if attr.path().is_ident("flat") {
+                attr.parse_nested_meta(|meta| {
+                    if meta.path.is_ident("copy") {
+                        is_copy = true;
+                        Ok(())
+                    } else {
+                        Err(meta.error("Unknown `flat` attribute"))
+                    }
+                })?;
+            }
+        }
+        Ok(Self {
+            ty: field.ty.clone(),
+            is_copy,
+        })
+    }
+
+    fn alignment(&self) -> TokenStream2 {
+        let ty = &self.ty;
+        if self.is_copy {
+            quote! { std::mem::align_of::<#ty>() }
+        } else {
+            quote! { <#ty as blade_asset::Flat>::ALIGNMENT }
+        }
+    }
+
+    /// Size of a value, given an expression of a reference to it.
+    fn size(&self, value: &TokenStream2) -> TokenStream2 {
+        let ty = &self.ty;
+        if self.is_copy {
+            quote! { std::mem::size_of::<#ty>() }
+        } else {
+            quote! { <#ty as blade_asset::Flat>::size(#value) }
+        }
+    }
+
+    /// Write a value at the `ptr`, advancing it.
+    fn write(&self, value: &TokenStream2) -> TokenStream2 {
+        let ty = &self.ty;
+        let align = self.alignment();
+        let size = self.size(value);
+        let write = if self.is_copy {
+            quote! { std::ptr::write(ptr as *mut #ty, *#value) }
+        } else {
+            quote! { <#ty as blade_asset::Flat>::write(#value, ptr) }
+        };
+        quote! {
+            ptr = ptr.add(ptr.align_offset(#align));
+            #write;
+            ptr = ptr.add(#size);
+        }
+    }
+
+    /// Read a value from the `ptr`, advancing it.
+    fn read(&self) -> TokenStream2 {
+        let ty = &self.ty;
+        let align = self.alignment();
+        let size = self.size(&quote!(&value));
+        let read = if self.is_copy {
+            quote! { std::ptr::read(ptr as *const #ty) }
+        } else {
+            quote! { <#ty as blade_asset::Flat>::read(ptr) }
+        };
+        quote! {
+            {
+                ptr = ptr.add(ptr.align_offset(#align));
+                let value = #read;
+                ptr = ptr.add(#size);
+                value
+            }
+        }
+    }
+}
+
+fn max_alignment<'a>(
+    mut expr: TokenStream2,
+    fields: impl Iterator<Item = &'a FieldInfo>,
+) -> TokenStream2 {
+    for field in fields {
+        let align = field.alignment();
+        expr = quote! {
+            [#expr, #align][(#expr < #align) as usize]
+        };
+    }
+    expr
+}
+
+fn sum_sizes<'a>(
+    mut expr: TokenStream2,
+    fields: impl Iterator<Item = (&'a FieldInfo, TokenStream2)>,
+) -> TokenStream2 {
+    for (field, value) in fields {
+        let align = field.alignment();
+        let size = field.size(&value);
+        expr = quote! {
+            blade_asset::round_up(#expr, #align) + #size
+        };
+    }
+    expr
+}
+
+/// Fields of a struct or an enum variant.
+struct FieldList {
+    infos: Vec<FieldInfo>,
+    /// Names of the named fields, or `None` for a tuple.
+    names: Option<Vec<syn::Ident>>,
+}
+
+impl FieldList {
+    fn parse(fields: &syn::Fields) -> syn::Result<Self> {
+        let infos = fields
+            .iter()
+            .map(FieldInfo::parse)
+            .collect::<syn::Result<Vec<_>>>()?;
+        let names = match *fields {
+            syn::Fields::Named(ref named) => Some(
+                named
+                    .named
+                    .iter()
+                    .map(|field| field.ident.clone().unwrap())
+                    .collect(),
+            ),
+            syn::Fields::Unnamed(_) | syn::Fields::Unit => None,
+        };
+        Ok(Self { infos, names })
+    }
+
+    /// Names of the variables the fields are bound to in a pattern.
+    fn bindings(&self) -> Vec<syn::Ident> {
+        match self.names {
+            Some(ref names) => names.clone(),
+            None => (0..self.infos.len())
+                .map(|i| format_ident!("field{}", i))
+                .collect(),
+        }
+    }
+
+    /// Pattern binding all the fields by reference.
+    fn pattern(&self, path: TokenStream2) -> TokenStream2 {
+        let bindings = self.bindings();
+        match self.names {
+            Some(_) => quote! { #path { #(ref #bindings),* } },
+            None if self.infos.is_empty() => path,
+            None => quote! { #path ( #(ref #bindings),* ) },
+        }
+    }
+
+    /// Expression constructing the value by reading all the fields.
+    fn construct(&self, path: TokenStream2) -> TokenStream2 {
+        let reads = self.infos.iter().map(FieldInfo::read);
+        match self.names {
+            Some(ref names) => quote! { #path { #(#names: #reads,)* } },
+            None if self.infos.is_empty() => path,
+            None => quote! { #path ( #(#reads,)* ) },
+        }
+    }
+
+    fn size(&self, base: TokenStream2) -> TokenStream2 {
+        let bindings = self.bindings();
+        sum_sizes(
+            base,
+            self.infos
+                .iter()
+                .zip(bindings.iter().map(|name| quote!(#name))),
+        )
+    }
+
+    fn write(&self) -> TokenStream2 {
+        let bindings = self.bindings();
+        let writes = self
+            .infos
+            .iter()
+            .zip(bindings.iter())
+            .map(|(info, name)| info.write(&quote!(#name)));
+        quote! { #(#writes)* }
+    }
+}
+
+fn generate_transparent(name: &syn::Ident, generics: &[syn::Lifetime]) -> proc_macro2::TokenStream {
+    quote! {
+        impl<#(#generics),*> blade_asset::Flat for #name<#(#generics),*> {
+            const ALIGNMENT: usize = std::mem::align_of::<Self>();
+            const FIXED_SIZE: Option<std::num::NonZeroUsize> = std::num::NonZeroUsize::new(std::mem::size_of::<Self>());
+            unsafe fn write(&self, mut ptr: *mut u8) {
+                std::ptr::write(ptr as *mut Self, *self);
+            }
+            unsafe fn read(mut ptr: *const u8) -> Self {
+                std::ptr::read(ptr as *const Self)
+            }
+        }
+    }
+}
+
+fn generate_struct(
+    name: &syn::Ident,
+    generics: &[syn::Lifetime],
+    fields: &FieldList,
+) -> proc_macro2::TokenStream {
+    let expr_alignment = max_alignment(quote!(1), fields.infos.iter());
+    let pattern = fields.pattern(quote!(Self));
+    let expr_size = fields.size(quote!(0));
+    let st_write = fields.write();
+    let construct = fields.construct(quote!(Self));
+
+    quote! {
+        impl<#(#generics),*> blade_asset::Flat for #name<#(#generics),*> {
+            const ALIGNMENT: usize = #expr_alignment;
+            //Note: this could be improved if we see all fields being `FIXED_SIZE`
+            const FIXED_SIZE: Option<std::num::NonZeroUsize> = None;
+            fn size(&self) -> usize {
+                let #pattern = *self;
+                #expr_size
+            }
+            unsafe fn write(&self, mut ptr: *mut u8) {
+                let #pattern = *self;
+                #st_write
+            }
+            unsafe fn read(mut ptr: *const u8) -> Self {
+                #construct
+            }
+        }
+    }
+}
+
+fn generate_enum(
+    name: &syn::Ident,
+    generics: &[syn::Lifetime],
+    variants: &[(syn::Ident, FieldList)],
+) -> proc_macro2::TokenStream {
+    // The variant is identified by a `u32` tag, followed by its fields.
+    let tag_size = quote!(std::mem::size_of::<u32>());
+    let expr_alignment = max_alignment(
+        quote!(std::mem::align_of::<u32>()),
+        variants.iter().flat_map(|(_, fields)| fields.infos.iter()),
+    );
+
+    let mut size_arms = Vec::new();
+    let mut write_arms = Vec::new();
+    let mut read_arms = Vec::new();
+    for (index, (variant, fields)) in variants.iter().enumerate() {
+        let tag = index as u32;
+        let pattern = fields.pattern(quote!(Self::#variant));
+        let expr_size = fields.size(tag_size.clone());
+        let st_write = fields.write();
+        let construct = fields.construct(quote!(Self::#variant));
+        size_arms.push(quote! {
+            #pattern => #expr_size,
+        });
+        write_arms.push(quote! {
+            #pattern => {
+                std::ptr::write(ptr as *mut u32, #tag);
+                ptr = ptr.add(#tag_size);
+                #st_write
+            }
+        });
+        read_arms.push(quote! {
+            #tag => #construct,
+        });
+    }
+
+    quote! {
+        impl<#(#generics),*> blade_asset::Flat for #name<#(#generics),*> {
+            const ALIGNMENT: usize = #expr_alignment;
+            const FIXED_SIZE: Option<std::num::NonZeroUsize> = None;
+            fn size(&self) -> usize {
+                match *self {
+                    #(#size_arms)*
+                }
+            }
+            unsafe fn write(&self, mut ptr: *mut u8) {
+                match *self {
+                    #(#write_arms)*
+                }
+            }
+            unsafe fn read(mut ptr: *const u8) -> Self {
+                let tag = std::ptr::read(ptr as *const u32);
+                ptr = ptr.add(#tag_size);
+                match tag {
+                    #(#read_arms)*
+                    _ => unreachable!("Unknown variant tag {}", tag),
+                }
+            }
+        }
+    }
+}
 
 pub fn generate(input_stream: TokenStream) -> syn::Result<proc_macro2::TokenStream> {
-    let item_struct = syn::parse::<syn::ItemStruct>(input_stream)?;
+    let input = syn::parse::<syn::DeriveInput>(input_stream)?;
 
-    let struct_name = item_struct.ident;
     let mut generics = Vec::new();
-    for param in item_struct.generics.params {
+    for param in input.generics.params {
         match param {
             syn::GenericParam::Lifetime(lt) => {
                 generics.push(lt.lifetime);
             }
             syn::GenericParam::Type(_) | syn::GenericParam::Const(_) => {
                 return Err(syn::Error::new(
-                    item_struct.struct_token.span,
+                    input.ident.span(),
                     "Unsupported generic parameters",
                 ))
             }
         }
     }
 
-    Ok(match item_struct.fields {
-        syn::Fields::Unnamed(_) => {
-            let is_transparent = item_struct.attrs.iter().any(|attr| {
+    Ok(match input.data {
+        syn::Data::Struct(ref data) => {
+            let is_transparent = input.attrs.iter().any(|attr| {
                 if !attr.path().is_ident("repr") {
                     return false;
                 }
-                let value = attr.parse_args::<syn::Ident>().unwrap();
-                value == "transparent"
-            });
-            if !is_transparent {
-                return Err(syn::Error::new(
-                    item_struct.struct_token.span,
-                    "Only `repr(transparent)` wrappers are supported",
-                ));
-            }
-
-            quote! {
-                impl<#(#generics),*> blade_asset::Flat for #struct_name<#(#generics),*> {
-                    const ALIGNMENT: usize = std::mem::size_of::<Self>();
-                    const FIXED_SIZE: Option<std::num::NonZeroUsize> = std::num::NonZeroUsize::new(std::mem::size_of::<Self>());
-                    unsafe fn write(&self, mut ptr: *mut u8) {
-                        std::ptr::write(ptr as *mut Self, *self);
-                    }
-                    unsafe fn read(mut ptr: *const u8) -> Self {
-                        std::ptr::read(ptr as *const Self)
-                    }
+                match attr.parse_args::<syn::Ident>() {
+                    Ok(value) => value == "transparent",
+                    Err(_) => false,
                 }
+            });
+            if is_transparent {
+                generate_transparent(&input.ident, &generics)
+            } else {
+                let fields = FieldList::parse(&data.fields)?;
+                generate_struct(&input.ident, &generics, &fields)
             }
         }
-        syn::Fields::Named(ref fields) => {
-            let mut expr_alignment = quote!(0);
-            let mut expr_size = quote!(0);
-            let mut st_write = Vec::new();
-            let mut init_read = Vec::new();
-
-            for field in fields.named.iter() {
-                let name = field.ident.as_ref().unwrap();
-                let ty = &field.ty;
-
-                let align = quote! { <#ty as blade_asset::Flat>::ALIGNMENT };
-                expr_alignment = quote! {
-                    [#expr_alignment, #align][(#expr_alignment < #align) as usize]
-                };
-                expr_size = quote! {
-                    blade_asset::round_up(#expr_size, #align) + self.#name.size()
-                };
-                st_write.push(quote! {
-                    ptr = ptr.add(ptr.align_offset(#align));
-                    self.#name.write(ptr);
-                    ptr = ptr.add(self.#name.size());
-                });
-                init_read.push(quote! {
-                    #name: {
-                        ptr = ptr.add(ptr.align_offset(#align));
-                        let value = <#ty as blade_asset::Flat>::read(ptr);
-                        ptr = ptr.add(value.size());
-                        value
-                    },
-                });
-            }
-
-            quote! {
-                impl<#(#generics),*> blade_asset::Flat for #struct_name<#(#generics),*> {
-                    const ALIGNMENT: usize = #expr_alignment;
-                    //Note: this could be improved if we see all fields being `FIXED_SIZE`
-                    const FIXED_SIZE: Option<std::num::NonZeroUsize> = None;
-                    fn size(&self) -> usize {
-                        #expr_size
-                    }
-                    unsafe fn write(&self, mut ptr: *mut u8) {
-                        #(#st_write)*
-                    }
-                    unsafe fn read(mut ptr: *const u8) -> Self {
-                        Self {
-                            #(#init_read)*
-                        }
-                    }
-                }
+        syn::Data::Enum(ref data) => {
+            if data.variants.is_empty() {
+                return Err(syn::Error::new(
+                    input.ident.span(),
+                    "Enums without variants are not supported",
+                ));
             }
+            let variants = data
+                .variants
+                .iter()
+                .map(|variant| Ok((variant.ident.clone(), FieldList::parse(&variant.fields)?)))
+                .collect::<syn::Result<Vec<_>>>()?;
+            generate_enum(&input.ident, &generics, &variants)
         }
-        _ => {
+        syn::Data::Union(_) => {
             return Err(syn::Error::new(
-                item_struct.struct_token.span,
-                "Structure fields must be named",
+                Span::call_site(),
+                "Unions are not supported",
             ))
         }
     })
@@ -60,6 +60,37 @@ pub fn vertex_derive(input: TokenStream) -> TokenStream {
 /// The struct may have a lifetime describing borrowed data members. Borrowing is
 /// needed for zero-copy deserialization.
 ///
+/// Enums are supported as well, with any kind of variants. The variant is stored
+/// as a `u32` tag, followed by the fields of the variant:
+///
+/// ```rust
+/// #[derive(blade_macros::Flat)]
+/// enum Shape {
+///     Point,
+///     Circle(f32),
+///     Polygon { name: Option<String>, points: Vec<[f32; 2]> },
+/// }
+/// ```
+///
+/// Fields of `Copy` types that don't implement `blade_asset::Flat`, such as plain
+/// non-exhaustive enums from 3rd party crates, can be stored by their raw bytes
+/// with the `#[flat(copy)]` attribute:
+///
+/// ```rust
+/// #[derive(Clone, Copy)]
+/// #[non_exhaustive]
+/// enum Foo {
+///     A,
+///     B,
+/// }
+/// #[derive(blade_macros::Flat)]
+/// struct Bar {
+///     #[flat(copy)]
+///     foo: Foo,
+///     count: u32,
+/// }
+/// ```
+///
 /// Alternatively, can be used on a transparent wrapper to force `blade_asset::Flat`
 /// implementation even if the wrapped type doesn't implement it:
 ///
@@ -76,9 +107,8 @@ pub fn vertex_derive(input: TokenStream) -> TokenStream {
 /// struct FooWrap(Foo);
 /// ```
 ///
-/// This can be particularly useful for types like `bytemuck::Pod` implementors,
-/// or plain non-exhaustive enums from 3rd party crates.
-#[proc_macro_derive(Flat)]
+/// This can be particularly useful for types like `bytemuck::Pod` implementors.
+#[proc_macro_derive(Flat, attributes(flat))]
 pub fn flat_derive(input: TokenStream) -> TokenStream {
     let stream = match flat::generate(input) {
         Ok(tokens) => tokens,
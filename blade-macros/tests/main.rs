@@ -56,3 +56,47 @@ fn test_flat_wrap() {
     let other = unsafe { Flat::read(vec.as_ptr()) };
     assert_eq!(foo, other);
 }
+
+#[derive(blade_macros::Flat, Debug, PartialEq)]
+enum FlatEnum<'a> {
+    Empty,
+    Tuple(u8, Vec<u32>),
+    Named {
+        name: String,
+        maybe: Option<f32>,
+        slice: &'a [u16],
+    },
+}
+
+#[derive(blade_macros::Flat, Debug, PartialEq)]
+struct FlatNested<'a> {
+    #[flat(copy)]
+    kind: Foo,
+    variants: Vec<FlatEnum<'a>>,
+    nested: Vec<Vec<u8>>,
+}
+
+#[test]
+fn test_flat_enum() {
+    use blade_asset::Flat;
+
+    let data = FlatNested {
+        kind: Foo::B,
+        variants: vec![
+            FlatEnum::Tuple(1, vec![2, 3]),
+            FlatEnum::Empty,
+            FlatEnum::Named {
+                name: "named".to_string(),
+                maybe: Some(4.0),
+                slice: &[5, 6],
+            },
+        ],
+        nested: vec![vec![7], vec![], vec![8, 9]],
+    };
+    // `usize::div_ceil` is not available on the MSRV
+    #[allow(clippy::manual_div_ceil)]
+    let mut vec = vec![0u64; (data.size() + 7) / 8];
+    unsafe { data.write(vec.as_mut_ptr() as *mut u8) };
+    let other = unsafe { Flat::read(vec.as_ptr() as *const u8) };
+    assert_eq!(data, other);
+}
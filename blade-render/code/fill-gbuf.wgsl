@@ -39,6 +39,8 @@ struct HitEntry {
     normal_scale: f32,
 }
 var<storage, read> hit_entries: array<HitEntry>;
+// Minimal UV footprint of a pixel per texture, as float bits.
+var<storage, read_write> texture_feedback: array<atomic<u32>>;
 
 var<uniform> camera: CameraParams;
 var<uniform> prev_camera: CameraParams;
@@ -61,6 +63,12 @@ fn debug_raw_normal(pos: vec3<f32>, normal_raw: u32, rotation: vec4<f32>, debug_
     debug_line(pos, pos + debug_len * nw, color);
 }
 
+// Mip level of a texture to sample, given the UV footprint of a pixel.
+fn texture_lod(texture_index: u32, uv_per_pixel: f32) -> f32 {
+    let dim = textureDimensions(textures[texture_index], 0);
+    return log2(max(uv_per_pixel * f32(max(dim.x, dim.y)), 1.0));
+}
+
 @compute @workgroup_size(8, 4)
 fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
     if (any(global_id.xy >= camera.target_size)) {
@@ -115,7 +123,16 @@ fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
         let tangent_geo = normalize(mat3x3(decode_normal(vertices[0].tangent), decode_normal(vertices[1].tangent), decode_normal(vertices[2].tangent)) * barycentrics);
         let bitangent_geo = normalize(cross(normal_geo, tangent_geo)) * vertices[0].bitangent_sign;
 
-        let lod = 0.0; //TODO: this is actually complicated
+        // Approximate the footprint of the pixel by the cone of the ray,
+        // scaled by the UV density of the triangle.
+        let world_area = length(cross(positions[1].xyz - positions[0].xyz, positions[2].xyz - positions[0].xyz));
+        let uv_area = abs(determinant(mat2x2(vertices[1].tex_coords - vertices[0].tex_coords, vertices[2].tex_coords - vertices[0].tex_coords)));
+        let pixel_size = intersection.t * camera.fov.y / f32(camera.target_size.y);
+        let cos_theta = max(abs(dot(ray_dir, flat_normal)), 0.1);
+        let uv_per_pixel = pixel_size * sqrt(uv_area / max(world_area, 1e-12)) / cos_theta;
+        // Positive floats are ordered the same way as their bits.
+        atomicMin(&texture_feedback[entry.base_color_texture], bitcast<u32>(uv_per_pixel));
+        atomicMin(&texture_feedback[entry.normal_texture], bitcast<u32>(uv_per_pixel));
 
         let geo_to_world_rot = normalize(unpack4x8snorm(entry.geometry_to_world_rotation));
         let tangent_space_geo = mat3x3(tangent_geo, bitangent_geo, normal_geo);
@@ -123,7 +140,7 @@ fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
         if ((debug.texture_flags & DebugTextureFlags_NORMAL) != 0u) {
             normal_local = vec3<f32>(0.0, 0.0, 1.0); // ignore normal map
         } else {
-            let raw_unorm = textureSampleLevel(textures[entry.normal_texture], sampler_linear, tex_coords, lod).xy;
+            let raw_unorm = textureSampleLevel(textures[entry.normal_texture], sampler_linear, tex_coords, texture_lod(entry.normal_texture, uv_per_pixel)).xy;
             let n_xy = entry.normal_scale * (2.0 * raw_unorm - 1.0);
             normal_local = vec3<f32>(n_xy, sqrt(max(0.0, 1.0 - dot(n_xy, n_xy))));
         }
@@ -169,7 +186,7 @@ fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
         if ((debug.texture_flags & DebugTextureFlags_ALBEDO) != 0u) {
             albedo = base_color_factor.xyz;
         } else {
-            let base_color_sample = textureSampleLevel(textures[entry.base_color_texture], sampler_linear, tex_coords, lod);
+            let base_color_sample = textureSampleLevel(textures[entry.base_color_texture], sampler_linear, tex_coords, texture_lod(entry.base_color_texture, uv_per_pixel));
             albedo = (base_color_factor * base_color_sample).xyz;
         }
 
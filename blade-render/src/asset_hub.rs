@@ -1,11 +1,34 @@
-use blade_asset::AssetManager;
-use std::{path::Path, sync::Arc};
+use blade_asset::{Archive, AssetManager, AssetStatus, CacheConfig, Progress};
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// How often to check all the assets for hot reloading, if they aren't watched.
+const HOT_RELOAD_SCAN_INTERVAL: Duration = Duration::from_secs(1);
 
 /// A single hub to manage all assets.
 pub struct AssetHub {
     pub textures: Arc<AssetManager<crate::texture::Baker>>,
     pub models: AssetManager<crate::model::Baker>,
     pub shaders: AssetManager<crate::shader::Baker>,
+    last_hot_reload_scan: Mutex<Option<Instant>>,
+}
+
+/// Progress of loading the assets of a hub, by type.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LoadProgress {
+    pub textures: Progress,
+    pub models: Progress,
+    pub shaders: Progress,
+}
+
+impl LoadProgress {
+    pub fn total(&self) -> Progress {
+        self.textures + self.models + self.shaders
+    }
 }
 
 pub struct LoadContext<'a> {
@@ -14,58 +37,169 @@ pub struct LoadContext<'a> {
     finish_task: choir::IdleTask,
 }
 
+fn create_shader_baker(gpu_context: Option<&Arc<blade_graphics::Context>>) -> crate::shader::Baker {
+    let mut sh_baker = match gpu_context {
+        Some(context) => crate::shader::Baker::new(context),
+        None => crate::shader::Baker::new_offline(),
+    };
+    sh_baker.register_bool("DEBUG_MODE", cfg!(debug_assertions));
+    sh_baker.register_enum::<crate::render::DebugMode>();
+    sh_baker.register_bitflags::<crate::render::DebugDrawFlags>();
+    sh_baker.register_bitflags::<crate::render::DebugTextureFlags>();
+    sh_baker
+}
+
 impl AssetHub {
     /// Create a new hub.
     pub fn new(
         target: &Path,
         choir: &Arc<choir::Choir>,
         gpu_context: &Arc<blade_graphics::Context>,
+    ) -> Self {
+        Self::with_cache_config(target, choir, gpu_context, CacheConfig::default())
+    }
+
+    /// Create a new hub with a specific configuration of the cooked asset cache.
+    pub fn with_cache_config(
+        target: &Path,
+        choir: &Arc<choir::Choir>,
+        gpu_context: &Arc<blade_graphics::Context>,
+        cache_config: CacheConfig,
+    ) -> Self {
+        Self::with_context(target, choir, Some(gpu_context), cache_config)
+    }
+
+    /// Create a new hub that only cooks the assets, without a GPU.
+    ///
+    /// Loading any of the assets fails, so this is only useful for `bake`.
+    pub fn offline(target: &Path, choir: &Arc<choir::Choir>, cache_config: CacheConfig) -> Self {
+        Self::with_context(target, choir, None, cache_config)
+    }
+
+    fn with_context(
+        target: &Path,
+        choir: &Arc<choir::Choir>,
+        gpu_context: Option<&Arc<blade_graphics::Context>>,
+        cache_config: CacheConfig,
     ) -> Self {
         let _ = std::fs::create_dir_all(target);
-        let textures = Arc::new(AssetManager::new(
+        let textures = Arc::new(AssetManager::with_cache_config(
             target,
             choir,
-            crate::texture::Baker::new(gpu_context),
+            match gpu_context {
+                Some(context) => crate::texture::Baker::new(context),
+                None => crate::texture::Baker::new_offline(),
+            },
+            cache_config.clone(),
         ));
-        let models = AssetManager::new(
+        let models = AssetManager::with_cache_config(
             target,
             choir,
-            crate::model::Baker::new(gpu_context, &textures),
+            match gpu_context {
+                Some(context) => crate::model::Baker::new(context, &textures),
+                None => crate::model::Baker::new_offline(&textures),
+            },
+            cache_config.clone(),
         );
 
-        let mut sh_baker = crate::shader::Baker::new(gpu_context);
-        sh_baker.register_bool("DEBUG_MODE", cfg!(debug_assertions));
-        sh_baker.register_enum::<crate::render::DebugMode>();
-        sh_baker.register_bitflags::<crate::render::DebugDrawFlags>();
-        sh_baker.register_bitflags::<crate::render::DebugTextureFlags>();
-        let shaders = AssetManager::new(target, choir, sh_baker);
+        let shaders = AssetManager::with_cache_config(
+            target,
+            choir,
+            create_shader_baker(gpu_context),
+            cache_config,
+        );
 
         Self {
             textures,
             models,
             shaders,
+            last_hot_reload_scan: Mutex::new(None),
+        }
+    }
+
+    /// Create a new hub that loads all the assets from an archive,
+    /// without needing the sources.
+    ///
+    /// The `cache_config` has to have the source root of the application,
+    /// if the archive was cooked relative to a source root.
+    pub fn with_archive(
+        archive: &Arc<Archive>,
+        choir: &Arc<choir::Choir>,
+        gpu_context: &Arc<blade_graphics::Context>,
+        cache_config: CacheConfig,
+    ) -> Self {
+        let textures = Arc::new(AssetManager::with_archive(
+            archive,
+            choir,
+            crate::texture::Baker::new(gpu_context),
+            cache_config.clone(),
+        ));
+        let models = AssetManager::with_archive(
+            archive,
+            choir,
+            crate::model::Baker::new(gpu_context, &textures),
+            cache_config.clone(),
+        );
+        let shaders = AssetManager::with_archive(
+            archive,
+            choir,
+            create_shader_baker(Some(gpu_context)),
+            cache_config,
+        );
+
+        Self {
+            textures,
+            models,
+            shaders,
+            last_hot_reload_scan: Mutex::new(None),
         }
     }
 
     /// Flush the GPU state updates into the specified command encoder.
     ///
-    /// Populates the list of temporary buffers that can be freed when the
-    /// relevant submission is completely retired.
+    /// Populates the temporary resources that can be freed when the
+    /// relevant submission is completely retired. These include the
+    /// resources of the deleted assets, which the GPU may still be using.
     #[profiling::function]
     pub fn flush(
         &self,
         command_encoder: &mut blade_graphics::CommandEncoder,
-        temp_buffers: &mut Vec<blade_graphics::Buffer>,
+        temp: &mut crate::FrameResources,
     ) {
-        self.textures.baker.flush(command_encoder, temp_buffers);
-        self.models.baker.flush(command_encoder, temp_buffers);
+        self.textures.baker.flush(command_encoder, temp);
+        self.models.baker.flush(command_encoder, temp);
+    }
+
+    /// Start watching the sources of all assets for changes,
+    /// so that hot reloading only considers the changed ones.
+    #[cfg(feature = "watch")]
+    pub fn watch(&self) -> Result<(), blade_asset::Error> {
+        self.textures.watch()?;
+        self.models.watch()?;
+        self.shaders.watch()?;
+        Ok(())
+    }
+
+    /// Delete the unused assets, see `AssetManager::collect_unused`.
+    ///
+    /// The textures of the models are referenced strongly, so they are
+    /// collected after the models that used them.
+    /// Returns the number of deleted assets.
+    pub fn collect_unused(&self) -> usize {
+        self.models.collect_unused()
+            + self.textures.collect_unused()
+            + self.shaders.collect_unused()
     }
 
     /// Destroy the hub contents.
+    ///
+    /// The GPU has to be done with all the assets.
     pub fn destroy(&mut self) {
         self.textures.clear();
         self.models.clear();
         self.shaders.clear();
+        self.textures.baker.free_retired();
+        self.models.baker.free_retired();
     }
 
     pub fn open_context<'a, N: Into<choir::Name>>(
@@ -88,6 +222,73 @@ impl AssetHub {
         self.shaders.list_running_tasks(&mut list);
         list
     }
+
+    /// Hot reload the changed textures and models.
+    ///
+    /// The models using the changed textures are served again.
+    /// The model handles are updated in place, and they may repeat.
+    /// Returns the tasks of reloading. The old models and textures
+    /// are destroyed with the resources of the next `flush`.
+    ///
+    /// If the sources aren't watched, all the assets are checked,
+    /// so this is only done once in a while.
+    pub fn hot_reload_models<'a>(
+        &self,
+        models: impl IntoIterator<Item = &'a mut blade_asset::Handle<crate::Model>>,
+    ) -> Vec<choir::RunningTask> {
+        let changed_models = self.models.changed();
+        if changed_models.is_none() || self.textures.changed().is_none() {
+            let now = Instant::now();
+            let mut last_scan = self.last_hot_reload_scan.lock().unwrap();
+            if let Some(time) = *last_scan {
+                if now.duration_since(time) < HOT_RELOAD_SCAN_INTERVAL {
+                    return Vec::new();
+                }
+            }
+            *last_scan = Some(now);
+        }
+
+        let mut changes = blade_asset::ChangeSet::default();
+        self.textures.hot_reload_all(&mut changes);
+        let mut tasks = changes.tasks().to_vec();
+
+        let mut reloaded = HashMap::new();
+        for handle in models {
+            if let Some(&new_handle) = reloaded.get(&*handle) {
+                *handle = new_handle;
+                continue;
+            }
+            let old_handle = *handle;
+            let task = match changed_models {
+                Some(ref list) if !list.contains(&*handle) => None,
+                _ => self.models.hot_reload(handle),
+            }
+            .or_else(|| self.models.hot_reload_dependent(handle, &changes));
+            if let Some(task) = task {
+                tasks.push(task.clone());
+                reloaded.insert(old_handle, *handle);
+            }
+        }
+        tasks
+    }
+
+    /// Count the assets in each stage of loading.
+    pub fn progress(&self) -> LoadProgress {
+        LoadProgress {
+            textures: self.textures.progress(),
+            models: self.models.progress(),
+            shaders: self.shaders.progress(),
+        }
+    }
+
+    /// List the status of all the assets.
+    pub fn list_status(&self) -> Vec<AssetStatus> {
+        let mut list = Vec::new();
+        self.textures.list_status(&mut list);
+        self.models.list_status(&mut list);
+        self.shaders.list_status(&mut list);
+        list
+    }
 }
 
 impl LoadContext<'_> {
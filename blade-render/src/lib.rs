@@ -23,6 +23,9 @@ pub use render::*;
 pub use shader::Shader;
 pub use texture::Texture;
 
+/// Error of serving an asset by a baker created without a GPU context.
+const NO_GPU: &str = "Unable to serve assets without a GPU context";
+
 // Has to match the `Vertex` in shaders
 #[repr(C)]
 #[derive(Clone, Copy, Debug, Default, bytemuck::Zeroable, bytemuck::Pod)]
@@ -7,8 +7,6 @@ use std::{
     sync::{Arc, Mutex},
 };
 
-const PRELOAD_TEXTURES: bool = false;
-
 const META_BASE_COLOR: crate::texture::Meta = crate::texture::Meta {
     format: blade_graphics::TextureFormat::Bc1UnormSrgb,
     generate_mips: true,
@@ -59,6 +57,8 @@ pub struct Model {
     pub index_buffer: blade_graphics::Buffer,
     pub transform_buffer: blade_graphics::Buffer,
     pub acceleration_structure: blade_graphics::AccelerationStructure,
+    // Keeps the textures of the materials alive while the model is.
+    _textures: Vec<blade_asset::StrongHandle<crate::Texture>>,
 }
 
 #[derive(blade_macros::Flat, Default)]
@@ -183,6 +183,29 @@ pub struct CookedModel<'a> {
     geometries: Vec<CookedGeometry<'a>>,
 }
 
+impl CookedModel<'_> {
+    /// List the textures referenced by path, with the metadata they are loaded with.
+    ///
+    /// The embedded textures are cooked together with the model, so they aren't listed.
+    pub fn texture_paths(&self) -> Vec<(String, crate::texture::Meta)> {
+        let mut list = Vec::new();
+        for material in self.materials.iter() {
+            let textures = [
+                (&material.base_color, META_BASE_COLOR),
+                (&material.normal, META_NORMAL),
+            ];
+            for (texture, meta) in textures {
+                if let Ok(path) = str::from_utf8(&texture.path) {
+                    if !path.is_empty() {
+                        list.push((path.to_string(), meta));
+                    }
+                }
+            }
+        }
+        list
+    }
+}
+
 #[cfg(feature = "asset")]
 impl CookedModel<'_> {
     fn populate_gltf(
@@ -191,7 +214,7 @@ impl CookedModel<'_> {
         parent_transform: glam::Mat4,
         data_buffers: &[Vec<u8>],
         flattened_geos: &mut Vec<FlattenedGeometry>,
-    ) {
+    ) -> Result<(), blade_asset::Error> {
         let local_transform = glam::Mat4::from_cols_array_2d(&g_node.transform().matrix());
         let global_transform = parent_transform * local_transform;
 
@@ -228,19 +251,22 @@ impl CookedModel<'_> {
                 };
 
                 let reader = g_primitive.reader(|buffer| Some(&data_buffers[buffer.index()]));
-                let vertex_count = g_primitive.get(&gltf::Semantic::Positions).unwrap().count();
+                let vertex_count = match g_primitive.get(&gltf::Semantic::Positions) {
+                    Some(accessor) => accessor.count(),
+                    None => return Err(format!("Geometry {name} doesn't have positions").into()),
+                };
 
                 // Read the vertices into memory
                 flattened_geos.push({
                     profiling::scope!("Read data");
                     let mut pre_vertices = vec![GltfVertex::default(); vertex_count];
 
-                    for (v, pos) in pre_vertices
-                        .iter_mut()
-                        .zip(reader.read_positions().unwrap())
-                    {
-                        for component in pos {
-                            assert!(component.is_finite());
+                    let positions = reader
+                        .read_positions()
+                        .ok_or_else(|| format!("Unable to read positions of {name}"))?;
+                    for (v, pos) in pre_vertices.iter_mut().zip(positions) {
+                        if !pos.iter().all(|component| component.is_finite()) {
+                            return Err(format!("Geometry {name} has non-finite positions").into());
                         }
                         v.position = pos;
                     }
@@ -252,14 +278,16 @@ impl CookedModel<'_> {
                         log::warn!("No tex coords in {name}");
                     }
                     if let Some(iter) = reader.read_normals() {
-                        assert_eq!(
-                            pre_vertices.len(),
-                            iter.len(),
-                            "geometry {name} doesn't have enough normals"
-                        );
+                        if pre_vertices.len() != iter.len() {
+                            return Err(
+                                format!("Geometry {name} doesn't have enough normals").into()
+                            );
+                        }
                         for (v, normal) in pre_vertices.iter_mut().zip(iter) {
+                            if encode_normal(normal) == 0 {
+                                return Err(format!("Geometry {name} has zero normals").into());
+                            }
                             v.normal = normal;
-                            assert_ne!(encode_normal(normal), 0);
                         }
                     } else {
                         log::warn!("No normals in {name}");
@@ -269,8 +297,12 @@ impl CookedModel<'_> {
                     match reader.read_indices() {
                         Some(read) => FlattenedGeometry(
                             read.into_u32()
-                                .map(|i| pre_vertices[i as usize].clone())
-                                .collect(),
+                                .map(|i| {
+                                    pre_vertices.get(i as usize).cloned().ok_or_else(|| {
+                                        format!("Geometry {name} has index {i} out of bounds")
+                                    })
+                                })
+                                .collect::<Result<_, _>>()?,
                         ),
                         None => FlattenedGeometry(pre_vertices.into_boxed_slice()),
                     }
@@ -287,12 +319,14 @@ impl CookedModel<'_> {
         }
 
         for child in g_node.children() {
-            self.populate_gltf(child, global_transform, data_buffers, flattened_geos);
+            self.populate_gltf(child, global_transform, data_buffers, flattened_geos)?;
         }
+        Ok(())
     }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
 pub enum FrontFace {
     Clockwise,
     CounterClockwise,
@@ -333,6 +367,8 @@ struct BlasConstruct {
 struct PendingOperations {
     transfers: Vec<Transfer>,
     blas_constructs: Vec<BlasConstruct>,
+    // Resources of the deleted models, which the GPU may still be using.
+    retired: crate::FrameResources,
 }
 
 enum TextureSource {
@@ -345,21 +381,22 @@ enum TextureSource {
 
 #[cfg(feature = "asset")]
 impl TextureReference<'_> {
-    fn complete(&mut self, sources: &slab::Slab<TextureSource>) {
+    fn complete(&mut self, sources: &slab::Slab<TextureSource>) -> Result<(), blade_asset::Error> {
         match sources.get(self.source_index) {
             Some(&TextureSource::Embedded(ref _task, ref sub_cooker)) => {
-                self.embedded_data = Cow::Owned(sub_cooker.extract_embedded());
+                self.embedded_data = Cow::Owned(sub_cooker.extract_embedded()?);
             }
             Some(&TextureSource::Path(ref full)) => {
                 self.path = Cow::Owned(full.as_bytes().to_owned());
             }
             None => {}
         }
+        Ok(())
     }
 }
 
 pub struct Baker {
-    gpu_context: Arc<blade_graphics::Context>,
+    gpu_context: Option<Arc<blade_graphics::Context>>,
     pending_operations: Mutex<PendingOperations>,
     //TODO: change to asset materials
     asset_textures: Arc<blade_asset::AssetManager<crate::texture::Baker>>,
@@ -371,18 +408,34 @@ impl Baker {
         asset_textures: &Arc<blade_asset::AssetManager<crate::texture::Baker>>,
     ) -> Self {
         Self {
-            gpu_context: Arc::clone(gpu_context),
+            gpu_context: Some(Arc::clone(gpu_context)),
+            pending_operations: Mutex::new(PendingOperations::default()),
+            asset_textures: Arc::clone(asset_textures),
+        }
+    }
+
+    /// Create a baker that only cooks the models, without a GPU.
+    pub fn new_offline(
+        asset_textures: &Arc<blade_asset::AssetManager<crate::texture::Baker>>,
+    ) -> Self {
+        Self {
+            gpu_context: None,
             pending_operations: Mutex::new(PendingOperations::default()),
             asset_textures: Arc::clone(asset_textures),
         }
     }
 
+    fn gpu(&self) -> &blade_graphics::Context {
+        self.gpu_context.as_deref().expect(crate::NO_GPU)
+    }
+
     pub fn flush(
         &self,
         encoder: &mut blade_graphics::CommandEncoder,
-        temp_buffers: &mut Vec<blade_graphics::Buffer>,
+        temp: &mut crate::FrameResources,
     ) {
         let mut pending_ops = self.pending_operations.lock().unwrap();
+        temp.append(&mut pending_ops.retired);
         if !pending_ops.transfers.is_empty() {
             let mut pass = encoder.transfer("init models");
             for transfer in pending_ops.transfers.drain(..) {
@@ -391,18 +444,26 @@ impl Baker {
                     transfer.dst.into(),
                     transfer.size,
                 );
-                temp_buffers.push(transfer.stage);
+                temp.buffers.push(transfer.stage);
             }
         }
         if !pending_ops.blas_constructs.is_empty() {
             let mut pass = encoder.acceleration_structure("BLAS");
             for construct in pending_ops.blas_constructs.drain(..) {
                 pass.build_bottom_level(construct.dst, &construct.meshes, construct.scratch.into());
-                temp_buffers.push(construct.scratch);
+                temp.buffers.push(construct.scratch);
             }
         }
     }
 
+    /// Destroy the resources of the deleted models right away,
+    /// which the GPU has to be done with.
+    pub(crate) fn free_retired(&self) {
+        if let Some(ref gpu) = self.gpu_context {
+            self.pending_operations.lock().unwrap().retired.free(gpu);
+        }
+    }
+
     #[cfg(feature = "asset")]
     fn cook_texture(
         &self,
@@ -410,34 +471,40 @@ impl Baker {
         meta: super::texture::Meta,
         parent_cooker: &blade_asset::Cooker<Baker>,
         data_buffers: &[Vec<u8>],
-    ) -> TextureSource {
-        match texture.source().source() {
+    ) -> Result<TextureSource, blade_asset::Error> {
+        Ok(match texture.source().source() {
             gltf::image::Source::View { view, mime_type } => {
                 let sub_cooker = Arc::new(blade_asset::Cooker::new_embedded());
                 let cooker = Arc::clone(&sub_cooker);
                 let baker = Arc::clone(&self.asset_textures.baker);
                 let buffer = &data_buffers[view.buffer().index()];
                 let data = buffer[view.offset()..view.offset() + view.length()].to_vec();
-                let extension = mime_type.split_once('/').unwrap().1.to_string();
+                let extension = match mime_type.split_once('/') {
+                    Some((_, extension)) => extension.to_string(),
+                    None => return Err(format!("Unknown texture MIME type: {mime_type}").into()),
+                };
                 let task =
                     self.asset_textures
                         .choir
                         .spawn("embedded cook")
                         .init(move |exe_ontext| {
-                            blade_asset::Baker::cook(
+                            let result = blade_asset::Baker::cook(
                                 baker.as_ref(),
                                 &data,
                                 &extension,
                                 meta,
-                                cooker,
+                                Arc::clone(&cooker),
                                 &exe_ontext,
                             );
+                            if let Err(error) = result {
+                                cooker.fail(error);
+                            }
                         });
                 TextureSource::Embedded(Some(task), sub_cooker)
             }
             gltf::image::Source::Uri { uri, mime_type: _ } => {
                 let relative = if let Some(_rest) = uri.strip_prefix("data:") {
-                    panic!("Data URL isn't supported for textures yet");
+                    return Err("Data URL isn't supported for textures yet".into());
                 } else if let Some(rest) = uri.strip_prefix("file://") {
                     rest
                 } else if let Some(rest) = uri.strip_prefix("file:") {
@@ -446,12 +513,14 @@ impl Baker {
                     uri
                 };
                 let full = parent_cooker.base_path().join(relative);
-                if PRELOAD_TEXTURES {
-                    self.asset_textures.load(&full, meta);
+                match full.to_str() {
+                    Some(full_str) => TextureSource::Path(full_str.to_string()),
+                    None => {
+                        return Err(format!("Texture path {} is not UTF-8", full.display()).into())
+                    }
                 }
-                TextureSource::Path(full.to_str().unwrap().to_string())
             }
-        }
+        })
     }
 
     fn serve_texture(
@@ -459,10 +528,11 @@ impl Baker {
         texture_ref: &TextureReference,
         meta: super::texture::Meta,
         exe_context: &choir::ExecutionContext,
-    ) -> Option<blade_asset::Handle<super::texture::Texture>> {
-        if !texture_ref.path.is_empty() {
-            let path_str = str::from_utf8(&texture_ref.path).unwrap();
-            let (handle, task) = self.asset_textures.load(path_str, meta);
+    ) -> Result<Option<blade_asset::StrongHandle<super::texture::Texture>>, blade_asset::Error>
+    {
+        Ok(if !texture_ref.path.is_empty() {
+            let path_str = str::from_utf8(&texture_ref.path).map_err(|e| e.to_string())?;
+            let (handle, task) = self.asset_textures.load_strong(path_str, meta);
             exe_context.add_fork(&task);
             Some(handle)
         } else if !texture_ref.embedded_data.is_empty() {
@@ -471,13 +541,12 @@ impl Baker {
                     texture_ref.embedded_data.as_ptr(),
                 )
             };
-            Some(
-                self.asset_textures
-                    .load_cooked_inside_task(cooked, exe_context),
-            )
+            let result = self.asset_textures.baker.serve_embedded(cooked);
+            let handle = self.asset_textures.add_served(result);
+            Some(self.asset_textures.make_strong(handle))
         } else {
             None
-        }
+        })
     }
 }
 
@@ -493,32 +562,45 @@ impl blade_asset::Baker for Baker {
         meta: Meta,
         cooker: Arc<blade_asset::Cooker<Self>>,
         exe_context: &choir::ExecutionContext,
-    ) {
+    ) -> Result<(), blade_asset::Error> {
         match extension {
             #[cfg(feature = "asset")]
             "gltf" | "glb" => {
                 use base64::engine::{general_purpose::URL_SAFE as ENCODING_ENGINE, Engine as _};
 
-                let gltf::Gltf { document, mut blob } = gltf::Gltf::from_slice(source).unwrap();
+                let gltf::Gltf { document, mut blob } =
+                    gltf::Gltf::from_slice(source).map_err(|e| e.to_string())?;
                 // extract buffers
                 let mut buffers = Vec::new();
                 for buffer in document.buffers() {
                     let mut data = match buffer.source() {
                         gltf::buffer::Source::Uri(uri) => {
                             if let Some(rest) = uri.strip_prefix("data:") {
-                                let (_before, after) = rest.split_once(";base64,").unwrap();
-                                ENCODING_ENGINE.decode(after).unwrap()
+                                let (_before, after) = rest
+                                    .split_once(";base64,")
+                                    .ok_or("Only base64 data URLs are supported")?;
+                                ENCODING_ENGINE.decode(after).map_err(|e| e.to_string())?
                             } else if let Some(rest) = uri.strip_prefix("file://") {
-                                cooker.add_dependency(rest.as_ref())
+                                cooker.add_dependency(rest.as_ref())?
                             } else if let Some(rest) = uri.strip_prefix("file:") {
-                                cooker.add_dependency(rest.as_ref())
+                                cooker.add_dependency(rest.as_ref())?
                             } else {
-                                cooker.add_dependency(uri.as_ref())
+                                cooker.add_dependency(uri.as_ref())?
                             }
                         }
-                        gltf::buffer::Source::Bin => blob.take().unwrap(),
+                        gltf::buffer::Source::Bin => {
+                            blob.take().ok_or("Binary buffer is missing")?
+                        }
                     };
-                    assert!(data.len() >= buffer.length());
+                    if data.len() < buffer.length() {
+                        return Err(format!(
+                            "Buffer {} is too short: {} bytes instead of {}",
+                            buffer.index(),
+                            data.len(),
+                            buffer.length()
+                        )
+                        .into());
+                    }
                     while data.len() % 4 != 0 {
                         data.push(0);
                     }
@@ -545,7 +627,7 @@ impl blade_asset::Baker for Baker {
                                     META_BASE_COLOR,
                                     &cooker,
                                     &buffers,
-                                )),
+                                )?),
                                 None => !0,
                             },
                             ..Default::default()
@@ -558,7 +640,7 @@ impl blade_asset::Baker for Baker {
                                     META_NORMAL,
                                     &cooker,
                                     &buffers,
-                                )),
+                                )?),
                                 None => !0,
                             },
                             ..Default::default()
@@ -576,22 +658,22 @@ impl blade_asset::Baker for Baker {
                             glam::Mat4::IDENTITY,
                             &buffers,
                             &mut flattened_geos,
-                        );
+                        )?;
                     }
                 }
 
-                assert!(
-                    !model.geometries.is_empty(),
-                    "Empty models are not supported yet"
-                );
+                if model.geometries.is_empty() {
+                    return Err("Empty models are not supported yet".into());
+                }
                 let model_shared = Arc::new(Mutex::new(model));
                 let model_clone = Arc::clone(&model_shared);
+                let tangent_cooker = Arc::clone(&cooker);
                 let gen_tangents = exe_context.choir().spawn("generate tangents").init_iter(
                     flattened_geos.into_iter().enumerate(),
                     move |_, (index, mut fg)| {
-                        if meta.generate_tangents {
-                            let ok = mikktspace::generate_tangents(&mut fg);
-                            assert!(ok, "MikkTSpace failed");
+                        if meta.generate_tangents && !mikktspace::generate_tangents(&mut fg) {
+                            tangent_cooker.fail("MikkTSpace failed".into());
+                            return;
                         }
                         let (indices, vertices) = fg.reconstruct_indices();
                         let mut model = model_clone.lock().unwrap();
@@ -611,8 +693,14 @@ impl blade_asset::Baker for Baker {
                 let mut finish = exe_context.fork("finish").init(move |_| {
                     let mut model = Arc::into_inner(model_shared).unwrap().into_inner().unwrap();
                     for material in model.materials.iter_mut() {
-                        material.base_color.complete(&sources);
-                        material.normal.complete(&sources);
+                        let result = material
+                            .base_color
+                            .complete(&sources)
+                            .and_then(|()| material.normal.complete(&sources));
+                        if let Err(error) = result {
+                            cooker.fail(error);
+                            return;
+                        }
                     }
                     cooker.finish(model);
                 });
@@ -620,24 +708,35 @@ impl blade_asset::Baker for Baker {
                     finish.depend_on(&dependency);
                 }
             }
-            other => panic!("Unknown model extension: {}", other),
+            other => return Err(format!("Unknown model extension: {}", other).into()),
         }
+        Ok(())
     }
 
-    fn serve(&self, model: CookedModel<'_>, exe_context: &choir::ExecutionContext) -> Self::Output {
+    fn serve(
+        &self,
+        model: CookedModel<'_>,
+        exe_context: &choir::ExecutionContext,
+    ) -> Result<Self::Output, blade_asset::Error> {
+        if self.gpu_context.is_none() {
+            return Err(crate::NO_GPU.into());
+        }
+        let name = str::from_utf8(model.name).map_err(|e| e.to_string())?;
         let mut materials = Vec::with_capacity(model.materials.len());
+        let mut textures = Vec::new();
         for material in model.materials.iter() {
+            let base_color_texture =
+                self.serve_texture(&material.base_color, META_BASE_COLOR, exe_context)?;
+            let normal_texture = self.serve_texture(&material.normal, META_NORMAL, exe_context)?;
             materials.push(Material {
-                base_color_texture: self.serve_texture(
-                    &material.base_color,
-                    META_BASE_COLOR,
-                    exe_context,
-                ),
+                base_color_texture: base_color_texture.as_deref().copied(),
                 base_color_factor: material.base_color_factor,
-                normal_texture: self.serve_texture(&material.normal, META_NORMAL, exe_context),
+                normal_texture: normal_texture.as_deref().copied(),
                 normal_scale: material.normal_scale,
                 transparent: material.transparent,
             });
+            textures.extend(base_color_texture);
+            textures.extend(normal_texture);
         }
 
         let total_vertices = model
@@ -646,12 +745,12 @@ impl blade_asset::Baker for Baker {
             .map(|geo| geo.vertices.len())
             .sum::<usize>();
         let total_vertex_size = (total_vertices * mem::size_of::<crate::Vertex>()) as u64;
-        let vertex_buffer = self.gpu_context.create_buffer(blade_graphics::BufferDesc {
+        let vertex_buffer = self.gpu().create_buffer(blade_graphics::BufferDesc {
             name: "vertex",
             size: total_vertex_size,
             memory: blade_graphics::Memory::Device,
         });
-        let vertex_stage = self.gpu_context.create_buffer(blade_graphics::BufferDesc {
+        let vertex_stage = self.gpu().create_buffer(blade_graphics::BufferDesc {
             name: "vertex stage",
             size: total_vertex_size,
             memory: blade_graphics::Memory::Upload,
@@ -664,12 +763,12 @@ impl blade_asset::Baker for Baker {
             .sum::<usize>();
         let total_index_size = total_indices as u64 * 4
             + model.geometries.len() as u64 * blade_graphics::limits::STORAGE_BUFFER_ALIGNMENT;
-        let index_buffer = self.gpu_context.create_buffer(blade_graphics::BufferDesc {
+        let index_buffer = self.gpu().create_buffer(blade_graphics::BufferDesc {
             name: "index",
             size: total_index_size,
             memory: blade_graphics::Memory::Device,
         });
-        let index_stage = self.gpu_context.create_buffer(blade_graphics::BufferDesc {
+        let index_stage = self.gpu().create_buffer(blade_graphics::BufferDesc {
             name: "index stage",
             size: total_index_size,
             memory: blade_graphics::Memory::Upload,
@@ -677,12 +776,12 @@ impl blade_asset::Baker for Baker {
 
         let total_transform_size =
             (model.geometries.len() * mem::size_of::<blade_graphics::Transform>()) as u64;
-        let transform_buffer = self.gpu_context.create_buffer(blade_graphics::BufferDesc {
+        let transform_buffer = self.gpu().create_buffer(blade_graphics::BufferDesc {
             name: "transform",
             size: total_transform_size,
             memory: blade_graphics::Memory::Device,
         });
-        let transform_stage = self.gpu_context.create_buffer(blade_graphics::BufferDesc {
+        let transform_stage = self.gpu().create_buffer(blade_graphics::BufferDesc {
             name: "transform stage",
             size: total_transform_size,
             memory: blade_graphics::Memory::Upload,
@@ -756,16 +855,16 @@ impl blade_asset::Baker for Baker {
         assert_eq!(transform_offset, total_transform_size);
 
         let sizes = self
-            .gpu_context
+            .gpu()
             .get_bottom_level_acceleration_structure_sizes(&meshes);
-        let acceleration_structure = self.gpu_context.create_acceleration_structure(
-            blade_graphics::AccelerationStructureDesc {
-                name: str::from_utf8(model.name).unwrap(),
-                ty: blade_graphics::AccelerationStructureType::BottomLevel,
-                size: sizes.data,
-            },
-        );
-        let scratch = self.gpu_context.create_buffer(blade_graphics::BufferDesc {
+        let acceleration_structure =
+            self.gpu()
+                .create_acceleration_structure(blade_graphics::AccelerationStructureDesc {
+                    name,
+                    ty: blade_graphics::AccelerationStructureType::BottomLevel,
+                    size: sizes.data,
+                });
+        let scratch = self.gpu().create_buffer(blade_graphics::BufferDesc {
             name: "BLAS scratch",
             size: sizes.scratch,
             memory: blade_graphics::Memory::Device,
@@ -793,8 +892,8 @@ impl blade_asset::Baker for Baker {
             dst: acceleration_structure,
         });
 
-        Model {
-            name: String::from_utf8_lossy(model.name).into_owned(),
+        Ok(Model {
+            name: name.to_string(),
             winding: model.winding,
             geometries,
             materials,
@@ -802,14 +901,26 @@ impl blade_asset::Baker for Baker {
             index_buffer,
             transform_buffer,
             acceleration_structure,
-        }
+            _textures: textures,
+        })
     }
 
     fn delete(&self, model: Self::Output) {
-        self.gpu_context
-            .destroy_acceleration_structure(model.acceleration_structure);
-        self.gpu_context.destroy_buffer(model.vertex_buffer);
-        self.gpu_context.destroy_buffer(model.index_buffer);
-        self.gpu_context.destroy_buffer(model.transform_buffer);
+        // The GPU may still be using the model, so it's destroyed
+        // together with the resources of the next flushed frame.
+        let retired = &mut self.pending_operations.lock().unwrap().retired;
+        retired
+            .acceleration_structures
+            .push(model.acceleration_structure);
+        retired.buffers.push(model.vertex_buffer);
+        retired.buffers.push(model.index_buffer);
+        retired.buffers.push(model.transform_buffer);
+    }
+
+    fn dependencies(&self, model: &Self::Output, list: &mut Vec<blade_asset::AssetId>) {
+        for material in model.materials.iter() {
+            let textures = [material.base_color_texture, material.normal_texture];
+            list.extend(textures.iter().flatten().map(|handle| handle.id()));
+        }
     }
 }
@@ -8,9 +8,10 @@ pub use debug::{DebugBlit, DebugLine, DebugPoint};
 pub use dummy::DummyResources;
 pub use env_map::EnvironmentMap;
 
-use std::{collections::HashMap, mem, num::NonZeroU32, path::Path, ptr};
+use std::{collections::HashMap, mem, num::NonZeroU32, path::Path, ptr, slice};
 
 const MAX_RESOURCES: u32 = 8192;
+const TEXTURE_FEEDBACK_SIZE: u64 = MAX_RESOURCES as u64 * mem::size_of::<u32>() as u64;
 const RADIANCE_FORMAT: blade_graphics::TextureFormat = blade_graphics::TextureFormat::Rgba16Float;
 
 fn mat4_transform(t: &blade_graphics::Transform) -> glam::Mat4 {
@@ -333,6 +334,9 @@ pub struct Renderer {
     vertex_buffers: blade_graphics::BufferArray<MAX_RESOURCES>,
     index_buffers: blade_graphics::BufferArray<MAX_RESOURCES>,
     textures: blade_graphics::TextureArray<MAX_RESOURCES>,
+    /// Minimal UV footprint of a pixel per texture, written by the G-buffer fill.
+    /// One buffer is written by the GPU, while the other one is read by the CPU.
+    texture_feedback: [blade_graphics::Buffer; 2],
     samplers: Samplers,
     reservoir_size: u32,
     debug: DebugRender,
@@ -395,6 +399,7 @@ struct FillData<'a> {
     textures: &'a blade_graphics::TextureArray<MAX_RESOURCES>,
     sampler_linear: blade_graphics::Sampler,
     debug_buf: blade_graphics::BufferPiece,
+    texture_feedback: blade_graphics::BufferPiece,
     out_depth: blade_graphics::TextureView,
     out_basis: blade_graphics::TextureView,
     out_flat_normal: blade_graphics::TextureView,
@@ -651,6 +656,35 @@ pub struct FrameConfig {
 pub struct FrameResources {
     pub buffers: Vec<blade_graphics::Buffer>,
     pub acceleration_structures: Vec<blade_graphics::AccelerationStructure>,
+    pub textures: Vec<blade_graphics::Texture>,
+    pub texture_views: Vec<blade_graphics::TextureView>,
+}
+
+impl FrameResources {
+    /// Take over all the resources of `other`.
+    pub(crate) fn append(&mut self, other: &mut Self) {
+        self.buffers.append(&mut other.buffers);
+        self.acceleration_structures
+            .append(&mut other.acceleration_structures);
+        self.textures.append(&mut other.textures);
+        self.texture_views.append(&mut other.texture_views);
+    }
+
+    /// Destroy all the resources, which the GPU has to be done with.
+    pub fn free(&mut self, gpu: &blade_graphics::Context) {
+        for buffer in self.buffers.drain(..) {
+            gpu.destroy_buffer(buffer);
+        }
+        for accel_structure in self.acceleration_structures.drain(..) {
+            gpu.destroy_acceleration_structure(accel_structure);
+        }
+        for view in self.texture_views.drain(..) {
+            gpu.destroy_texture_view(view);
+        }
+        for texture in self.textures.drain(..) {
+            gpu.destroy_texture(texture);
+        }
+    }
 }
 
 impl Renderer {
@@ -687,6 +721,17 @@ impl Renderer {
 
         let targets = RestirTargets::new(config.surface_size, sp.reservoir_size, encoder, gpu);
         let dummy = DummyResources::new(encoder, gpu);
+        let texture_feedback = [0, 1].map(|i| {
+            let buffer = gpu.create_buffer(blade_graphics::BufferDesc {
+                name: &format!("texture feedback{i}"),
+                size: TEXTURE_FEEDBACK_SIZE,
+                memory: blade_graphics::Memory::Shared,
+            });
+            unsafe {
+                ptr::write_bytes(buffer.data(), !0, TEXTURE_FEEDBACK_SIZE as usize);
+            }
+            buffer
+        });
 
         let samplers = Samplers {
             nearest: gpu.create_sampler(blade_graphics::SamplerDesc {
@@ -726,6 +771,7 @@ impl Renderer {
             vertex_buffers: blade_graphics::BufferArray::new(),
             index_buffers: blade_graphics::BufferArray::new(),
             textures: blade_graphics::TextureArray::new(),
+            texture_feedback,
             samplers,
             reservoir_size: sp.reservoir_size,
             debug,
@@ -745,6 +791,9 @@ impl Renderer {
         if self.hit_buffer != blade_graphics::Buffer::default() {
             gpu.destroy_buffer(self.hit_buffer);
         }
+        for buffer in self.texture_feedback {
+            gpu.destroy_buffer(buffer);
+        }
         gpu.destroy_acceleration_structure(self.acceleration_structure);
         if self.prev_acceleration_structure != blade_graphics::AccelerationStructure::default() {
             gpu.destroy_acceleration_structure(self.prev_acceleration_structure);
@@ -774,12 +823,18 @@ impl Renderer {
         let mut tasks = Vec::new();
         let old = self.shaders.clone();
 
-        tasks.extend(asset_hub.shaders.hot_reload(&mut self.shaders.fill_gbuf));
-        tasks.extend(asset_hub.shaders.hot_reload(&mut self.shaders.ray_trace));
-        tasks.extend(asset_hub.shaders.hot_reload(&mut self.shaders.a_trous));
-        tasks.extend(asset_hub.shaders.hot_reload(&mut self.shaders.post_proc));
-        tasks.extend(asset_hub.shaders.hot_reload(&mut self.shaders.debug_draw));
-        tasks.extend(asset_hub.shaders.hot_reload(&mut self.shaders.debug_blit));
+        // Only the changed shaders are reloaded, if the sources are watched
+        let changed = asset_hub.shaders.changed();
+        let hot_reload = |handle: &mut blade_asset::Handle<crate::Shader>| match changed {
+            Some(ref list) if !list.contains(handle) => None,
+            _ => asset_hub.shaders.hot_reload(handle),
+        };
+        tasks.extend(hot_reload(&mut self.shaders.fill_gbuf));
+        tasks.extend(hot_reload(&mut self.shaders.ray_trace));
+        tasks.extend(hot_reload(&mut self.shaders.a_trous));
+        tasks.extend(hot_reload(&mut self.shaders.post_proc));
+        tasks.extend(hot_reload(&mut self.shaders.debug_draw));
+        tasks.extend(hot_reload(&mut self.shaders.debug_blit));
 
         if tasks.is_empty() {
             return false;
@@ -868,11 +923,9 @@ impl Renderer {
         gpu: &blade_graphics::Context,
         temp: &mut FrameResources,
     ) {
-        let (env_view, env_extent) = match env_map {
-            Some(handle) => {
-                let asset = &asset_hub.textures[handle];
-                (asset.view, asset.extent)
-            }
+        let (env_view, env_extent) = match env_map.and_then(|handle| asset_hub.textures.get(handle))
+        {
+            Some(asset) => (asset.view, asset.extent),
             None => (self.dummy.white_view, blade_graphics::Extent::default()),
         };
         self.env_map
@@ -886,10 +939,8 @@ impl Renderer {
 
         let geometry_count = objects
             .iter()
-            .map(|object| {
-                let model = &asset_hub.models[object.model];
-                model.geometries.len()
-            })
+            .filter_map(|object| asset_hub.models.get(object.model))
+            .map(|model| model.geometries.len())
             .sum::<usize>();
         let hit_size = (geometry_count.max(1) * mem::size_of::<HitEntry>()) as u64;
         //TODO: reuse the hit buffer
@@ -925,7 +976,11 @@ impl Renderer {
 
         for object in objects {
             let m3_object = mat3_transform(&object.transform);
-            let model = &asset_hub.models[object.model];
+            // Models that failed to load are skipped
+            let model = match asset_hub.models.get(object.model) {
+                Some(model) => model,
+                None => continue,
+            };
             instances.push(blade_graphics::AccelerationStructureInstance {
                 acceleration_structure_index: blases.len() as u32,
                 transform: object.transform,
@@ -965,10 +1020,14 @@ impl Renderer {
                         w: [0.0, 0.0, 0.0, 1.0].into(),
                     }),
                     prev_object_to_world: mat4_transform(&object.prev_transform).into(),
-                    base_color_texture: match material.base_color_texture {
+                    // Textures that failed to load are replaced by dummies
+                    base_color_texture: match material
+                        .base_color_texture
+                        .filter(|&handle| asset_hub.textures.get(handle).is_some())
+                    {
                         Some(handle) => *texture_indices.entry(handle).or_insert_with(|| {
                             let texture = &asset_hub.textures[handle];
-                            self.textures.alloc(texture.view)
+                            self.textures.alloc(asset_hub.textures.baker.view(texture))
                         }),
                         None => dummy_white,
                     },
@@ -981,10 +1040,13 @@ impl Renderer {
                             (c[3] * 255.0) as u8,
                         ]
                     },
-                    normal_texture: match material.normal_texture {
+                    normal_texture: match material
+                        .normal_texture
+                        .filter(|&handle| asset_hub.textures.get(handle).is_some())
+                    {
                         Some(handle) => *texture_indices.entry(handle).or_insert_with(|| {
                             let texture = &asset_hub.textures[handle];
-                            self.textures.alloc(texture.view)
+                            self.textures.alloc(asset_hub.textures.baker.view(texture))
                         }),
                         None => dummy_black,
                     },
@@ -1042,6 +1104,45 @@ impl Renderer {
         self.frame_scene_built = self.frame_index + 1;
     }
 
+    /// Stream the mip levels of the scene textures, according to
+    /// their screen-space footprint in the previously rendered frames.
+    ///
+    /// Needs to be called before `AssetHub::flush`, which uploads the new mips.
+    /// Only affects the textures loaded with streaming enabled,
+    /// see [`crate::texture::Baker::configure_streaming`].
+    #[profiling::function]
+    pub fn stream_textures(&mut self, asset_hub: &crate::AssetHub, temp: &mut FrameResources) {
+        let baker = &asset_hub.textures.baker;
+        // The last frame may still be in flight, but the one before is done.
+        // Its feedback is only valid if it was rendered after the scene was built,
+        // since the resource indices change with the scene.
+        if self.frame_index > self.frame_scene_built {
+            let feedback = self.texture_feedback[(self.frame_index + 1) % 2];
+            let footprints = unsafe {
+                slice::from_raw_parts(feedback.data() as *const u32, MAX_RESOURCES as usize)
+            };
+            for (&res_id, &handle) in self.texture_resource_lookup.iter() {
+                let bits = footprints[res_id as usize];
+                if bits == !0 {
+                    continue;
+                }
+                if let Some(texture) = asset_hub.textures.get(handle) {
+                    let size = texture.extent.width.max(texture.extent.height);
+                    let texels_per_pixel = f32::from_bits(bits) * size as f32;
+                    baker.request_mip(handle, texture, texels_per_pixel.max(1.0).log2() as u32);
+                }
+            }
+        }
+
+        baker.stream(&asset_hub.textures, temp);
+
+        for (&res_id, &handle) in self.texture_resource_lookup.iter() {
+            if let Some(texture) = asset_hub.textures.get(handle) {
+                self.textures[res_id] = baker.view(texture);
+            }
+        }
+    }
+
     fn make_debug_params(&self, config: &DebugConfig) -> DebugParams {
         DebugParams {
             view_mode: config.view_mode as u32,
@@ -1113,6 +1214,11 @@ impl Renderer {
         if !config.frozen {
             self.frame_index += 1;
         }
+        transfer.fill_buffer(
+            self.texture_feedback[self.frame_index % 2].into(),
+            TEXTURE_FEEDBACK_SIZE,
+            !0,
+        );
         self.is_frozen = config.frozen;
         self.targets.camera_params[self.frame_index % 2] = self.make_camera_params(camera);
         self.post_proc_input_index = self.frame_index % 2;
@@ -1148,6 +1254,7 @@ impl Renderer {
                     textures: &self.textures,
                     sampler_linear: self.samplers.linear,
                     debug_buf: self.debug.buffer_resource(),
+                    texture_feedback: self.texture_feedback[self.frame_index % 2].into(),
                     out_depth: self.targets.depth.views[cur],
                     out_basis: self.targets.basis.views[cur],
                     out_flat_normal: self.targets.flat_normal.views[cur],
@@ -42,14 +42,22 @@ impl Expansion {
 }
 
 pub struct Baker {
-    gpu_context: Arc<blade_graphics::Context>,
+    gpu_context: Option<Arc<blade_graphics::Context>>,
     expansions: HashMap<String, Expansion>,
 }
 
 impl Baker {
     pub fn new(gpu_context: &Arc<blade_graphics::Context>) -> Self {
         Self {
-            gpu_context: Arc::clone(gpu_context),
+            gpu_context: Some(Arc::clone(gpu_context)),
+            expansions: HashMap::default(),
+        }
+    }
+
+    /// Create a baker that only cooks the shaders, without a GPU.
+    pub fn new_offline() -> Self {
+        Self {
+            gpu_context: None,
             expansions: HashMap::default(),
         }
     }
@@ -80,17 +88,19 @@ fn parse_impl(
     text_out: &mut String,
     cooker: &blade_asset::Cooker<Baker>,
     expansions: &HashMap<String, Expansion>,
-) {
+) -> Result<(), blade_asset::Error> {
     use std::fmt::Write as _;
 
-    let text_in = str::from_utf8(text_raw).unwrap();
+    let text_in = str::from_utf8(text_raw).map_err(|e| e.to_string())?;
     for line in text_in.lines() {
         if line.starts_with("#include") {
             let include_path = match line.split('"').nth(1) {
                 Some(include) => base_path.join(include),
-                None => panic!("Unable to extract the include path from: {line}"),
+                None => {
+                    return Err(format!("Unable to extract the include path from: {line}").into())
+                }
             };
-            let include = cooker.add_dependency(&include_path);
+            let include = cooker.add_dependency(&include_path)?;
             writeln!(text_out, "//{}", line).unwrap();
             parse_impl(
                 &include,
@@ -98,10 +108,13 @@ fn parse_impl(
                 text_out,
                 cooker,
                 expansions,
-            );
+            )?;
         } else if line.starts_with("#use") {
             let type_name = line.split_whitespace().last().unwrap();
-            match expansions[type_name] {
+            let expansion = expansions
+                .get(type_name)
+                .ok_or_else(|| format!("Unknown expansion: {type_name}"))?;
+            match *expansion {
                 Expansion::Values(ref map) => {
                     for (key, value) in map.iter() {
                         writeln!(text_out, "const {}_{}: u32 = {}u;", type_name, key, value)
@@ -117,16 +130,17 @@ fn parse_impl(
         }
         *text_out += "\n";
     }
+    Ok(())
 }
 
 pub fn parse_shader(
     text_raw: &[u8],
     cooker: &blade_asset::Cooker<Baker>,
     expansions: &HashMap<String, Expansion>,
-) -> String {
+) -> Result<String, blade_asset::Error> {
     let mut text_out = String::new();
-    parse_impl(text_raw, ".".as_ref(), &mut text_out, cooker, expansions);
-    text_out
+    parse_impl(text_raw, ".".as_ref(), &mut text_out, cooker, expansions)?;
+    Ok(text_out)
 }
 
 impl blade_asset::Baker for Baker {
@@ -140,23 +154,29 @@ impl blade_asset::Baker for Baker {
         _meta: Meta,
         cooker: Arc<blade_asset::Cooker<Self>>,
         _exe_context: &choir::ExecutionContext,
-    ) {
-        assert_eq!(extension, "wgsl");
-        let text_out = parse_shader(source, &cooker, &self.expansions);
+    ) -> Result<(), blade_asset::Error> {
+        if extension != "wgsl" {
+            return Err(format!("Unknown shader extension: {}", extension).into());
+        }
+        let text_out = parse_shader(source, &cooker, &self.expansions)?;
         cooker.finish(CookedShader {
             data: text_out.as_bytes(),
         });
+        Ok(())
     }
-    fn serve(&self, cooked: CookedShader, _exe_context: &choir::ExecutionContext) -> Shader {
-        let source = str::from_utf8(cooked.data).unwrap();
-        let raw = self
-            .gpu_context
-            .try_create_shader(blade_graphics::ShaderDesc { source });
+    fn serve(
+        &self,
+        cooked: CookedShader,
+        _exe_context: &choir::ExecutionContext,
+    ) -> Result<Shader, blade_asset::Error> {
+        let gpu_context = self.gpu_context.as_ref().ok_or(crate::NO_GPU)?;
+        let source = str::from_utf8(cooked.data).map_err(|e| e.to_string())?;
+        let raw = gpu_context.try_create_shader(blade_graphics::ShaderDesc { source });
         if let Err(e) = raw {
             let _ = fs::write(FAILURE_DUMP_NAME, source);
             log::warn!("Shader compilation failed: {e:?}, source dumped as '{FAILURE_DUMP_NAME}'.")
         }
-        Shader { raw }
+        Ok(Shader { raw })
     }
     fn delete(&self, _output: Shader) {}
 }
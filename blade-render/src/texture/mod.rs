@@ -1,12 +1,12 @@
+mod streaming;
+
+pub use streaming::{Residency, StreamingConfig};
+
 use std::{
     fmt, io, mem, ptr, slice, str,
     sync::{Arc, Mutex},
 };
 
-#[repr(transparent)]
-#[derive(Clone, Copy, Debug, blade_macros::Flat)]
-struct TextureFormatWrap(blade_graphics::TextureFormat);
-
 #[derive(blade_macros::Flat)]
 struct CookedMip<'a> {
     data: &'a [u8],
@@ -16,10 +16,28 @@ struct CookedMip<'a> {
 pub struct CookedImage<'a> {
     name: &'a [u8],
     extent: [u32; 3],
-    format: TextureFormatWrap,
+    #[flat(copy)]
+    format: blade_graphics::TextureFormat,
     mips: Vec<CookedMip<'a>>,
 }
 
+/// Formats that the textures can be cooked into.
+///
+/// The block-compressed formats are for LDR images, and `Rgba32Float` is for HDR.
+pub const COOKED_FORMATS: &[blade_graphics::TextureFormat] = &[
+    blade_graphics::TextureFormat::Rgba32Float,
+    blade_graphics::TextureFormat::Bc1Unorm,
+    blade_graphics::TextureFormat::Bc1UnormSrgb,
+    blade_graphics::TextureFormat::Bc2Unorm,
+    blade_graphics::TextureFormat::Bc2UnormSrgb,
+    blade_graphics::TextureFormat::Bc3Unorm,
+    blade_graphics::TextureFormat::Bc3UnormSrgb,
+    blade_graphics::TextureFormat::Bc4Unorm,
+    blade_graphics::TextureFormat::Bc4Snorm,
+    blade_graphics::TextureFormat::Bc5Unorm,
+    blade_graphics::TextureFormat::Bc5Snorm,
+];
+
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Meta {
     pub format: blade_graphics::TextureFormat,
@@ -34,9 +52,22 @@ impl fmt::Display for Meta {
 }
 
 pub struct Texture {
+    /// GPU texture with the mip levels uploaded at load time.
+    /// For streamed textures, these are only the coarse ones.
     pub object: blade_graphics::Texture,
+    /// View of the `object`. See [`Baker::view`] for the view
+    /// of all the mip levels currently resident.
     pub view: blade_graphics::TextureView,
+    /// Extent of the finest mip level of the image.
     pub extent: blade_graphics::Extent,
+    stream_key: Option<usize>,
+}
+
+impl Texture {
+    /// Check if the finer mip levels of this texture are streamed.
+    pub fn is_streamed(&self) -> bool {
+        self.stream_key.is_some()
+    }
 }
 
 struct Initialization {
@@ -56,27 +87,130 @@ struct Transfer {
 struct PendingOperations {
     initializations: Vec<Initialization>,
     transfers: Vec<Transfer>,
+    // Resources of the deleted textures, which the GPU may still be using.
+    retired: crate::FrameResources,
+}
+
+#[cfg(feature = "asset")]
+const MISSING_INFO: &str = "Image info is missing after decoding headers";
+
+#[cfg(feature = "asset")]
+fn decode_error(error: impl fmt::Debug) -> blade_asset::Error {
+    format!("Unable to decode: {:?}", error).into()
+}
+
+#[cfg(feature = "asset")]
+fn check_buffer_size(count: usize, expected: Option<usize>) -> Result<(), blade_asset::Error> {
+    if expected == Some(count) {
+        Ok(())
+    } else {
+        Err(format!("Output buffer size {:?} doesn't match {}", expected, count).into())
+    }
 }
 
 pub struct Baker {
-    gpu_context: Arc<blade_graphics::Context>,
+    gpu_context: Option<Arc<blade_graphics::Context>>,
     pending_operations: Mutex<PendingOperations>,
+    streamer: Mutex<streaming::Streamer>,
 }
 
 impl Baker {
     pub fn new(gpu_context: &Arc<blade_graphics::Context>) -> Self {
         Self {
-            gpu_context: Arc::clone(gpu_context),
+            gpu_context: Some(Arc::clone(gpu_context)),
             pending_operations: Mutex::new(PendingOperations::default()),
+            streamer: Mutex::new(streaming::Streamer::default()),
+        }
+    }
+
+    /// Create a baker that only cooks the textures, without a GPU.
+    pub fn new_offline() -> Self {
+        Self {
+            gpu_context: None,
+            pending_operations: Mutex::new(PendingOperations::default()),
+            streamer: Mutex::new(streaming::Streamer::default()),
+        }
+    }
+
+    fn gpu(&self) -> &blade_graphics::Context {
+        self.gpu_context.as_deref().expect(crate::NO_GPU)
+    }
+
+    /// Create a texture with the given mip levels, finest first,
+    /// and schedule the upload of their data.
+    fn create_uploaded(
+        &self,
+        name: &str,
+        format: blade_graphics::TextureFormat,
+        base_extent: blade_graphics::Extent,
+        mips: &[&[u8]],
+    ) -> (blade_graphics::Texture, blade_graphics::TextureView) {
+        let texture = self.gpu().create_texture(blade_graphics::TextureDesc {
+            name,
+            format,
+            size: base_extent,
+            array_layer_count: 1,
+            mip_level_count: mips.len() as u32,
+            dimension: blade_graphics::TextureDimension::D2,
+            usage: blade_graphics::TextureUsage::COPY | blade_graphics::TextureUsage::RESOURCE,
+            sample_count: 1,
+            external: None,
+        });
+        let view = self.gpu().create_texture_view(
+            texture,
+            blade_graphics::TextureViewDesc {
+                name,
+                format,
+                dimension: blade_graphics::ViewDimension::D2,
+                subresources: &Default::default(),
+            },
+        );
+
+        let mut pending_ops = self.pending_operations.lock().unwrap();
+        pending_ops
+            .initializations
+            .push(Initialization { dst: texture });
+
+        for (i, data) in mips.iter().enumerate() {
+            let stage = self.gpu().create_buffer(blade_graphics::BufferDesc {
+                name: &format!("{name}[{i}]/stage"),
+                size: data.len() as u64,
+                memory: blade_graphics::Memory::Upload,
+            });
+            unsafe {
+                ptr::copy_nonoverlapping(data.as_ptr(), stage.data(), data.len());
+            }
+
+            let block_info = format.block_info();
+            let extent = base_extent.at_mip_level(i as u32);
+            let bytes_per_row = ((extent.width + block_info.dimensions.0 as u32 - 1)
+                / block_info.dimensions.0 as u32)
+                * block_info.size as u32;
+            let rows_per_image = (extent.height + block_info.dimensions.1 as u32 - 1)
+                / block_info.dimensions.1 as u32;
+            assert!(data.len() >= rows_per_image as usize * bytes_per_row as usize,
+                "Image mip[{i}] data of size {} is insufficient for {bytes_per_row} bytes per {rows_per_image} rows",
+                data.len());
+
+            pending_ops.transfers.push(Transfer {
+                stage,
+                bytes_per_row,
+                dst: texture,
+                extent,
+                mip_level: i as u32,
+            });
         }
+
+        (texture, view)
     }
 
     pub fn flush(
         &self,
         encoder: &mut blade_graphics::CommandEncoder,
-        temp_buffers: &mut Vec<blade_graphics::Buffer>,
+        temp: &mut crate::FrameResources,
     ) {
         let mut pending_ops = self.pending_operations.lock().unwrap();
+        temp.append(&mut pending_ops.retired);
         for init in pending_ops.initializations.drain(..) {
             encoder.init_texture(init.dst);
         }
@@ -95,8 +229,76 @@ impl Baker {
                     dst,
                     transfer.extent,
                 );
-                temp_buffers.push(transfer.stage);
+                temp.buffers.push(transfer.stage);
+            }
+        }
+    }
+
+    /// Upload the cooked image, streaming its finer mip levels if `streamable`.
+    fn serve_image(
+        &self,
+        image: CookedImage<'_>,
+        streamable: bool,
+    ) -> Result<Texture, blade_asset::Error> {
+        if self.gpu_context.is_none() {
+            return Err(crate::NO_GPU.into());
+        }
+        let name = str::from_utf8(image.name).map_err(|e| e.to_string())?;
+        let base_extent = blade_graphics::Extent {
+            width: image.extent[0],
+            height: image.extent[1],
+            depth: image.extent[2],
+        };
+        let mips = image.mips.iter().map(|mip| mip.data).collect::<Vec<_>>();
+
+        // Only the coarse mips are uploaded for the streamed textures.
+        // The HDR textures, such as the environment maps, are not streamed,
+        // since they need all of their mips for sampling the lighting.
+        let config = self.streamer.lock().unwrap().config;
+        let base_mip = match config {
+            Some(ref config) if streamable && image.format.block_info().dimensions != (1, 1) => {
+                streaming::base_mip_level(base_extent, mips.len(), config)
             }
+            _ => 0,
+        };
+        let (object, view) = self.create_uploaded(
+            name,
+            image.format,
+            base_extent.at_mip_level(base_mip),
+            &mips[base_mip as usize..],
+        );
+        let stream_key = if base_mip != 0 {
+            let mut streamer = self.streamer.lock().unwrap();
+            let mip_sizes = mips.iter().map(|data| data.len() as u64).collect();
+            Some(streamer.add(name, image.format, base_extent, mip_sizes, base_mip))
+        } else {
+            None
+        };
+
+        Ok(Texture {
+            object,
+            view,
+            extent: base_extent,
+            stream_key,
+        })
+    }
+
+    /// Serve an image embedded into another asset.
+    ///
+    /// It's never streamed, since there is no cooked asset
+    /// to read the finer mip levels from.
+    pub(crate) fn serve_embedded(
+        &self,
+        image: CookedImage<'_>,
+    ) -> Result<Texture, blade_asset::Error> {
+        self.serve_image(image, false)
+    }
+
+    /// Destroy the resources of the deleted textures right away,
+    /// which the GPU has to be done with.
+    pub(crate) fn free_retired(&self) {
+        if let Some(ref gpu) = self.gpu_context {
+            self.pending_operations.lock().unwrap().retired.free(gpu);
         }
     }
 }
@@ -112,7 +314,7 @@ impl blade_asset::Baker for Baker {
         meta: Meta,
         cooker: Arc<blade_asset::Cooker<Self>>,
         exe_context: &choir::ExecutionContext,
-    ) {
+    ) -> Result<(), blade_asset::Error> {
         use blade_graphics::TextureFormat as Tf;
 
         type LdrTexel = [u8; 4];
@@ -134,16 +336,16 @@ impl blade_asset::Baker for Baker {
                 let options =
                     zune_core::options::DecoderOptions::default().png_set_add_alpha_channel(true);
                 let mut decoder = zune_png::PngDecoder::new_with_options(source, options);
-                decoder.decode_headers().unwrap();
-                let info = decoder.get_info().unwrap().clone();
+                decoder.decode_headers().map_err(decode_error)?;
+                let info = decoder.get_info().ok_or(MISSING_INFO)?.clone();
                 let mut data = vec![[0u8; 4]; info.width * info.height];
                 let count = data.len() * data[0].len();
-                assert_eq!(count, decoder.output_buffer_size().unwrap());
+                check_buffer_size(count, decoder.output_buffer_size())?;
                 decoder
                     .decode_into(unsafe {
                         slice::from_raw_parts_mut(data.as_mut_ptr() as *mut u8, count)
                     })
-                    .unwrap();
+                    .map_err(decode_error)?;
                 PlainImage {
                     width: info.width,
                     height: info.height,
@@ -156,16 +358,16 @@ impl blade_asset::Baker for Baker {
                 let options = zune_core::options::DecoderOptions::default()
                     .jpeg_set_out_colorspace(zune_core::colorspace::ColorSpace::RGBA);
                 let mut decoder = zune_jpeg::JpegDecoder::new_with_options(source, options);
-                decoder.decode_headers().unwrap();
-                let info = decoder.info().unwrap();
+                decoder.decode_headers().map_err(decode_error)?;
+                let info = decoder.info().ok_or(MISSING_INFO)?;
                 let mut data = vec![[0u8; 4]; info.width as usize * info.height as usize];
                 let count = data.len() * data[0].len();
-                assert_eq!(count, decoder.output_buffer_size().unwrap());
+                check_buffer_size(count, decoder.output_buffer_size())?;
                 decoder
                     .decode_into(unsafe {
                         slice::from_raw_parts_mut(data.as_mut_ptr() as *mut u8, count)
                     })
-                    .unwrap();
+                    .map_err(decode_error)?;
                 PlainImage {
                     width: info.width as usize,
                     height: info.height as usize,
@@ -177,18 +379,20 @@ impl blade_asset::Baker for Baker {
                 profiling::scope!("decode hdr");
                 let options = zune_core::options::DecoderOptions::default();
                 let mut decoder = zune_hdr::HdrDecoder::new_with_options(source, options);
-                decoder.decode_headers().unwrap();
-                let (width, height) = decoder.get_dimensions().unwrap();
-                let colorspace = decoder.get_colorspace().unwrap();
-                assert_eq!(colorspace, zune_core::colorspace::ColorSpace::RGB);
+                decoder.decode_headers().map_err(decode_error)?;
+                let (width, height) = decoder.get_dimensions().ok_or(MISSING_INFO)?;
+                let colorspace = decoder.get_colorspace().ok_or(MISSING_INFO)?;
+                if colorspace != zune_core::colorspace::ColorSpace::RGB {
+                    return Err(format!("Unsupported HDR color space {:?}", colorspace).into());
+                }
                 let mut data = vec![[0f32; 3]; width * height];
                 let count = data.len() * data[0].len();
-                assert_eq!(count, decoder.output_buffer_size().unwrap());
+                check_buffer_size(count, decoder.output_buffer_size())?;
                 decoder
                     .decode_into(unsafe {
                         slice::from_raw_parts_mut(data.as_mut_ptr() as *mut f32, count)
                     })
-                    .unwrap();
+                    .map_err(decode_error)?;
                 PlainImage {
                     width,
                     height,
@@ -218,14 +422,14 @@ impl blade_asset::Baker for Baker {
                     .first_valid_layer()
                     .all_attributes()
                     .from_buffered(io::Cursor::new(source))
-                    .unwrap();
+                    .map_err(decode_error)?;
                 PlainImage {
                     width: image.layer_data.size.width(),
                     height: image.layer_data.size.height(),
                     data: PlainData::Hdr(image.layer_data.channel_data.pixels.data),
                 }
             }
-            other => panic!("Unknown texture extension: {}", other),
+            other => return Err(format!("Unknown texture extension: {}", other).into()),
         };
 
         #[cfg(feature = "asset")]
@@ -242,7 +446,9 @@ impl blade_asset::Baker for Baker {
                     Tf::Bc3Unorm | Tf::Bc3UnormSrgb => texpresso::Format::Bc3,
                     Tf::Bc4Unorm | Tf::Bc4Snorm => texpresso::Format::Bc4,
                     Tf::Bc5Unorm | Tf::Bc5Snorm => texpresso::Format::Bc5,
-                    other => panic!("Unsupported destination format {:?}", other),
+                    other => {
+                        return Err(format!("Unsupported destination format {:?}", other).into())
+                    }
                 };
 
                 let mut src_mips = vec![data];
@@ -343,7 +549,7 @@ impl blade_asset::Baker for Baker {
                         cooker.finish(CookedImage {
                             name: &[],
                             extent: [base_extent.width, base_extent.height, base_extent.depth],
-                            format: TextureFormatWrap(meta.format),
+                            format: meta.format,
                             mips: mips.iter().map(|data| CookedMip { data }).collect(),
                         });
                     })
@@ -352,7 +558,13 @@ impl blade_asset::Baker for Baker {
             PlainData::Hdr(data) => {
                 //TODO: compress as BC6E
                 //Note: we convert RGB32 to RGBA32 here, for now
-                assert_eq!(meta.format, blade_graphics::TextureFormat::Rgba32Float);
+                if meta.format != blade_graphics::TextureFormat::Rgba32Float {
+                    return Err(format!(
+                        "Unsupported destination format {:?} for HDR",
+                        meta.format
+                    )
+                    .into());
+                }
                 let in_texel_elements = data[0].len();
                 let out_texel_size = 4 * mem::size_of::<f32>();
                 let mut buf = vec![0u8; data.len() * out_texel_size];
@@ -368,92 +580,34 @@ impl blade_asset::Baker for Baker {
                 cooker.finish(CookedImage {
                     name: &[],
                     extent: [src.width as u32, src.height as u32, 1],
-                    format: TextureFormatWrap(meta.format),
+                    format: meta.format,
                     mips: vec![CookedMip { data: &buf }],
                 });
             }
         }
+        Ok(())
     }
 
     fn serve(
         &self,
         image: CookedImage<'_>,
         _exe_context: &choir::ExecutionContext,
-    ) -> Self::Output {
-        let name = str::from_utf8(image.name).unwrap();
-        let base_extent = blade_graphics::Extent {
-            width: image.extent[0],
-            height: image.extent[1],
-            depth: image.extent[2],
-        };
-        let texture = self
-            .gpu_context
-            .create_texture(blade_graphics::TextureDesc {
-                name,
-                format: image.format.0,
-                size: base_extent,
-                array_layer_count: 1,
-                mip_level_count: image.mips.len() as u32,
-                dimension: blade_graphics::TextureDimension::D2,
-                usage: blade_graphics::TextureUsage::COPY | blade_graphics::TextureUsage::RESOURCE,
-                sample_count: 1,
-                external: None,
-            });
-        let view = self.gpu_context.create_texture_view(
-            texture,
-            blade_graphics::TextureViewDesc {
-                name,
-                format: image.format.0,
-                dimension: blade_graphics::ViewDimension::D2,
-                subresources: &Default::default(),
-            },
-        );
-        self.pending_operations
-            .lock()
-            .unwrap()
-            .initializations
-            .push(Initialization { dst: texture });
-
-        for (i, mip) in image.mips.iter().enumerate() {
-            let stage = self.gpu_context.create_buffer(blade_graphics::BufferDesc {
-                name: &format!("{name}[{i}]/stage"),
-                size: mip.data.len() as u64,
-                memory: blade_graphics::Memory::Upload,
-            });
-            unsafe {
-                ptr::copy_nonoverlapping(mip.data.as_ptr(), stage.data(), mip.data.len());
-            }
-
-            let block_info = image.format.0.block_info();
-            let extent = base_extent.at_mip_level(i as u32);
-            let bytes_per_row = ((extent.width + block_info.dimensions.0 as u32 - 1)
-                / block_info.dimensions.0 as u32)
-                * block_info.size as u32;
-            let rows_per_image = (extent.height + block_info.dimensions.1 as u32 - 1)
-                / block_info.dimensions.1 as u32;
-            assert!(mip.data.len() >= rows_per_image as usize * bytes_per_row as usize,
-                "Image mip[{i}] data of size {} is insufficient for {bytes_per_row} bytes per {rows_per_image} rows",
-                mip.data.len());
-
-            let mut pending_ops = self.pending_operations.lock().unwrap();
-            pending_ops.transfers.push(Transfer {
-                stage,
-                bytes_per_row,
-                dst: texture,
-                extent,
-                mip_level: i as u32,
-            });
-        }
-
-        Texture {
-            object: texture,
-            view,
-            extent: base_extent,
-        }
+    ) -> Result<Self::Output, blade_asset::Error> {
+        self.serve_image(image, true)
     }
 
     fn delete(&self, texture: Self::Output) {
-        self.gpu_context.destroy_texture_view(texture.view);
-        self.gpu_context.destroy_texture(texture.object);
+        let upgrade = texture
+            .stream_key
+            .and_then(|key| self.streamer.lock().unwrap().remove(key));
+        // The GPU may still be using the texture, so it's destroyed
+        // together with the resources of the next flushed frame.
+        let retired = &mut self.pending_operations.lock().unwrap().retired;
+        if let Some(upgrade) = upgrade {
+            retired.texture_views.push(upgrade.view);
+            retired.textures.push(upgrade.object);
+        }
+        retired.texture_views.push(texture.view);
+        retired.textures.push(texture.object);
     }
 }
@@ -0,0 +1,667 @@
+use std::{
+    cmp,
+    collections::HashMap,
+    mem,
+    sync::{Arc, Mutex},
+};
+
+/// Configuration of the texture streaming.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StreamingConfig {
+    /// Mip levels up to this size are uploaded at load time, and always stay resident.
+    /// Finer mip levels are streamed in on demand.
+    pub base_size: u32,
+    /// Maximum number of bytes uploaded per frame.
+    /// At least one texture is updated in a frame, even if it's over the budget.
+    pub upload_budget: u64,
+    /// Maximum GPU memory of the streamed mip levels, in bytes.
+    pub memory_budget: u64,
+}
+
+impl Default for StreamingConfig {
+    fn default() -> Self {
+        Self {
+            base_size: 128,
+            upload_budget: 16 << 20,
+            memory_budget: 512 << 20,
+        }
+    }
+}
+
+/// Residency of the mip levels of a streamed texture.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Residency {
+    /// Finest mip level resident on GPU, or being streamed in.
+    pub resident_mip: u32,
+    /// Finest mip level requested by the last feedback.
+    pub requested_mip: u32,
+    /// Finest mip level that is always resident.
+    pub base_mip: u32,
+    /// GPU memory occupied by the texture, in bytes.
+    pub memory: u64,
+}
+
+/// Finest mip level of an image to be uploaded at load time.
+pub(super) fn base_mip_level(
+    extent: blade_graphics::Extent,
+    mip_count: usize,
+    config: &StreamingConfig,
+) -> u32 {
+    let mut mip = 0;
+    while mip + 1 < mip_count as u32 {
+        let mip_extent = extent.at_mip_level(mip);
+        if mip_extent.width.max(mip_extent.height) <= config.base_size {
+            break;
+        }
+        mip += 1;
+    }
+    mip
+}
+
+/// Texture with the finer mip levels than the ones uploaded at load time.
+pub(super) struct Upgrade {
+    pub object: blade_graphics::Texture,
+    pub view: blade_graphics::TextureView,
+}
+
+struct Stream {
+    name: String,
+    format: blade_graphics::TextureFormat,
+    extent: blade_graphics::Extent,
+    /// Sizes of all the mip levels, finest first.
+    /// The finer mips are read from the cooked asset when streamed in.
+    mip_sizes: Vec<u64>,
+    base_mip: u32,
+    resident_mip: u32,
+    /// Asset of the texture, as given by the last request.
+    handle: Option<blade_asset::Handle<super::Texture>>,
+    /// Finest mip level requested since the last update.
+    requested_mip: Option<u32>,
+    last_requested_mip: u32,
+    last_used_frame: u64,
+    /// The finer mip levels are being read from the cooked asset.
+    reading: bool,
+    /// Reading the cooked asset failed, so the stream stays at the base mip level.
+    failed: bool,
+}
+
+impl Stream {
+    fn chain_memory(&self, mip: u32) -> u64 {
+        self.mip_sizes[mip as usize..].iter().sum()
+    }
+
+    fn upgrade_memory(&self) -> u64 {
+        if self.resident_mip < self.base_mip {
+            self.chain_memory(self.resident_mip)
+        } else {
+            0
+        }
+    }
+}
+
+/// Mip levels read from the cooked asset of a stream, finest first.
+struct ReadMips {
+    key: usize,
+    mip: u32,
+    previous_mip: u32,
+    result: Result<Vec<Vec<u8>>, blade_asset::Error>,
+}
+
+/// Change of the resident mip level of a stream.
+/// Going back to the base mip level drops the upgrade.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Change {
+    key: usize,
+    mip: u32,
+    previous_mip: u32,
+}
+
+#[derive(Default)]
+pub(super) struct Streamer {
+    pub config: Option<StreamingConfig>,
+    streams: HashMap<usize, Stream>,
+    upgrades: HashMap<usize, Upgrade>,
+    /// Reads finished since the last update, filled by the tasks.
+    finished_reads: Arc<Mutex<Vec<ReadMips>>>,
+    next_key: usize,
+    frame_index: u64,
+    /// GPU memory of all the upgrades.
+    memory: u64,
+}
+
+impl Streamer {
+    pub fn add(
+        &mut self,
+        name: &str,
+        format: blade_graphics::TextureFormat,
+        extent: blade_graphics::Extent,
+        mip_sizes: Vec<u64>,
+        base_mip: u32,
+    ) -> usize {
+        let key = self.next_key;
+        self.next_key += 1;
+        self.streams.insert(
+            key,
+            Stream {
+                name: name.to_string(),
+                format,
+                extent,
+                mip_sizes,
+                base_mip,
+                resident_mip: base_mip,
+                handle: None,
+                requested_mip: None,
+                last_requested_mip: base_mip,
+                last_used_frame: 0,
+                reading: false,
+                failed: false,
+            },
+        );
+        key
+    }
+
+    pub fn remove(&mut self, key: usize) -> Option<Upgrade> {
+        let stream = self.streams.remove(&key)?;
+        self.memory -= stream.upgrade_memory();
+        self.upgrades.remove(&key)
+    }
+
+    fn request(&mut self, key: usize, mip: u32) {
+        if let Some(stream) = self.streams.get_mut(&key) {
+            let mip = mip.min(stream.base_mip);
+            stream.requested_mip = Some(match stream.requested_mip {
+                Some(other) => other.min(mip),
+                None => mip,
+            });
+        }
+    }
+
+    fn set_resident_mip(&mut self, key: usize, mip: u32) {
+        let stream = self.streams.get_mut(&key).unwrap();
+        self.memory -= stream.upgrade_memory();
+        stream.resident_mip = mip;
+        self.memory += stream.upgrade_memory();
+    }
+
+    /// Finish reading the mip levels of a stream.
+    ///
+    /// A failed stream goes back to the `previous_mip` level and stays there,
+    /// since its cooked asset is not going to become readable.
+    fn finish_read(&mut self, key: usize, previous_mip: u32, success: bool) {
+        let stream = self.streams.get_mut(&key).unwrap();
+        stream.reading = false;
+        if !success {
+            stream.failed = true;
+            self.set_resident_mip(key, previous_mip);
+        }
+    }
+
+    /// Iterate over the upgraded streams not used in the current frame.
+    fn unused(&self) -> impl Iterator<Item = (usize, &Stream)> {
+        self.streams.iter().filter_map(move |(&key, stream)| {
+            if stream.resident_mip < stream.base_mip
+                && stream.last_used_frame != self.frame_index
+                && !stream.reading
+            {
+                Some((key, stream))
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Drop the upgrades of the textures not used in the current frame,
+    /// least recently used first, until `needed` bytes fit into the `budget`.
+    fn evict(&mut self, needed: u64, budget: u64, changes: &mut Vec<Change>) {
+        let mut unused = self
+            .unused()
+            .map(|(key, stream)| (stream.last_used_frame, key))
+            .collect::<Vec<_>>();
+        unused.sort();
+        for (_, key) in unused {
+            if self.memory + needed <= budget {
+                break;
+            }
+            let stream = &self.streams[&key];
+            let base_mip = stream.base_mip;
+            changes.push(Change {
+                key,
+                mip: base_mip,
+                previous_mip: stream.resident_mip,
+            });
+            self.set_resident_mip(key, base_mip);
+        }
+    }
+
+    /// Start a new frame and decide on the residency changes,
+    /// according to the requests since the last frame.
+    ///
+    /// The changes are applied to the residency right away.
+    /// The upgraded streams are marked as reading until `finish_read`.
+    fn plan(&mut self, config: &StreamingConfig) -> Vec<Change> {
+        self.frame_index += 1;
+        let frame_index = self.frame_index;
+
+        let mut candidates = Vec::new();
+        for (&key, stream) in self.streams.iter_mut() {
+            if let Some(mip) = stream.requested_mip.take() {
+                stream.last_used_frame = frame_index;
+                stream.last_requested_mip = mip;
+                if mip < stream.resident_mip && !stream.reading && !stream.failed {
+                    candidates.push((stream.resident_mip - mip, key));
+                }
+            }
+        }
+        // The largest improvements go first
+        candidates.sort_by_key(|&(gain, key)| (cmp::Reverse(gain), key));
+
+        let mut changes = Vec::new();
+        let mut uploaded = 0;
+        for (_, key) in candidates {
+            if uploaded >= config.upload_budget {
+                break;
+            }
+            let evictable = self
+                .unused()
+                .map(|(_, stream)| stream.upgrade_memory())
+                .sum::<u64>();
+            let stream = &self.streams[&key];
+            let old_memory = stream.upgrade_memory();
+            // Pick the finest mip level that fits into the budgets
+            let target = (stream.last_requested_mip..stream.resident_mip).find_map(|mip| {
+                let memory = stream.chain_memory(mip);
+                if uploaded != 0 && uploaded + memory > config.upload_budget {
+                    return None;
+                }
+                let needed = memory.saturating_sub(old_memory);
+                if self.memory + needed > config.memory_budget + evictable {
+                    return None;
+                }
+                Some((mip, memory))
+            });
+            let (mip, memory) = match target {
+                Some(target) => target,
+                None => continue,
+            };
+
+            let previous_mip = stream.resident_mip;
+            self.evict(
+                memory.saturating_sub(old_memory),
+                config.memory_budget,
+                &mut changes,
+            );
+            changes.push(Change {
+                key,
+                mip,
+                previous_mip,
+            });
+            self.set_resident_mip(key, mip);
+            self.streams.get_mut(&key).unwrap().reading = true;
+            uploaded += memory;
+        }
+        changes
+    }
+}
+
+impl super::Baker {
+    /// Enable streaming of the textures loaded from now on,
+    /// or disable it with `None`.
+    ///
+    /// Only the block-compressed textures loaded from their own files are streamed.
+    /// The HDR ones, such as the environment maps, and the ones embedded
+    /// into models always have all of their mips resident.
+    pub fn configure_streaming(&self, config: Option<StreamingConfig>) {
+        self.streamer.lock().unwrap().config = config;
+    }
+
+    /// Request a mip level of the texture to be resident.
+    /// The finest level requested since the last [`Baker::stream`] wins.
+    ///
+    /// The `handle` is used for reading the finer mip levels of the cooked texture.
+    ///
+    /// [`Baker::stream`]: super::Baker::stream
+    pub fn request_mip(
+        &self,
+        handle: blade_asset::Handle<super::Texture>,
+        texture: &super::Texture,
+        mip: u32,
+    ) {
+        let key = match texture.stream_key {
+            Some(key) => key,
+            None => return,
+        };
+        let mut streamer = self.streamer.lock().unwrap();
+        if let Some(stream) = streamer.streams.get_mut(&key) {
+            stream.handle = Some(handle);
+        }
+        streamer.request(key, mip);
+    }
+
+    /// Get the view of all the currently resident mip levels of a texture.
+    pub fn view(&self, texture: &super::Texture) -> blade_graphics::TextureView {
+        if let Some(key) = texture.stream_key {
+            let streamer = self.streamer.lock().unwrap();
+            if let Some(upgrade) = streamer.upgrades.get(&key) {
+                return upgrade.view;
+            }
+        }
+        texture.view
+    }
+
+    /// Get the residency of a streamed texture.
+    pub fn residency(&self, texture: &super::Texture) -> Option<Residency> {
+        let streamer = self.streamer.lock().unwrap();
+        let stream = streamer.streams.get(&texture.stream_key?)?;
+        Some(Residency {
+            resident_mip: stream.resident_mip,
+            requested_mip: stream.last_requested_mip,
+            base_mip: stream.base_mip,
+            memory: stream.chain_memory(stream.base_mip) + stream.upgrade_memory(),
+        })
+    }
+
+    /// Get the GPU memory of all the streamed mip levels, in bytes.
+    pub fn streaming_memory(&self) -> u64 {
+        self.streamer.lock().unwrap().memory
+    }
+
+    /// Update the residency of the streamed textures, according to
+    /// the requests since the last call, within the configured budgets.
+    ///
+    /// The finer mip levels are read from the cooked assets of `textures`,
+    /// which has to be the manager of this baker, by the tasks of its `choir`.
+    /// They are uploaded by one of the next calls, once read, and recorded
+    /// by the following [`Baker::flush`](super::Baker::flush).
+    /// The replaced textures are added to `temp`, to be destroyed once
+    /// the GPU is done with them.
+    #[profiling::function]
+    pub fn stream(
+        &self,
+        textures: &Arc<blade_asset::AssetManager<super::Baker>>,
+        temp: &mut crate::FrameResources,
+    ) {
+        let mut guard = self.streamer.lock().unwrap();
+        let streamer = &mut *guard;
+        let config = match streamer.config {
+            Some(config) => config,
+            None => return,
+        };
+
+        let mut retired = Vec::new();
+        let finished_reads = mem::take(&mut *streamer.finished_reads.lock().unwrap());
+        for read in finished_reads {
+            // The texture may have been deleted while reading.
+            let stream = match streamer.streams.get(&read.key) {
+                Some(stream) => stream,
+                None => continue,
+            };
+            let success = match read.result {
+                Ok(mips) => {
+                    let mips = mips.iter().map(|data| data.as_slice()).collect::<Vec<_>>();
+                    let (object, view) = self.create_uploaded(
+                        &format!("{}/mip{}", stream.name, read.mip),
+                        stream.format,
+                        stream.extent.at_mip_level(read.mip),
+                        &mips,
+                    );
+                    retired.extend(streamer.upgrades.insert(read.key, Upgrade { object, view }));
+                    true
+                }
+                Err(error) => {
+                    log::warn!("Unable to stream {}: {}", stream.name, error);
+                    false
+                }
+            };
+            streamer.finish_read(read.key, read.previous_mip, success);
+        }
+
+        for change in streamer.plan(&config) {
+            let stream = &streamer.streams[&change.key];
+            if change.mip == stream.base_mip {
+                retired.extend(streamer.upgrades.remove(&change.key));
+                continue;
+            }
+            // Only the path and the metadata are taken here,
+            // since the asset slots can't be accessed from the tasks.
+            let source = match stream.handle {
+                Some(handle) => textures.get_cooked_source(handle),
+                None => Err(blade_asset::Error::new("Texture asset is unknown")),
+            };
+            let layout = (stream.format, stream.extent, stream.mip_sizes.clone());
+            let task_textures = Arc::clone(textures);
+            let finished_reads = Arc::clone(&streamer.finished_reads);
+            textures
+                .choir
+                .spawn(format!("stream {}", stream.name))
+                .init(move |_| {
+                    let result = source.and_then(|(path, meta)| {
+                        task_textures
+                            .read_cooked(path, &meta, |image| read_mips(image, &layout, change.mip))
+                            .and_then(|result| result)
+                    });
+                    finished_reads.lock().unwrap().push(ReadMips {
+                        key: change.key,
+                        mip: change.mip,
+                        previous_mip: change.previous_mip,
+                        result,
+                    });
+                });
+        }
+        drop(guard);
+
+        for upgrade in retired {
+            temp.texture_views.push(upgrade.view);
+            temp.textures.push(upgrade.object);
+        }
+    }
+}
+
+/// Copy the mip levels of the cooked `image`, starting from `mip`.
+///
+/// The `layout` of format, extent, and mip sizes is the one the stream was served with,
+/// since the asset may have been cooked again since then.
+fn read_mips(
+    image: super::CookedImage<'_>,
+    layout: &(
+        blade_graphics::TextureFormat,
+        blade_graphics::Extent,
+        Vec<u64>,
+    ),
+    mip: u32,
+) -> Result<Vec<Vec<u8>>, blade_asset::Error> {
+    let (format, extent, ref mip_sizes) = *layout;
+    let matches = image.format == format
+        && image.extent == [extent.width, extent.height, extent.depth]
+        && image.mips.len() == mip_sizes.len()
+        && image
+            .mips
+            .iter()
+            .zip(mip_sizes.iter())
+            .all(|(cooked, &size)| cooked.data.len() as u64 == size);
+    if !matches {
+        return Err(blade_asset::Error::new("Cooked texture has changed"));
+    }
+    Ok(image.mips[mip as usize..]
+        .iter()
+        .map(|cooked| cooked.data.to_vec())
+        .collect())
+}
+
+#[cfg(test)]
+fn add_test_stream(streamer: &mut Streamer) -> usize {
+    let extent = blade_graphics::Extent {
+        width: 4,
+        height: 4,
+        depth: 1,
+    };
+    // mips of 4x4, 2x2, and 1x1 texels
+    let mip_sizes = vec![64, 16, 4];
+    streamer.add(
+        "test",
+        blade_graphics::TextureFormat::Rgba8Unorm,
+        extent,
+        mip_sizes,
+        2,
+    )
+}
+
+#[test]
+fn test_base_mip_level() {
+    let config = StreamingConfig {
+        base_size: 128,
+        ..Default::default()
+    };
+    let extent = blade_graphics::Extent {
+        width: 1024,
+        height: 512,
+        depth: 1,
+    };
+    assert_eq!(base_mip_level(extent, 11, &config), 3);
+    // limited by the available mips
+    assert_eq!(base_mip_level(extent, 2, &config), 1);
+    assert_eq!(base_mip_level(extent, 1, &config), 0);
+    // small enough already
+    assert_eq!(base_mip_level(extent.at_mip_level(3), 8, &config), 0);
+}
+
+#[test]
+fn test_streaming_budget() {
+    let config = StreamingConfig {
+        base_size: 1,
+        upload_budget: 1000,
+        memory_budget: 110,
+    };
+    let mut streamer = Streamer::default();
+    let first = add_test_stream(&mut streamer);
+    let second = add_test_stream(&mut streamer);
+    assert!(streamer.plan(&config).is_empty());
+
+    streamer.request(first, 0);
+    let changes = streamer.plan(&config);
+    assert_eq!(
+        changes,
+        [Change {
+            key: first,
+            mip: 0,
+            previous_mip: 2
+        }]
+    );
+    assert_eq!(streamer.memory, 84);
+    streamer.finish_read(first, 2, true);
+
+    // the second stream only fits a coarser mip, since the first is used
+    streamer.request(first, 0);
+    streamer.request(second, 0);
+    let changes = streamer.plan(&config);
+    assert_eq!(
+        changes,
+        [Change {
+            key: second,
+            mip: 1,
+            previous_mip: 2
+        }]
+    );
+    assert_eq!(streamer.memory, 84 + 20);
+    streamer.finish_read(second, 2, true);
+
+    // the first stream is not used, so it's evicted before the upgrade
+    streamer.request(second, 0);
+    let changes = streamer.plan(&config);
+    assert_eq!(
+        changes,
+        [
+            Change {
+                key: first,
+                mip: 2,
+                previous_mip: 0
+            },
+            Change {
+                key: second,
+                mip: 0,
+                previous_mip: 1
+            }
+        ]
+    );
+    assert_eq!(streamer.memory, 84);
+    assert!(streamer.remove(second).is_none());
+    assert_eq!(streamer.memory, 0);
+}
+
+#[test]
+fn test_streaming_upload_budget() {
+    let config = StreamingConfig {
+        base_size: 1,
+        upload_budget: 50,
+        memory_budget: 1000,
+    };
+    let mut streamer = Streamer::default();
+    let first = add_test_stream(&mut streamer);
+    let second = add_test_stream(&mut streamer);
+    streamer.request(first, 0);
+    streamer.request(second, 1);
+    // the largest improvement goes first, even if it's over the budget
+    let changes = streamer.plan(&config);
+    assert_eq!(
+        changes,
+        [Change {
+            key: first,
+            mip: 0,
+            previous_mip: 2
+        }]
+    );
+    streamer.finish_read(first, 2, true);
+    // not requested again, but still waiting
+    assert!(streamer.plan(&config).is_empty());
+    streamer.request(second, 0);
+    streamer.request(first, 0);
+    let changes = streamer.plan(&config);
+    assert_eq!(
+        changes,
+        [Change {
+            key: second,
+            mip: 0,
+            previous_mip: 2
+        }]
+    );
+}
+
+#[test]
+fn test_streaming_reads() {
+    let config = StreamingConfig {
+        base_size: 1,
+        upload_budget: 1000,
+        memory_budget: 110,
+    };
+    let mut streamer = Streamer::default();
+    let first = add_test_stream(&mut streamer);
+    let second = add_test_stream(&mut streamer);
+    streamer.request(first, 0);
+    streamer.request(second, 1);
+    assert_eq!(streamer.plan(&config).len(), 2);
+    assert_eq!(streamer.memory, 84 + 20);
+
+    // streams being read are neither upgraded again nor evicted
+    streamer.request(second, 0);
+    assert!(streamer.plan(&config).is_empty());
+    assert_eq!(streamer.memory, 84 + 20);
+
+    // a failed read goes back to the previous mip level for good
+    streamer.finish_read(first, 2, false);
+    streamer.finish_read(second, 2, true);
+    assert_eq!(streamer.memory, 20);
+    streamer.request(first, 0);
+    streamer.request(second, 0);
+    let changes = streamer.plan(&config);
+    assert_eq!(
+        changes,
+        [Change {
+            key: second,
+            mip: 0,
+            previous_mip: 1
+        }]
+    );
+    streamer.finish_read(second, 1, true);
+    streamer.request(first, 0);
+    assert!(streamer.plan(&config).is_empty());
+    assert_eq!(streamer.memory, 84);
+}
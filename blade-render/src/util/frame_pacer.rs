@@ -32,12 +32,7 @@ impl FramePacer {
         if let Some(sp) = self.prev_sync_point.take() {
             context.wait_for(&sp, !0);
         }
-        for buffer in self.prev_resources.buffers.drain(..) {
-            context.destroy_buffer(buffer);
-        }
-        for accel_structure in self.prev_resources.acceleration_structures.drain(..) {
-            context.destroy_acceleration_structure(accel_structure);
-        }
+        self.prev_resources.free(context);
     }
 
     pub fn last_sync_point(&self) -> Option<&blade_graphics::SyncPoint> {
@@ -0,0 +1,21 @@
+var source: texture_2d<f32>;
+// The format is substituted by the host.
+var destination: texture_storage_2d<STORAGE_FORMAT, write>;
+
+@compute
+@workgroup_size(8, 8)
+fn downsample(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let dst_size = textureDimensions(destination);
+    if (any(global_id.xy >= dst_size)) {
+        return;
+    }
+
+    // Odd sizes are handled by clamping to the last texel.
+    let src_max = vec2<i32>(textureDimensions(source)) - vec2<i32>(1);
+    let base = vec2<i32>(global_id.xy) * 2;
+    let sum = textureLoad(source, min(base + vec2<i32>(0, 0), src_max), 0) +
+        textureLoad(source, min(base + vec2<i32>(1, 0), src_max), 0) +
+        textureLoad(source, min(base + vec2<i32>(0, 1), src_max), 0) +
+        textureLoad(source, min(base + vec2<i32>(1, 1), src_max), 0);
+    textureStore(destination, vec2<i32>(global_id.xy), 0.25 * sum);
+}
@@ -0,0 +1,21 @@
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+}
+
+var source: texture_2d<f32>;
+var source_sampler: sampler;
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    // Full-screen triangle
+    let tc = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    let position = vec4<f32>(tc.x * 2.0 - 1.0, 1.0 - tc.y * 2.0, 0.0, 1.0);
+    return VertexOutput(position, tc);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // Linear filtering at the center of 2x2 source texels averages them.
+    return textureSampleLevel(source, source_sampler, in.tex_coords, 0.0);
+}
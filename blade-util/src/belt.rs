@@ -1,25 +1,168 @@
 use blade_graphics as gpu;
-use std::mem;
+use std::{collections::VecDeque, mem};
 
 struct ReusableBuffer {
     raw: gpu::Buffer,
     size: u64,
 }
 
+/// Behavior of a bounded belt when its budget is exhausted.
+///
+/// Allocations bigger than the budget, or not fitting next to the other
+/// allocations of the current frame, can't be satisfied by waiting.
+/// These fall back to temporary chunks in either mode,
+/// and are counted in [`BeltStats::fallbacks`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BeltExhaustion {
+    /// Wait for the GPU to finish with the oldest frames.
+    Block,
+    /// Allocate a temporary chunk outside of the budget.
+    Fallback,
+}
+
+/// Fixed memory budget of a belt.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BeltBudget {
+    /// Size of the ring buffer, in bytes.
+    pub size: u64,
+    pub exhaustion: BeltExhaustion,
+}
+
 /// Configuration of the Blade belt.
 pub struct BufferBeltDescriptor {
     /// Kind of memory to allocate from.
     pub memory: gpu::Memory,
     pub min_chunk_size: u64,
     pub alignment: u64,
+    /// If set, the belt allocates from a single ring buffer of this size,
+    /// instead of growing the number of chunks.
+    pub budget: Option<BeltBudget>,
+}
+
+impl Default for BufferBeltDescriptor {
+    fn default() -> Self {
+        Self {
+            memory: gpu::Memory::Shared,
+            min_chunk_size: 0x10000,
+            alignment: gpu::limits::STORAGE_BUFFER_ALIGNMENT,
+            budget: None,
+        }
+    }
+}
+
+/// Statistics of the belt usage.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct BeltStats {
+    /// Bytes allocated within the last frame.
+    pub bytes_allocated: u64,
+    /// Maximum of bytes allocated within a frame.
+    pub peak_bytes_allocated: u64,
+    /// Number of buffers alive at the end of the last frame.
+    pub buffers_alive: usize,
+    /// Total size of the alive buffers.
+    pub memory_size: u64,
+    /// Number of times the last frame had to wait for GPU.
+    pub blocked: usize,
+    /// Number of allocations outside of the budget within the last frame.
+    pub fallbacks: usize,
+}
+
+/// Space of a ring buffer, allocated linearly and freed in the same order.
+struct RingSpace {
+    capacity: u64,
+    /// Offset of the next allocation.
+    head: u64,
+    /// Bytes in use by CPU or GPU, ending at `head`.
+    used: u64,
+}
+
+impl RingSpace {
+    /// Find space for `size` bytes, returning the offset and the consumed bytes.
+    fn find_space(&self, size: u64, alignment: u64) -> Option<(u64, u64)> {
+        let capacity = self.capacity;
+        if self.used == 0 {
+            return if size <= capacity {
+                Some((0, size))
+            } else {
+                None
+            };
+        }
+        let tail = (self.head + capacity - self.used) % capacity;
+        let aligned = self.head.next_multiple_of(alignment);
+        if tail < self.head {
+            if aligned + size <= capacity {
+                Some((aligned, aligned - self.head + size))
+            } else if size <= tail {
+                // Wrap around, skipping the end of the buffer.
+                Some((0, capacity - self.head + size))
+            } else {
+                None
+            }
+        } else if tail > self.head && aligned + size <= tail {
+            Some((aligned, aligned - self.head + size))
+        } else {
+            None
+        }
+    }
+
+    /// Allocate `size` bytes, returning the offset and the consumed bytes.
+    fn alloc(&mut self, size: u64, alignment: u64) -> Option<(u64, u64)> {
+        let (offset, consumed) = self.find_space(size, alignment)?;
+        self.head = offset + size;
+        self.used += consumed;
+        Some((offset, consumed))
+    }
+
+    /// Free the oldest `bytes` of the used space.
+    fn free(&mut self, bytes: u64) {
+        self.used -= bytes;
+        if self.used == 0 {
+            self.head = 0;
+        }
+    }
+}
+
+/// Ring buffer, sub-allocated linearly and freed by the frame fences.
+struct Ring {
+    buffer: ReusableBuffer,
+    space: RingSpace,
+    /// Bytes allocated within the current frame.
+    pending: u64,
+    /// Bytes used by each submitted frame, in submission order.
+    fences: VecDeque<(u64, gpu::SyncPoint)>,
+}
+
+impl Ring {
+    /// Free the space of the frames finished by GPU.
+    fn retire(&mut self, gpu: &gpu::Context, timeout_ms: u32) -> bool {
+        let mut retired = false;
+        while let Some(&(bytes, ref sp)) = self.fences.front() {
+            if !gpu.wait_for(sp, timeout_ms) {
+                break;
+            }
+            self.space.free(bytes);
+            self.fences.pop_front();
+            retired = true;
+            if timeout_ms != 0 {
+                // Only block for a single frame at a time.
+                break;
+            }
+        }
+        retired
+    }
 }
 
 /// A belt of reusable buffer space.
 /// Could be useful for temporary data, such as texture staging areas.
+///
+/// With a budget, it works as a ring buffer with predictable memory usage.
 pub struct BufferBelt {
     desc: BufferBeltDescriptor,
     buffers: Vec<(ReusableBuffer, gpu::SyncPoint)>,
     active: Vec<(ReusableBuffer, u64)>,
+    ring: Option<Ring>,
+    frame_stats: BeltStats,
+    stats: BeltStats,
 }
 
 impl BufferBelt {
@@ -30,6 +173,9 @@ impl BufferBelt {
             desc,
             buffers: Vec::new(),
             active: Vec::new(),
+            ring: None,
+            frame_stats: BeltStats::default(),
+            stats: BeltStats::default(),
         }
     }
 
@@ -41,11 +187,77 @@ impl BufferBelt {
         for (buffer, _) in self.active.drain(..) {
             gpu.destroy_buffer(buffer.raw);
         }
+        if let Some(ring) = self.ring.take() {
+            gpu.destroy_buffer(ring.buffer.raw);
+        }
+    }
+
+    /// Get the statistics, as of the last `flush()`.
+    pub fn stats(&self) -> BeltStats {
+        self.stats
+    }
+
+    fn alloc_ring(
+        &mut self,
+        budget: BeltBudget,
+        size: u64,
+        gpu: &gpu::Context,
+    ) -> Option<gpu::BufferPiece> {
+        if size > budget.size {
+            log::warn!(
+                "Allocation of {} bytes exceeds the belt budget of {}",
+                size,
+                budget.size
+            );
+            return None;
+        }
+        let memory = self.desc.memory;
+        let ring = self.ring.get_or_insert_with(|| Ring {
+            buffer: ReusableBuffer {
+                raw: gpu.create_buffer(gpu::BufferDesc {
+                    name: "ring",
+                    size: budget.size,
+                    memory,
+                }),
+                size: budget.size,
+            },
+            space: RingSpace {
+                capacity: budget.size,
+                head: 0,
+                used: 0,
+            },
+            pending: 0,
+            fences: VecDeque::new(),
+        });
+
+        loop {
+            if let Some((offset, consumed)) = ring.space.alloc(size, self.desc.alignment) {
+                ring.pending += consumed;
+                return Some(ring.buffer.raw.at(offset));
+            }
+            if ring.retire(gpu, 0) {
+                continue;
+            }
+            if ring.fences.is_empty() || budget.exhaustion == BeltExhaustion::Fallback {
+                // Nothing to wait for, or not allowed to wait.
+                return None;
+            }
+            self.frame_stats.blocked += 1;
+            ring.retire(gpu, !0);
+        }
     }
 
     /// Allocate a region of `size` bytes.
     #[profiling::function]
     pub fn alloc(&mut self, size: u64, gpu: &gpu::Context) -> gpu::BufferPiece {
+        self.frame_stats.bytes_allocated += size;
+        if let Some(budget) = self.desc.budget {
+            if let Some(piece) = self.alloc_ring(budget, size, gpu) {
+                return piece;
+            }
+            self.frame_stats.fallbacks += 1;
+        }
+
         for &mut (ref rb, ref mut offset) in self.active.iter_mut() {
             let aligned = offset.next_multiple_of(self.desc.alignment);
             if aligned + size <= rb.size {
@@ -91,8 +303,11 @@ impl BufferBelt {
         bp
     }
 
-    // SAFETY: T should be zeroable and ordinary data, no references, pointers, cells or other complicated data type.
     /// Allocate a region to hold the typed `data` slice contents.
+    ///
+    /// # Safety
+    ///
+    /// T should be zeroable and ordinary data, no references, pointers, cells or other complicated data type.
     pub unsafe fn alloc_typed<T>(&mut self, data: &[T], gpu: &gpu::Context) -> gpu::BufferPiece {
         assert!(!data.is_empty());
         let type_alignment = mem::align_of::<T>() as u64;
@@ -120,8 +335,87 @@ impl BufferBelt {
     }
 
     /// Mark the actively used buffers as used by GPU with a given sync point.
+    /// This finishes the frame for the statistics.
     pub fn flush(&mut self, sp: &gpu::SyncPoint) {
         self.buffers
             .extend(self.active.drain(..).map(|(rb, _)| (rb, sp.clone())));
+        if let Some(ref mut ring) = self.ring {
+            if ring.pending != 0 {
+                ring.fences.push_back((ring.pending, sp.clone()));
+                ring.pending = 0;
+            }
+        }
+
+        let mut stats = mem::take(&mut self.frame_stats);
+        stats.peak_bytes_allocated = self.stats.peak_bytes_allocated.max(stats.bytes_allocated);
+        stats.buffers_alive = self.buffers.len();
+        stats.memory_size = self.buffers.iter().map(|entry| entry.0.size).sum();
+        if let Some(ref ring) = self.ring {
+            stats.buffers_alive += 1;
+            stats.memory_size += ring.buffer.size;
+        }
+        self.stats = stats;
     }
 }
+
+#[test]
+fn test_ring_alignment() {
+    let mut space = RingSpace {
+        capacity: 100,
+        head: 0,
+        used: 0,
+    };
+    assert_eq!(space.alloc(10, 8), Some((0, 10)));
+    // padding is consumed along with the allocation
+    assert_eq!(space.alloc(10, 8), Some((16, 16)));
+    assert_eq!(space.head, 26);
+    assert_eq!(space.used, 26);
+    // too big for the whole ring
+    assert_eq!(space.find_space(101, 1), None);
+}
+
+#[test]
+fn test_ring_wrap_around() {
+    let mut space = RingSpace {
+        capacity: 100,
+        head: 0,
+        used: 0,
+    };
+    assert_eq!(space.alloc(40, 1), Some((0, 40)));
+    assert_eq!(space.alloc(40, 1), Some((40, 40)));
+    // doesn't fit at the end, and the start is still in use
+    assert_eq!(space.find_space(30, 1), None);
+    space.free(40);
+    // the end of the buffer is skipped
+    assert_eq!(space.alloc(30, 1), Some((0, 50)));
+    assert_eq!(space.head, 30);
+    assert_eq!(space.used, 90);
+    // only the space between the head and the tail is left
+    assert_eq!(space.find_space(11, 1), None);
+    assert_eq!(space.alloc(10, 1), Some((30, 10)));
+    space.free(40);
+    space.free(60);
+    assert_eq!((space.head, space.used), (0, 0));
+}
+
+#[test]
+fn test_ring_full() {
+    let mut space = RingSpace {
+        capacity: 100,
+        head: 0,
+        used: 0,
+    };
+    assert_eq!(space.alloc(100, 1), Some((0, 100)));
+    // the head is at the capacity, and the tail is at the start
+    assert_eq!(space.head, 100);
+    assert_eq!(space.find_space(1, 1), None);
+    space.free(100);
+    assert_eq!(space.alloc(60, 1), Some((0, 60)));
+    assert_eq!(space.alloc(40, 1), Some((60, 40)));
+    assert_eq!(space.head, 100);
+    space.free(60);
+    // wrapping from the end of the buffer doesn't skip anything
+    assert_eq!(space.alloc(60, 4), Some((0, 60)));
+    assert_eq!(space.head, 60);
+    assert_eq!(space.find_space(1, 1), None);
+}
@@ -1,3 +1,5 @@
 mod belt;
+mod mipmap;
 
-pub use belt::{BufferBelt, BufferBeltDescriptor};
+pub use belt::{BeltBudget, BeltExhaustion, BeltStats, BufferBelt, BufferBeltDescriptor};
+pub use mipmap::MipmapGenerator;
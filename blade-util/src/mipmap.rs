@@ -0,0 +1,267 @@
+use blade_graphics as gpu;
+use std::{collections::HashMap, mem, num::NonZeroU32};
+
+const SHADER_SOURCE: &str = include_str!("../mipmap.wgsl");
+const COMPUTE_SHADER_SOURCE: &str = include_str!("../mipmap-compute.wgsl");
+
+#[derive(blade_macros::ShaderData)]
+struct BlitData {
+    source: gpu::TextureView,
+    source_sampler: gpu::Sampler,
+}
+
+#[derive(blade_macros::ShaderData)]
+struct DownsampleData {
+    source: gpu::TextureView,
+    destination: gpu::TextureView,
+}
+
+/// Name of the format in WGSL storage textures, if it can be filtered.
+fn storage_format_name(format: gpu::TextureFormat) -> Option<&'static str> {
+    use gpu::TextureFormat as Tf;
+    Some(match format {
+        Tf::R8Unorm => "r8unorm",
+        Tf::Rg8Unorm => "rg8unorm",
+        Tf::Rg8Snorm => "rg8snorm",
+        Tf::Rgba8Unorm => "rgba8unorm",
+        Tf::Rgba8Snorm => "rgba8snorm",
+        Tf::R16Float => "r16float",
+        Tf::Rg16Float => "rg16float",
+        Tf::Rgba16Float => "rgba16float",
+        Tf::R32Float => "r32float",
+        Tf::Rg32Float => "rg32float",
+        Tf::Rgba32Float => "rgba32float",
+        Tf::Rgb10a2Unorm => "rgb10a2unorm",
+        _ => return None,
+    })
+}
+
+/// Generator of the texture mip chains on GPU.
+///
+/// Each mip level is downsampled from the previous one, so the texture
+/// needs `TextureUsage::RESOURCE`, in addition to either:
+///   - `TextureUsage::TARGET`, for downsampling by rendering
+///   - `TextureUsage::STORAGE`, for downsampling by compute,
+///     limited to the formats that can be stored and filtered
+pub struct MipmapGenerator {
+    sampler: gpu::Sampler,
+    shader: gpu::Shader,
+    render_pipelines: HashMap<gpu::TextureFormat, gpu::RenderPipeline>,
+    compute_pipelines: HashMap<gpu::TextureFormat, gpu::ComputePipeline>,
+    /// Views used by the commands not submitted yet.
+    active_views: Vec<gpu::TextureView>,
+    /// Views used by the submitted commands.
+    pending_views: Vec<(gpu::SyncPoint, Vec<gpu::TextureView>)>,
+}
+
+impl MipmapGenerator {
+    /// Create a new generator.
+    pub fn new(gpu: &gpu::Context) -> Self {
+        let sampler = gpu.create_sampler(gpu::SamplerDesc {
+            name: "mipmap",
+            address_modes: [gpu::AddressMode::ClampToEdge; 3],
+            mag_filter: gpu::FilterMode::Linear,
+            min_filter: gpu::FilterMode::Linear,
+            mipmap_filter: gpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+        let shader = gpu.create_shader(gpu::ShaderDesc {
+            source: SHADER_SOURCE,
+        });
+        Self {
+            sampler,
+            shader,
+            render_pipelines: HashMap::default(),
+            compute_pipelines: HashMap::default(),
+            active_views: Vec::new(),
+            pending_views: Vec::new(),
+        }
+    }
+
+    /// Destroy this generator.
+    pub fn destroy(&mut self, gpu: &gpu::Context) {
+        for (sp, views) in self.pending_views.drain(..) {
+            gpu.wait_for(&sp, !0);
+            for view in views {
+                gpu.destroy_texture_view(view);
+            }
+        }
+        for view in self.active_views.drain(..) {
+            gpu.destroy_texture_view(view);
+        }
+        for (_, mut pipeline) in self.render_pipelines.drain() {
+            gpu.destroy_render_pipeline(&mut pipeline);
+        }
+        for (_, mut pipeline) in self.compute_pipelines.drain() {
+            gpu.destroy_compute_pipeline(&mut pipeline);
+        }
+        gpu.destroy_sampler(self.sampler);
+    }
+
+    fn create_view(
+        &mut self,
+        texture: gpu::Texture,
+        desc: &gpu::TextureDesc,
+        mip_level: u32,
+        array_layer: u32,
+        gpu: &gpu::Context,
+    ) -> gpu::TextureView {
+        let view = gpu.create_texture_view(
+            texture,
+            gpu::TextureViewDesc {
+                name: &format!("{}/mip{}[{}]", desc.name, mip_level, array_layer),
+                format: desc.format,
+                dimension: gpu::ViewDimension::D2,
+                subresources: &gpu::TextureSubresources {
+                    base_mip_level: mip_level,
+                    mip_level_count: NonZeroU32::new(1),
+                    base_array_layer: array_layer,
+                    array_layer_count: NonZeroU32::new(1),
+                },
+            },
+        );
+        self.active_views.push(view);
+        view
+    }
+
+    fn free_finished_views(&mut self, gpu: &gpu::Context) {
+        self.pending_views.retain_mut(|(sp, views)| {
+            if gpu.wait_for(sp, 0) {
+                for view in views.drain(..) {
+                    gpu.destroy_texture_view(view);
+                }
+                false
+            } else {
+                true
+            }
+        });
+    }
+
+    /// Generate all the mip levels of a texture, starting from mip 0.
+    ///
+    /// The `desc` has to match the one the texture was created with.
+    /// Every array layer of a 2D texture gets its own mip chain.
+    #[profiling::function]
+    pub fn generate(
+        &mut self,
+        encoder: &mut gpu::CommandEncoder,
+        texture: gpu::Texture,
+        desc: &gpu::TextureDesc,
+        gpu: &gpu::Context,
+    ) {
+        assert_eq!(
+            desc.dimension,
+            gpu::TextureDimension::D2,
+            "Only 2D textures are supported"
+        );
+        assert!(
+            desc.usage.contains(gpu::TextureUsage::RESOURCE),
+            "Texture '{}' needs the RESOURCE usage",
+            desc.name
+        );
+        self.free_finished_views(gpu);
+
+        if desc.usage.contains(gpu::TextureUsage::TARGET) {
+            self.render_pipelines
+                .entry(desc.format)
+                .or_insert_with(|| Self::create_render_pipeline(&self.shader, desc.format, gpu));
+            for array_layer in 0..desc.array_layer_count {
+                for mip_level in 1..desc.mip_level_count {
+                    let source = self.create_view(texture, desc, mip_level - 1, array_layer, gpu);
+                    let target = self.create_view(texture, desc, mip_level, array_layer, gpu);
+                    let mut pass = encoder.render(
+                        "mipmap",
+                        gpu::RenderTargetSet {
+                            colors: &[gpu::RenderTarget {
+                                view: target,
+                                init_op: gpu::InitOp::DontCare,
+                                finish_op: gpu::FinishOp::Store,
+                            }],
+                            depth_stencil: None,
+                        },
+                    );
+                    let mut pc = pass.with(&self.render_pipelines[&desc.format]);
+                    pc.bind(
+                        0,
+                        &BlitData {
+                            source,
+                            source_sampler: self.sampler,
+                        },
+                    );
+                    pc.draw(0, 3, 0, 1);
+                }
+            }
+        } else if desc.usage.contains(gpu::TextureUsage::STORAGE) {
+            let format_name = storage_format_name(desc.format).unwrap_or_else(|| {
+                panic!("Format {:?} can't be downsampled by compute", desc.format)
+            });
+            self.compute_pipelines
+                .entry(desc.format)
+                .or_insert_with(|| Self::create_compute_pipeline(format_name, gpu));
+            for array_layer in 0..desc.array_layer_count {
+                for mip_level in 1..desc.mip_level_count {
+                    let source = self.create_view(texture, desc, mip_level - 1, array_layer, gpu);
+                    let destination = self.create_view(texture, desc, mip_level, array_layer, gpu);
+                    let pipeline = &self.compute_pipelines[&desc.format];
+                    let groups = pipeline.get_dispatch_for(desc.size.at_mip_level(mip_level));
+                    let mut pass = encoder.compute("mipmap");
+                    let mut pc = pass.with(pipeline);
+                    pc.bind(
+                        0,
+                        &DownsampleData {
+                            source,
+                            destination,
+                        },
+                    );
+                    pc.dispatch(groups);
+                }
+            }
+        } else {
+            panic!(
+                "Texture '{}' needs either TARGET or STORAGE usage",
+                desc.name
+            );
+        }
+    }
+
+    /// Mark the generated mips as used by GPU with a given sync point.
+    pub fn flush(&mut self, sp: &gpu::SyncPoint) {
+        if !self.active_views.is_empty() {
+            let views = mem::take(&mut self.active_views);
+            self.pending_views.push((sp.clone(), views));
+        }
+    }
+
+    fn create_render_pipeline(
+        shader: &gpu::Shader,
+        format: gpu::TextureFormat,
+        gpu: &gpu::Context,
+    ) -> gpu::RenderPipeline {
+        let layout = <BlitData as gpu::ShaderData>::layout();
+        gpu.create_render_pipeline(gpu::RenderPipelineDesc {
+            name: "mipmap",
+            data_layouts: &[&layout],
+            vertex: shader.at("vs_main"),
+            vertex_fetches: &[],
+            primitive: gpu::PrimitiveState {
+                topology: gpu::PrimitiveTopology::TriangleList,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            fragment: Some(shader.at("fs_main")),
+            color_targets: &[format.into()],
+            multisample_state: Default::default(),
+        })
+    }
+
+    fn create_compute_pipeline(format_name: &str, gpu: &gpu::Context) -> gpu::ComputePipeline {
+        let source = COMPUTE_SHADER_SOURCE.replace("STORAGE_FORMAT", format_name);
+        let shader = gpu.create_shader(gpu::ShaderDesc { source: &source });
+        let layout = <DownsampleData as gpu::ShaderData>::layout();
+        gpu.create_compute_pipeline(gpu::ComputePipelineDesc {
+            name: "mipmap",
+            data_layouts: &[&layout],
+            compute: shader.at("downsample"),
+        })
+    }
+}
@@ -203,8 +203,8 @@ fn main() {
     });
     command_encoder.start();
     let mut dummy = blade_render::DummyResources::new(&mut command_encoder, &context);
-    let mut temp_buffers = Vec::new();
-    asset_hub.flush(&mut command_encoder, &mut temp_buffers);
+    let mut temp = blade_render::FrameResources::default();
+    asset_hub.flush(&mut command_encoder, &mut temp);
 
     let mut env_map = blade_render::EnvironmentMap::new(
         asset_hub.shaders[shader_init_handle].raw.as_ref().unwrap(),
@@ -229,9 +229,7 @@ fn main() {
 
     context.wait_for(&sync_point, !0);
     context.destroy_command_encoder(&mut command_encoder);
-    for buffer in temp_buffers {
-        context.destroy_buffer(buffer);
-    }
+    temp.free(&context);
     env_map.destroy(&context);
     env_sampler.destroy(&context);
     dummy.destroy(&context);
@@ -1,12 +1,25 @@
-use blade_helpers::{Camera, ControlledCamera};
+use blade_helpers::{Camera, ControlledCamera, FollowCamera, FpsCamera, OrbitCamera};
 use std::{f32::consts, path::PathBuf, time};
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum CameraMode {
+    Fly,
+    Orbit,
+    Fps,
+    Follow,
+}
+
 struct Game {
     // engine stuff
     engine: blade::Engine,
     last_update: time::Instant,
     is_paused: bool,
+    camera_mode: CameraMode,
     camera: ControlledCamera,
+    orbit_camera: OrbitCamera,
+    fps_camera: FpsCamera,
+    follow_camera: FollowCamera,
+    is_look_active: bool,
     // windowing
     window: winit::window::Window,
     egui_state: egui_winit::State,
@@ -39,15 +52,22 @@ impl Game {
 
         let window = event_loop.create_window(window_attributes).unwrap();
 
-        let camera = ControlledCamera {
-            inner: Camera {
+        let camera = ControlledCamera::new(
+            Camera {
                 pos: glam::Vec3::new(0.0, 1.0, 10.0).into(),
                 rot: glam::Quat::from_rotation_x(consts::PI * 0.0).into(),
                 fov_y: 1.0,
                 depth: 0.0,
             },
-            fly_speed: 10.0,
-        };
+            10.0,
+        );
+        let mut orbit_camera = OrbitCamera::default();
+        orbit_camera.target = glam::Vec3::new(0.0, 1.0, 0.0).into();
+        orbit_camera.distance = 12.0;
+        let mut fps_camera = FpsCamera::default();
+        fps_camera.pos = glam::Vec3::new(0.0, 1.5, 10.0).into();
+        let mut follow_camera = FollowCamera::default();
+        follow_camera.look_offset = glam::Vec3::new(0.0, 0.5, 0.0).into();
 
         let data_path = PathBuf::from("examples/move/data");
         let mut engine = blade::Engine::new(
@@ -56,6 +76,7 @@ impl Game {
                 shader_path: "blade-render/code".to_string(),
                 data_path: data_path.as_os_str().to_string_lossy().into_owned(),
                 time_step: 0.01,
+                collision_layers: Vec::new(),
             },
         );
 
@@ -95,7 +116,12 @@ impl Game {
             engine,
             last_update: time::Instant::now(),
             is_paused: false,
+            camera_mode: CameraMode::Fly,
             camera,
+            orbit_camera,
+            fps_camera,
+            follow_camera,
+            is_look_active: false,
             window,
             egui_state,
             egui_viewport_id,
@@ -112,13 +138,13 @@ impl Game {
     fn update_time(&mut self) {
         let engine_dt = self.last_update.elapsed().as_secs_f32();
         self.last_update = time::Instant::now();
+        let position = glam::Vec3::new(0.0, 1.0, 0.0)
+            + 5.0 * glam::Vec3::new(self.angle.sin(), 0.0, self.angle.cos());
         if !self.is_paused {
             self.engine.teleport_object(
                 self.object_handle,
                 blade::Transform {
-                    position: (glam::Vec3::new(0.0, 1.0, 0.0)
-                        + 5.0 * glam::Vec3::new(self.angle.sin(), 0.0, self.angle.cos()))
-                    .into(),
+                    position: position.into(),
                     orientation: (glam::Quat::from_rotation_y(self.angle)
                         * glam::Quat::from_rotation_z(-10.0 * self.angle))
                     .into(),
@@ -127,6 +153,33 @@ impl Game {
             self.angle += 1.0 * engine_dt;
             self.engine.update(engine_dt);
         }
+
+        self.fps_camera.update(engine_dt);
+        // Follow the heading of the object, ignoring its rolling.
+        self.follow_camera.update(
+            position.into(),
+            glam::Quat::from_rotation_y(self.angle + consts::FRAC_PI_2).into(),
+            engine_dt,
+        );
+    }
+
+    fn camera(&self) -> Camera {
+        match self.camera_mode {
+            CameraMode::Fly => self.camera.inner,
+            CameraMode::Orbit => self.orbit_camera.camera(),
+            CameraMode::Fps => self.fps_camera.camera(),
+            CameraMode::Follow => self.follow_camera.camera(),
+        }
+    }
+
+    fn on_device_event(&mut self, event: &winit::event::DeviceEvent) {
+        if let winit::event::DeviceEvent::MouseMotion { delta } = *event {
+            // The FPS camera only looks around while the middle button is held,
+            // to keep the mouse usable for the UI.
+            if self.camera_mode == CameraMode::Fps && self.is_look_active {
+                self.fps_camera.on_mouse_motion(delta);
+            }
+        }
     }
 
     fn on_event(
@@ -149,18 +202,31 @@ impl Game {
                 event:
                     winit::event::KeyEvent {
                         physical_key: winit::keyboard::PhysicalKey::Code(key_code),
-                        state: winit::event::ElementState::Pressed,
+                        state,
                         ..
                     },
                 ..
             } => {
-                if key_code == winit::keyboard::KeyCode::Escape {
+                let is_pressed = state == winit::event::ElementState::Pressed;
+                if key_code == winit::keyboard::KeyCode::Escape && is_pressed {
                     return Err(QuitEvent);
                 }
-                if self.camera.on_key(key_code, delta) {
-                    self.is_debug_active = false;
+                match self.camera_mode {
+                    CameraMode::Fly => {
+                        if is_pressed && self.camera.on_key(key_code, delta) {
+                            self.is_debug_active = false;
+                        }
+                    }
+                    CameraMode::Fps => {
+                        self.fps_camera.on_key(key_code, state);
+                    }
+                    CameraMode::Orbit | CameraMode::Follow => {}
                 }
             }
+            winit::event::WindowEvent::Focused(false) => {
+                self.fps_camera.release_keys();
+                self.is_look_active = false;
+            }
             winit::event::WindowEvent::CloseRequested => {
                 return Err(QuitEvent);
             }
@@ -187,9 +253,24 @@ impl Game {
                 };
                 self.is_debug_active |= self.is_point_selected;
             }
+            winit::event::WindowEvent::MouseInput { state, button, .. } => match self.camera_mode {
+                CameraMode::Orbit => {
+                    self.orbit_camera.on_mouse_button(button, state);
+                }
+                CameraMode::Fps if button == winit::event::MouseButton::Middle => {
+                    self.is_look_active = state == winit::event::ElementState::Pressed;
+                }
+                _ => {}
+            },
             winit::event::WindowEvent::CursorMoved { position, .. } => {
                 self.last_mouse_pos = [position.x as i32, position.y as i32];
+                self.orbit_camera.on_cursor_moved(position);
             }
+            winit::event::WindowEvent::MouseWheel { delta, .. } => match self.camera_mode {
+                CameraMode::Fly => self.camera.on_wheel(delta),
+                CameraMode::Orbit => self.orbit_camera.on_wheel(delta),
+                CameraMode::Fps | CameraMode::Follow => {}
+            },
             _ => {}
         }
 
@@ -207,7 +288,27 @@ impl Game {
         egui::CollapsingHeader::new("Camera")
             .default_open(true)
             .show(ui, |ui| {
-                self.camera.populate_hud(ui);
+                ui.horizontal(|ui| {
+                    for mode in [
+                        CameraMode::Fly,
+                        CameraMode::Orbit,
+                        CameraMode::Fps,
+                        CameraMode::Follow,
+                    ] {
+                        ui.selectable_value(&mut self.camera_mode, mode, format!("{mode:?}"));
+                    }
+                });
+                match self.camera_mode {
+                    CameraMode::Fly => self.camera.populate_hud(ui),
+                    CameraMode::Orbit => self.orbit_camera.populate_hud(ui),
+                    CameraMode::Fps => self.fps_camera.populate_hud(ui),
+                    CameraMode::Follow => {
+                        ui.add(
+                            egui::Slider::new(&mut self.follow_camera.stiffness, 0.1f32..=20.0f32)
+                                .text("Stiffness"),
+                        );
+                    }
+                }
             });
         self.engine.populate_hud(ui);
     }
@@ -249,7 +350,7 @@ impl Game {
             .egui_ctx()
             .tessellate(egui_output.shapes, egui_output.pixels_per_point);
         self.engine.render(
-            &self.camera.inner.into(),
+            &self.camera().into(),
             &primitives,
             &egui_output.textures_delta,
             self.window.inner_size(),
@@ -275,6 +376,9 @@ fn main() {
                 winit::event::Event::AboutToWait => {
                     game.window.request_redraw();
                 }
+                winit::event::Event::DeviceEvent { event, .. } => {
+                    game.on_device_event(&event);
+                }
                 winit::event::Event::WindowEvent { event, .. } => match game.on_event(&event) {
                     Ok(control_flow) => {
                         target.set_control_flow(control_flow);
@@ -9,7 +9,6 @@ struct Example {
     prev_sync_point: Option<gpu::SyncPoint>,
     context: gpu::Context,
     surface: gpu::Surface,
-    gui_painter: blade_egui::GuiPainter,
 
     particle_system: particle::System,
 
@@ -135,7 +134,6 @@ impl Example {
             .find(|&n| context.supports_texture_sample_count(n))
             .unwrap_or(1);
 
-        let gui_painter = blade_egui::GuiPainter::new(surface_info, &context);
         let particle_system = particle::System::new(
             &context,
             particle::SystemDesc {
@@ -159,7 +157,6 @@ impl Example {
             prev_sync_point: Some(sync_point),
             context,
             surface,
-            gui_painter,
             particle_system,
             sample_count,
             msaa_texture: None,
@@ -174,7 +171,6 @@ impl Example {
         }
         self.context
             .destroy_command_encoder(&mut self.command_encoder);
-        self.gui_painter.destroy(&self.context);
         self.particle_system.destroy(&self.context);
         self.context.destroy_surface(&mut self.surface);
 
@@ -186,16 +182,8 @@ impl Example {
         }
     }
 
-    fn render(
-        &mut self,
-        gui_primitives: &[egui::ClippedPrimitive],
-        gui_textures: &egui::TexturesDelta,
-        screen_desc: &blade_egui::ScreenDescriptor,
-    ) {
-        self.recreate_msaa_texutres_if_needed(
-            screen_desc.physical_size,
-            self.surface.info().format,
-        );
+    fn render(&mut self, gui: &mut blade_egui::GuiWindow, physical_size: (u32, u32)) {
+        self.recreate_msaa_texutres_if_needed(physical_size, self.surface.info().format);
 
         let frame = self.surface.acquire_frame();
         let frame_view = frame.texture_view();
@@ -205,8 +193,7 @@ impl Example {
         }
         self.command_encoder.init_texture(frame.texture());
 
-        self.gui_painter
-            .update_textures(&mut self.command_encoder, gui_textures, &self.context);
+        gui.update_textures(&mut self.command_encoder, &self.context);
         self.particle_system.update(&mut self.command_encoder);
 
         if self.sample_count <= 1 && !self.export_image {
@@ -221,10 +208,8 @@ impl Example {
                     depth_stencil: None,
                 },
             ) {
-                self.particle_system
-                    .draw(&mut pass, screen_desc.physical_size);
-                self.gui_painter
-                    .paint(&mut pass, gui_primitives, screen_desc, &self.context);
+                self.particle_system.draw(&mut pass, physical_size);
+                gui.paint(&mut pass, &self.context);
             }
         } else {
             if let mut pass = self.command_encoder.render(
@@ -242,8 +227,7 @@ impl Example {
                     depth_stencil: None,
                 },
             ) {
-                self.particle_system
-                    .draw(&mut pass, screen_desc.physical_size);
+                self.particle_system.draw(&mut pass, physical_size);
             }
             if let mut pass = self.command_encoder.render(
                 "draw ui",
@@ -256,14 +240,13 @@ impl Example {
                     depth_stencil: None,
                 },
             ) {
-                self.gui_painter
-                    .paint(&mut pass, gui_primitives, screen_desc, &self.context);
+                gui.paint(&mut pass, &self.context);
             }
         }
 
         self.command_encoder.present(frame);
         let sync_point = self.context.submit(&mut self.command_encoder);
-        self.gui_painter.after_submit(&sync_point);
+        gui.after_submit(&sync_point);
 
         if let Some(sp) = self.prev_sync_point.take() {
             self.context.wait_for(&sp, !0);
@@ -342,11 +325,8 @@ fn main() {
 
     let window = event_loop.create_window(window_attributes).unwrap();
 
-    let egui_ctx = egui::Context::default();
-    let viewport_id = egui_ctx.viewport_id();
-    let mut egui_winit = egui_winit::State::new(egui_ctx, viewport_id, &window, None, None, None);
-
     let mut example = Example::new(&window);
+    let mut gui = blade_egui::GuiWindow::new(&window, example.surface.info(), &example.context);
 
     event_loop
         .run(|event, target| {
@@ -357,7 +337,7 @@ fn main() {
                 }
 
                 winit::event::Event::WindowEvent { event, .. } => {
-                    let response = egui_winit.on_window_event(&window, &event);
+                    let response = gui.on_window_event(&window, &event);
                     if response.consumed {
                         return;
                     }
@@ -389,8 +369,7 @@ fn main() {
                         }
 
                         winit::event::WindowEvent::RedrawRequested => {
-                            let raw_input = egui_winit.take_egui_input(&window);
-                            let egui_output = egui_winit.egui_ctx().run(raw_input, |egui_ctx| {
+                            let repaint_delay = gui.run(&window, |egui_ctx| {
                                 egui::SidePanel::left("info").show(egui_ctx, |ui| {
                                     ui.add_space(5.0);
                                     example.add_gui(ui);
@@ -402,16 +381,6 @@ fn main() {
                                 });
                             });
 
-                            egui_winit.handle_platform_output(&window, egui_output.platform_output);
-                            let repaint_delay =
-                                egui_output.viewport_output[&viewport_id].repaint_delay;
-
-                            let pixels_per_point =
-                                egui_winit::pixels_per_point(egui_winit.egui_ctx(), &window);
-                            let primitives = egui_winit
-                                .egui_ctx()
-                                .tessellate(egui_output.shapes, pixels_per_point);
-
                             let control_flow = if let Some(repaint_after_instant) =
                                 std::time::Instant::now().checked_add(repaint_delay)
                             {
@@ -423,14 +392,8 @@ fn main() {
                             };
                             target.set_control_flow(control_flow);
 
-                            //Note: this will probably look different with proper support for resizing
                             let window_size = window.inner_size();
-                            let screen_desc = blade_egui::ScreenDescriptor {
-                                physical_size: (window_size.width, window_size.height),
-                                scale_factor: pixels_per_point,
-                            };
-
-                            example.render(&primitives, &egui_output.textures_delta, &screen_desc);
+                            example.render(&mut gui, (window_size.width, window_size.height));
                         }
                         _ => {}
                     }
@@ -441,4 +404,5 @@ fn main() {
         .unwrap();
 
     example.destroy();
+    gui.destroy(&example.context);
 }
@@ -398,6 +398,18 @@ impl Example {
                 self.pacer.last_sync_point().unwrap(),
             );
         }
+        if self.track_hot_reloads && self.scene_load_task.is_none() {
+            let tasks = self
+                .asset_hub
+                .hot_reload_models(self.objects.iter_mut().map(|object| &mut object.model));
+            if !tasks.is_empty() {
+                log::info!("Hot reloading models");
+                for task in tasks {
+                    task.join();
+                }
+                self.have_objects_changed = true;
+            }
+        }
 
         // Note: the resize is split in 2 parts because `wait_for_previous_frame`
         // wants to borrow `self` mutably, and `command_encoder` blocks that.
@@ -420,11 +432,17 @@ impl Example {
         self.gui_painter
             .update_textures(command_encoder, gui_textures, &self.context);
 
-        self.asset_hub.flush(command_encoder, &mut temp.buffers);
+        self.renderer.stream_textures(&self.asset_hub, temp);
+        self.asset_hub.flush(command_encoder, temp);
 
         if let Some(ref task) = self.scene_load_task {
             if task.is_done() {
                 log::info!("Scene is loaded");
+                for (object, extra) in self.objects.iter().zip(self.object_extras.iter()) {
+                    if let Some(error) = self.asset_hub.models.get_error(object.model) {
+                        log::error!("Model {} is skipped: {}", extra.path.display(), error);
+                    }
+                }
                 self.scene_load_task = None;
                 self.have_objects_changed = true;
             }
@@ -561,6 +579,7 @@ impl Example {
                 ui.label("Loading...");
                 ui.spinner();
             });
+            blade_helpers::populate_load_progress(&self.asset_hub, ui);
             //TODO: seeing GPU Device Lost issues without this
             for task in self.asset_hub.list_running_tasks() {
                 ui.label(format!("{}", task.as_ref()));
@@ -744,7 +763,10 @@ impl Example {
     fn find_object(&self, geometry_index: u32) -> Option<usize> {
         let mut index = geometry_index as usize;
         for (obj_index, object) in self.objects.iter().enumerate() {
-            let model = &self.asset_hub.models[object.model];
+            let model = match self.asset_hub.models.get(object.model) {
+                Some(model) => model,
+                None => continue,
+            };
             match index.checked_sub(model.geometries.len()) {
                 Some(i) => index = i,
                 None => return Some(obj_index),
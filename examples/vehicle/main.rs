@@ -1,20 +1,13 @@
 mod config;
 
-use std::{f32::consts, fs, mem, ops, path::PathBuf, time};
-
-#[derive(Clone)]
-struct Wheel {
-    object: blade::ObjectHandle,
-    spin_joint: blade::JointHandle,
-    suspender: Option<blade::ObjectHandle>,
-    steer_joint: Option<blade::JointHandle>,
-}
+use std::{f32::consts, fs, ops, path::PathBuf, time};
 
 struct Vehicle {
+    handle: blade::VehicleHandle,
     body_handle: blade::ObjectHandle,
+    input: blade::VehicleInput,
     jump_impulse: f32,
     roll_impulse: f32,
-    wheels: Vec<Wheel>,
 }
 
 struct Game {
@@ -107,6 +100,7 @@ impl Game {
                 shader_path: "blade-render/code".to_string(),
                 data_path: data_path.as_os_str().to_string_lossy().into_owned(),
                 time_step: 0.01,
+                collision_layers: Vec::new(),
             },
         );
 
@@ -128,179 +122,21 @@ impl Game {
             &fs::read(data_path.join("raceFuture.ron")).expect("Unable to open the vehicle config"),
         )
         .expect("Unable to parse the vehicle config");
-        let body_config = blade::config::Object {
-            name: "vehicle/body".to_string(),
-            visuals: vec![veh_config.body.visual],
-            colliders: vec![veh_config.body.collider],
-            additional_mass: None,
-        };
         let spawn_pos = glam::Vec3::from(lev_config.spawn_pos);
-        let mut vehicle = Vehicle {
-            body_handle: engine.add_object(
-                &body_config,
-                blade::Transform {
-                    position: spawn_pos.into(),
-                    ..Default::default()
-                },
-                blade::DynamicInput::Full,
-            ),
+        let vehicle_handle = engine.add_vehicle(
+            &veh_config.model,
+            blade::Transform {
+                position: spawn_pos.into(),
+                ..Default::default()
+            },
+        );
+        let vehicle = Vehicle {
+            handle: vehicle_handle,
+            body_handle: engine.get_vehicle_body(vehicle_handle),
+            input: blade::VehicleInput::default(),
             jump_impulse: veh_config.jump_impulse,
             roll_impulse: veh_config.roll_impulse,
-            wheels: Vec::new(),
-        };
-        let wheel_config = blade::config::Object {
-            name: "vehicle/wheel".to_string(),
-            visuals: vec![veh_config.wheel.visual],
-            colliders: vec![veh_config.wheel.collider],
-            additional_mass: None,
         };
-        let suspender_config = blade::config::Object {
-            name: "vehicle/suspender".to_string(),
-            visuals: vec![],
-            colliders: vec![],
-            additional_mass: Some(veh_config.suspender),
-        };
-
-        //Note: in the vehicle coordinate system X=left, Y=up, Z=forward
-        for ac in veh_config.axles {
-            for wheel_x in ac.x_wheels {
-                let offset = glam::Vec3::new(wheel_x, ac.y, ac.z);
-                let rotation = if wheel_x > 0.0 {
-                    glam::Quat::from_rotation_y(consts::PI)
-                } else {
-                    glam::Quat::IDENTITY
-                };
-
-                let wheel_handle = engine.add_object(
-                    &wheel_config,
-                    blade::Transform {
-                        position: (spawn_pos + offset).into(),
-                        orientation: rotation.into(),
-                    },
-                    blade::DynamicInput::Full,
-                );
-                let wheel_angular_freedoms = mint::Vector3 {
-                    x: Some(blade::FreedomAxis {
-                        limits: None,
-                        motor: Some(blade::config::Motor {
-                            stiffness: 0.0,
-                            damping: veh_config.drive_factor,
-                            max_force: 1000.0,
-                        }),
-                    }),
-                    y: None,
-                    z: None,
-                };
-
-                vehicle.wheels.push(
-                    if ac.max_steering_angle > 0.0 || ac.max_suspension_offset > 0.0 {
-                        let max_angle = ac.max_steering_angle.to_radians();
-                        let suspender_handle = engine.add_object(
-                            &suspender_config,
-                            blade::Transform {
-                                position: (spawn_pos + offset).into(),
-                                ..Default::default()
-                            },
-                            blade::DynamicInput::Full,
-                        );
-
-                        let suspension_joint = engine.add_joint(
-                            vehicle.body_handle,
-                            suspender_handle,
-                            blade::JointDesc {
-                                parent_anchor: blade::Transform {
-                                    position: offset.into(),
-                                    ..Default::default()
-                                },
-                                linear: mint::Vector3 {
-                                    x: None,
-                                    y: if ac.max_suspension_offset > 0.0 {
-                                        Some(blade::FreedomAxis {
-                                            limits: Some(0.0..ac.max_suspension_offset),
-                                            motor: Some(ac.suspension),
-                                        })
-                                    } else {
-                                        None
-                                    },
-                                    z: None,
-                                },
-                                angular: mint::Vector3 {
-                                    x: None,
-                                    y: if ac.max_steering_angle > 0.0 {
-                                        Some(blade::FreedomAxis {
-                                            limits: Some(-max_angle..max_angle),
-                                            motor: Some(ac.steering),
-                                        })
-                                    } else {
-                                        None
-                                    },
-                                    z: None,
-                                },
-                                ..Default::default()
-                            },
-                        );
-
-                        let wheel_joint = engine.add_joint(
-                            suspender_handle,
-                            wheel_handle,
-                            blade::JointDesc {
-                                child_anchor: blade::Transform {
-                                    orientation: rotation.into(),
-                                    ..Default::default()
-                                },
-                                angular: wheel_angular_freedoms,
-                                ..Default::default()
-                            },
-                        );
-
-                        let _extra_joint = engine.add_joint(
-                            vehicle.body_handle,
-                            wheel_handle,
-                            blade::JointDesc {
-                                linear: blade::FreedomAxis::ALL_FREE,
-                                angular: blade::FreedomAxis::ALL_FREE,
-                                ..Default::default()
-                            },
-                        );
-
-                        Wheel {
-                            object: wheel_handle,
-                            spin_joint: wheel_joint,
-                            suspender: Some(suspender_handle),
-                            steer_joint: if ac.max_steering_angle > 0.0 {
-                                Some(suspension_joint)
-                            } else {
-                                None
-                            },
-                        }
-                    } else {
-                        let wheel_joint = engine.add_joint(
-                            vehicle.body_handle,
-                            wheel_handle,
-                            blade::JointDesc {
-                                parent_anchor: blade::Transform {
-                                    position: offset.into(),
-                                    ..Default::default()
-                                },
-                                child_anchor: blade::Transform {
-                                    orientation: rotation.into(),
-                                    ..Default::default()
-                                },
-                                angular: wheel_angular_freedoms,
-                                ..Default::default()
-                            },
-                        );
-
-                        Wheel {
-                            object: wheel_handle,
-                            spin_joint: wheel_joint,
-                            suspender: None,
-                            steer_joint: None,
-                        }
-                    },
-                );
-            }
-        }
 
         let egui_context = egui::Context::default();
         let egui_viewport_id = egui_context.viewport_id();
@@ -323,58 +159,20 @@ impl Game {
         }
     }
 
-    fn set_velocity(&mut self, velocity: f32) {
-        self.engine.wake_up(self.vehicle.body_handle);
+    fn update_input(&mut self) {
         self.update_time();
-        for wheel in self.vehicle.wheels.iter() {
-            self.engine.set_joint_motor(
-                wheel.spin_joint,
-                blade::JointAxis::AngularX,
-                0.0,
-                velocity,
-            );
-        }
-    }
-
-    fn set_steering(&mut self, angle_rad: f32) {
-        self.update_time();
-        for wheel in self.vehicle.wheels.iter() {
-            if let Some(handle) = wheel.steer_joint {
-                self.engine
-                    .set_joint_motor(handle, blade::JointAxis::AngularY, angle_rad, 0.0);
-            }
-        }
-    }
-
-    fn teleport_object_rel(&mut self, handle: blade::ObjectHandle, isometry: &Isometry) {
-        let prev_transform = self
-            .engine
-            .get_object_transform(handle, blade::Prediction::LastKnown);
-        let next = isometry.clone() * Isometry::from(prev_transform);
-        self.engine.teleport_object(handle, next.to_blade());
+        self.engine
+            .set_vehicle_input(self.vehicle.handle, self.vehicle.input);
     }
 
     fn teleport(&mut self, position: glam::Vec3) {
-        let old_transform = self
-            .engine
-            .get_object_transform(self.vehicle.body_handle, blade::Prediction::LastKnown);
-        let old_isometry_inv = Isometry::from(old_transform).inverse();
-        let new_transform = blade::Transform {
-            position: position.into(),
-            ..Default::default()
-        };
-        self.engine
-            .teleport_object(self.vehicle.body_handle, new_transform.clone());
-
-        let relative = Isometry::from(new_transform) * old_isometry_inv;
-        let wheels = mem::take(&mut self.vehicle.wheels);
-        for wheel in wheels.iter() {
-            if let Some(suspender) = wheel.suspender {
-                self.teleport_object_rel(suspender, &relative);
-            }
-            self.teleport_object_rel(wheel.object, &relative);
-        }
-        self.vehicle.wheels = wheels;
+        self.engine.teleport_vehicle(
+            self.vehicle.handle,
+            blade::Transform {
+                position: position.into(),
+                ..Default::default()
+            },
+        );
     }
 
     fn update_time(&mut self) {
@@ -412,16 +210,20 @@ impl Game {
                     return Err(QuitEvent);
                 }
                 winit::keyboard::KeyCode::ArrowUp => {
-                    self.set_velocity(100.0);
+                    self.vehicle.input.throttle = 1.0;
+                    self.update_input();
                 }
                 winit::keyboard::KeyCode::ArrowDown => {
-                    self.set_velocity(-20.0);
+                    self.vehicle.input.throttle = -1.0;
+                    self.update_input();
                 }
                 winit::keyboard::KeyCode::ArrowLeft => {
-                    self.set_steering(1.0);
+                    self.vehicle.input.steering = 1.0;
+                    self.update_input();
                 }
                 winit::keyboard::KeyCode::ArrowRight => {
-                    self.set_steering(-1.0);
+                    self.vehicle.input.steering = -1.0;
+                    self.update_input();
                 }
                 winit::keyboard::KeyCode::Comma => {
                     let transform = self.engine.get_object_transform(
@@ -469,10 +271,12 @@ impl Game {
                 ..
             } => match key_code {
                 winit::keyboard::KeyCode::ArrowUp | winit::keyboard::KeyCode::ArrowDown => {
-                    self.set_velocity(0.0);
+                    self.vehicle.input.throttle = 0.0;
+                    self.update_input();
                 }
                 winit::keyboard::KeyCode::ArrowLeft | winit::keyboard::KeyCode::ArrowRight => {
-                    self.set_steering(0.0);
+                    self.vehicle.input.steering = 0.0;
+                    self.update_input();
                 }
                 _ => {}
             },
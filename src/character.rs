@@ -0,0 +1,311 @@
+use crate::{config, CollisionGroups, DynamicInput, Engine, ObjectHandle, Physics, Transform};
+use rapier3d::control::{CharacterAutostep, CharacterLength, KinematicCharacterController};
+
+/// Current state of a character, as seen after the last physics step.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct CharacterState {
+    /// Character is standing on the ground.
+    pub is_grounded: bool,
+    /// Character is sliding down a slope that is too steep to stand on.
+    pub is_sliding: bool,
+}
+
+pub(super) struct Character {
+    controller: KinematicCharacterController,
+    collider: rapier3d::geometry::ColliderHandle,
+    /// Velocity requested by the user.
+    velocity: nalgebra::Vector3<f32>,
+    /// Velocity accumulated from gravity and jumps.
+    fall_velocity: nalgebra::Vector3<f32>,
+    state: CharacterState,
+}
+
+impl Character {
+    fn new(config: &config::Character, collider: rapier3d::geometry::ColliderHandle) -> Self {
+        let controller = KinematicCharacterController {
+            offset: CharacterLength::Absolute(config.offset),
+            slide: config.slide,
+            autostep: if config.step_height > 0.0 {
+                Some(CharacterAutostep {
+                    max_height: CharacterLength::Absolute(config.step_height),
+                    min_width: CharacterLength::Absolute(config.step_min_width),
+                    include_dynamic_bodies: false,
+                })
+            } else {
+                None
+            },
+            max_slope_climb_angle: config.max_climb_slope.to_radians(),
+            min_slope_slide_angle: config.min_slide_slope.to_radians(),
+            snap_to_ground: if config.snap_to_ground > 0.0 {
+                Some(CharacterLength::Absolute(config.snap_to_ground))
+            } else {
+                None
+            },
+            ..Default::default()
+        };
+        Self {
+            controller,
+            collider,
+            velocity: nalgebra::Vector3::zeros(),
+            fall_velocity: nalgebra::Vector3::zeros(),
+            state: CharacterState::default(),
+        }
+    }
+
+    /// Move the character body for the duration of a single physics step.
+    fn step(&mut self, rigid_body: rapier3d::dynamics::RigidBodyHandle, physics: &mut Physics) {
+        let dt = physics.integration_params.dt;
+        let up = self.controller.up.into_inner();
+        let body = &physics.rigid_bodies[rigid_body];
+        let position = *body.position();
+        let collider = &physics.colliders[self.collider];
+
+        self.fall_velocity += physics.gravity * dt;
+        let desired_translation = (self.velocity + self.fall_velocity) * dt;
+        let movement = self.controller.move_shape(
+            dt,
+            &physics.rigid_bodies,
+            &physics.colliders,
+            &physics.query_pipeline,
+            collider.shape(),
+            &position,
+            desired_translation,
+            rapier3d::pipeline::QueryFilter::new()
+                .exclude_rigid_body(rigid_body)
+                .groups(collider.collision_groups()),
+            |_| {},
+        );
+
+        let rise_speed = self.fall_velocity.dot(&up);
+        // Hitting the ground stops the fall, but we keep the
+        // gravity pushing down, so that the ground is detected.
+        if movement.grounded && rise_speed <= 0.0 {
+            self.fall_velocity = nalgebra::Vector3::zeros();
+        }
+        // Hitting the ceiling stops the rise, so that the character
+        // starts falling immediately instead of sticking to it.
+        let desired_rise = desired_translation.dot(&up);
+        if rise_speed > 0.0 && movement.translation.dot(&up) < 0.5 * desired_rise {
+            self.fall_velocity -= up * rise_speed;
+        }
+        self.state = CharacterState {
+            is_grounded: movement.grounded,
+            is_sliding: movement.is_sliding_down_slope,
+        };
+
+        let body = &mut physics.rigid_bodies[rigid_body];
+        body.set_next_kinematic_translation(position.translation.vector + movement.translation);
+    }
+}
+
+impl Engine {
+    /// Add a character object, which is kinematically moved
+    /// by a capsule-shaped controller.
+    ///
+    /// The capsule is centered at the object origin and is added on top
+    /// of any colliders from the object config.
+    pub fn add_character(
+        &mut self,
+        config: &config::Object,
+        character: &config::Character,
+        transform: Transform,
+    ) -> ObjectHandle {
+        let handle = self.add_object(config, transform, DynamicInput::SetPosition);
+        let groups = CollisionGroups {
+            memberships: self.get_collision_mask(&character.layers),
+            filter: self.get_collision_mask(&character.collides_with),
+        };
+        let object = &mut self.objects[handle.0];
+
+        let collider =
+            rapier3d::geometry::ColliderBuilder::capsule_y(character.half_height, character.radius)
+                .collision_groups(groups.into_rapier())
+                .build();
+        let c_handle = self.physics.colliders.insert_with_parent(
+            collider,
+            object.rigid_body,
+            &mut self.physics.rigid_bodies,
+        );
+        object.colliders.push(c_handle);
+
+        object.character = Some(Character::new(character, c_handle));
+        handle
+    }
+
+    fn character_mut(&mut self, handle: ObjectHandle) -> Option<&mut Character> {
+        self.objects.get_mut(handle.0)?.character.as_mut()
+    }
+
+    /// Set the velocity the character is trying to move with.
+    ///
+    /// Gravity is applied on top of it automatically.
+    /// Returns `false` if the object is not a character.
+    pub fn set_character_velocity(
+        &mut self,
+        handle: ObjectHandle,
+        velocity: mint::Vector3<f32>,
+    ) -> bool {
+        match self.character_mut(handle) {
+            Some(character) => {
+                character.velocity = velocity.into();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Make the character jump with a given vertical speed.
+    ///
+    /// Returns `false` if the object is not a character, or it's not on the ground.
+    pub fn jump_character(&mut self, handle: ObjectHandle, speed: f32) -> bool {
+        let character = match self.character_mut(handle) {
+            Some(character) => character,
+            None => return false,
+        };
+        if !character.state.is_grounded {
+            return false;
+        }
+        character.fall_velocity = character.controller.up.into_inner() * speed;
+        character.state.is_grounded = false;
+        true
+    }
+
+    /// Get the state of a character, or `None` if the object is not a character.
+    pub fn get_character_state(&self, handle: ObjectHandle) -> Option<CharacterState> {
+        let character = self.objects.get(handle.0)?.character.as_ref()?;
+        Some(character.state)
+    }
+
+    /// Move all the characters for the duration of a single physics step.
+    pub(super) fn step_characters(&mut self) {
+        for (_, object) in self.objects.iter_mut() {
+            if let Some(ref mut character) = object.character {
+                character.step(object.rigid_body, &mut self.physics);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Character;
+    use crate::{config, Physics};
+    use rapier3d::{
+        dynamics::{RigidBodyBuilder, RigidBodyHandle},
+        geometry::ColliderBuilder,
+        math::Vector,
+    };
+
+    const RADIUS: f32 = 0.3;
+    const HALF_HEIGHT: f32 = 0.5;
+
+    fn make_config() -> config::Character {
+        config::Character {
+            radius: RADIUS,
+            half_height: HALF_HEIGHT,
+            offset: 0.01,
+            max_climb_slope: 45.0,
+            min_slide_slope: 45.0,
+            step_height: 0.0,
+            step_min_width: 0.0,
+            snap_to_ground: 0.0,
+            slide: true,
+            layers: Vec::new(),
+            collides_with: Vec::new(),
+        }
+    }
+
+    /// Create a world with a floor at zero height, and optionally a ceiling.
+    fn make_world(
+        ceiling: Option<f32>,
+        start_height: f32,
+    ) -> (Physics, Character, RigidBodyHandle) {
+        let mut physics = Physics {
+            gravity: Vector::new(0.0, -9.81, 0.0),
+            ..Default::default()
+        };
+        let floor = ColliderBuilder::cuboid(10.0, 0.5, 10.0)
+            .translation(Vector::new(0.0, -0.5, 0.0))
+            .build();
+        physics.colliders.insert(floor);
+        if let Some(height) = ceiling {
+            let roof = ColliderBuilder::cuboid(10.0, 0.5, 10.0)
+                .translation(Vector::new(0.0, height + 0.5, 0.0))
+                .build();
+            physics.colliders.insert(roof);
+        }
+
+        let body = RigidBodyBuilder::kinematic_position_based()
+            .translation(Vector::new(0.0, start_height, 0.0))
+            .build();
+        let body_handle = physics.rigid_bodies.insert(body);
+        let collider = ColliderBuilder::capsule_y(HALF_HEIGHT, RADIUS).build();
+        let collider_handle =
+            physics
+                .colliders
+                .insert_with_parent(collider, body_handle, &mut physics.rigid_bodies);
+        physics.step();
+        let character = Character::new(&make_config(), collider_handle);
+        (physics, character, body_handle)
+    }
+
+    fn step(physics: &mut Physics, character: &mut Character, body: RigidBodyHandle) -> f32 {
+        character.step(body, physics);
+        physics.step();
+        physics.rigid_bodies[body].translation().y
+    }
+
+    #[test]
+    fn fall_to_ground() {
+        let (mut physics, mut character, body) = make_world(None, 2.0);
+        for _ in 0..120 {
+            step(&mut physics, &mut character, body);
+        }
+        let height = physics.rigid_bodies[body].translation().y;
+        assert!(character.state.is_grounded);
+        assert!((height - HALF_HEIGHT - RADIUS).abs() < 0.05, "{}", height);
+        assert_eq!(character.fall_velocity, nalgebra::Vector3::zeros());
+    }
+
+    #[test]
+    fn walk_on_ground() {
+        let (mut physics, mut character, body) = make_world(None, HALF_HEIGHT + RADIUS + 0.02);
+        character.velocity = nalgebra::Vector3::new(1.0, 0.0, 0.0);
+        for _ in 0..60 {
+            step(&mut physics, &mut character, body);
+        }
+        let position = physics.rigid_bodies[body].translation();
+        assert!(character.state.is_grounded);
+        assert!((position.x - 1.0).abs() < 0.05, "{}", position.x);
+    }
+
+    #[test]
+    fn bump_into_ceiling() {
+        let ceiling = 2.0 * (HALF_HEIGHT + RADIUS) + 0.2;
+        let (mut physics, mut character, body) =
+            make_world(Some(ceiling), HALF_HEIGHT + RADIUS + 0.02);
+        for _ in 0..10 {
+            step(&mut physics, &mut character, body);
+        }
+        assert!(character.state.is_grounded);
+
+        // Jump that would rise for a whole second without the ceiling
+        character.fall_velocity = nalgebra::Vector3::new(0.0, 10.0, 0.0);
+        character.state.is_grounded = false;
+        let mut max_height = 0.0f32;
+        for _ in 0..10 {
+            max_height = max_height.max(step(&mut physics, &mut character, body));
+        }
+        assert!(max_height + HALF_HEIGHT + RADIUS <= ceiling + 0.01);
+        assert!(
+            character.fall_velocity.y <= 0.0,
+            "{}",
+            character.fall_velocity.y
+        );
+
+        for _ in 0..30 {
+            step(&mut physics, &mut character, body);
+        }
+        assert!(character.state.is_grounded);
+    }
+}
@@ -1,4 +1,4 @@
-#[derive(serde::Deserialize)]
+#[derive(Clone, serde::Deserialize)]
 pub enum FrontFace {
     Cw,
     Ccw,
@@ -16,7 +16,7 @@ fn default_scale() -> f32 {
     1.0
 }
 
-#[derive(serde::Deserialize)]
+#[derive(Clone, serde::Deserialize)]
 pub struct Visual {
     pub model: String,
     #[serde(default)]
@@ -40,7 +40,7 @@ impl Default for Visual {
     }
 }
 
-#[derive(serde::Deserialize)]
+#[derive(Clone, serde::Deserialize)]
 pub enum Shape {
     Ball {
         radius: f32,
@@ -73,7 +73,7 @@ fn default_restitution() -> f32 {
     0.0
 }
 
-#[derive(serde::Deserialize)]
+#[derive(Clone, serde::Deserialize)]
 pub struct Collider {
     pub density: f32,
     pub shape: Shape,
@@ -85,9 +85,15 @@ pub struct Collider {
     pub pos: mint::Vector3<f32>,
     #[serde(default = "default_vec")]
     pub rot: mint::Vector3<f32>,
+    /// Collision layers this collider belongs to. Empty means all.
+    #[serde(default)]
+    pub layers: Vec<String>,
+    /// Collision layers this collider interacts with. Empty means all.
+    #[serde(default)]
+    pub collides_with: Vec<String>,
 }
 
-#[derive(serde::Deserialize)]
+#[derive(Clone, serde::Deserialize)]
 pub struct AdditionalMass {
     pub density: f32,
     pub shape: Shape,
@@ -102,6 +108,51 @@ pub struct Object {
     pub additional_mass: Option<AdditionalMass>,
 }
 
+fn default_character_offset() -> f32 {
+    0.01
+}
+fn default_slope() -> f32 {
+    45.0
+}
+fn default_slide() -> bool {
+    true
+}
+
+#[derive(serde::Deserialize)]
+pub struct Character {
+    /// Radius of the capsule.
+    pub radius: f32,
+    /// Half-height of the cylindrical part of the capsule.
+    pub half_height: f32,
+    /// Gap to preserve between the capsule and the surroundings.
+    #[serde(default = "default_character_offset")]
+    pub offset: f32,
+    /// Steepest slope (in degrees) that can be climbed.
+    #[serde(default = "default_slope")]
+    pub max_climb_slope: f32,
+    /// Slope (in degrees) at which the character starts sliding down.
+    #[serde(default = "default_slope")]
+    pub min_slide_slope: f32,
+    /// Maximum height of a step to climb automatically, if non-zero.
+    #[serde(default)]
+    pub step_height: f32,
+    /// Minimum free space required on top of a step.
+    #[serde(default)]
+    pub step_min_width: f32,
+    /// Maximum distance to snap down to the ground, if non-zero.
+    #[serde(default)]
+    pub snap_to_ground: f32,
+    /// Slide along the obstacles instead of stopping.
+    #[serde(default = "default_slide")]
+    pub slide: bool,
+    /// Collision layers the capsule belongs to. Empty means all.
+    #[serde(default)]
+    pub layers: Vec<String>,
+    /// Collision layers the capsule interacts with. Empty means all.
+    #[serde(default)]
+    pub collides_with: Vec<String>,
+}
+
 #[derive(Clone, Copy, Debug, Default, PartialEq, serde::Deserialize)]
 pub struct Motor {
     pub stiffness: f32,
@@ -109,14 +160,128 @@ pub struct Motor {
     pub max_force: f32,
 }
 
+#[derive(serde::Deserialize)]
+pub struct VehiclePart {
+    pub visual: Visual,
+    pub collider: Collider,
+}
+
+#[derive(serde::Deserialize)]
+pub struct Axle {
+    /// Side offset for each wheel.
+    pub x_wheels: Vec<f32>,
+    /// Height offset from the body.
+    pub y: f32,
+    /// Forward offset from the body.
+    pub z: f32,
+    #[serde(default)]
+    pub max_steering_angle: f32,
+    #[serde(default)]
+    pub max_suspension_offset: f32,
+    #[serde(default)]
+    pub suspension: Motor,
+    #[serde(default)]
+    pub steering: Motor,
+}
+
+fn default_suspension_compression() -> f32 {
+    0.83
+}
+fn default_friction_slip() -> f32 {
+    10.5
+}
+
+/// Wheels simulated by casting rays down from the body,
+/// instead of using separate rigid bodies and joints.
+#[derive(serde::Deserialize)]
+pub struct RayCastWheels {
+    /// Length of the suspension at rest.
+    pub suspension_rest_length: f32,
+    /// Suspension spring, defined per unit of the body mass.
+    /// The damping applies when the suspension is relaxing.
+    pub suspension: Motor,
+    /// Damping of the suspension when it's being compressed.
+    #[serde(default = "default_suspension_compression")]
+    pub suspension_compression: f32,
+    /// Traction of the tires.
+    #[serde(default = "default_friction_slip")]
+    pub friction_slip: f32,
+    /// Multiplier of the side friction of the tires.
+    #[serde(default = "default_friction")]
+    pub side_friction_stiffness: f32,
+    /// Force applied by the engine at full throttle.
+    pub engine_force: f32,
+    /// Force applied by the brakes when fully pressed.
+    pub brake_force: f32,
+}
+
+fn default_suspender() -> AdditionalMass {
+    AdditionalMass {
+        density: 0.0,
+        shape: Shape::Ball { radius: 0.0 },
+    }
+}
+fn default_max_speed() -> f32 {
+    100.0
+}
+fn default_max_reverse_speed() -> f32 {
+    20.0
+}
+fn default_max_drive_force() -> f32 {
+    1000.0
+}
+
+#[derive(serde::Deserialize)]
+pub struct Vehicle {
+    pub body: VehiclePart,
+    pub wheel: VehiclePart,
+    #[serde(default = "default_suspender")]
+    pub suspender: AdditionalMass,
+    /// Damping of the wheel motors, defining how fast they reach the target speed.
+    pub drive_factor: f32,
+    #[serde(default = "default_max_drive_force")]
+    pub max_drive_force: f32,
+    /// Angular speed of the wheels at full throttle.
+    #[serde(default = "default_max_speed")]
+    pub max_speed: f32,
+    /// Angular speed of the wheels at full reverse throttle.
+    #[serde(default = "default_max_reverse_speed")]
+    pub max_reverse_speed: f32,
+    /// Additional damping of the wheel motors when fully braking.
+    #[serde(default)]
+    pub brake_factor: f32,
+    pub axles: Vec<Axle>,
+    #[serde(default)]
+    pub ray_cast: Option<RayCastWheels>,
+}
+
 fn default_time_step() -> f32 {
     0.01
 }
 
+/// Maximum number of the collision layers, limited by the bits of a collision group.
+pub const MAX_COLLISION_LAYERS: usize = 32;
+
+fn deserialize_collision_layers<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<String>, D::Error> {
+    let layers = <Vec<String> as serde::Deserialize>::deserialize(deserializer)?;
+    if layers.len() > MAX_COLLISION_LAYERS {
+        return Err(serde::de::Error::invalid_length(
+            layers.len(),
+            &"at most 32 collision layers",
+        ));
+    }
+    Ok(layers)
+}
+
 #[derive(serde::Deserialize)]
 pub struct Engine {
     pub shader_path: String,
     pub data_path: String,
     #[serde(default = "default_time_step")]
     pub time_step: f32,
+    /// Names of the collision layers, up to [`MAX_COLLISION_LAYERS`].
+    #[serde(default, deserialize_with = "deserialize_collision_layers")]
+    pub collision_layers: Vec<String>,
 }
@@ -17,8 +17,13 @@
 use blade_graphics as gpu;
 use std::{ops, path::Path, sync::Arc};
 
+mod character;
 pub mod config;
 mod trimesh;
+mod vehicle;
+
+pub use character::CharacterState;
+pub use vehicle::{VehicleHandle, VehicleInput};
 
 const ZERO_V3: mint::Vector3<f32> = mint::Vector3 {
     x: 0.0,
@@ -124,6 +129,60 @@ impl JointAxis {
     }
 }
 
+/// Interaction groups of a collider.
+///
+/// Two colliders interact only if the memberships of each one
+/// intersect with the filter of the other.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct CollisionGroups {
+    pub memberships: u32,
+    pub filter: u32,
+}
+impl Default for CollisionGroups {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+impl CollisionGroups {
+    pub const ALL: Self = Self {
+        memberships: !0,
+        filter: !0,
+    };
+    fn from_rapier(groups: rapier3d::geometry::InteractionGroups) -> Self {
+        Self {
+            memberships: groups.memberships.bits(),
+            filter: groups.filter.bits(),
+        }
+    }
+    fn into_rapier(self) -> rapier3d::geometry::InteractionGroups {
+        use rapier3d::geometry::Group;
+        rapier3d::geometry::InteractionGroups::new(
+            Group::from_bits_truncate(self.memberships),
+            Group::from_bits_truncate(self.filter),
+        )
+    }
+}
+
+/// Properties of a collider that can be changed at run-time.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ColliderProperties {
+    pub density: f32,
+    pub friction: f32,
+    pub restitution: f32,
+    pub groups: CollisionGroups,
+    /// Sensors detect intersections but don't generate contacts.
+    pub is_sensor: bool,
+}
+
+/// Properties of an object body that can be changed at run-time.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BodyProperties {
+    pub linear_damping: f32,
+    pub angular_damping: f32,
+    /// Multiplier of the gravity affecting this object.
+    pub gravity_scale: f32,
+}
+
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub enum JointHandle {
     Soft(#[doc(hidden)] rapier3d::dynamics::ImpulseJointHandle),
@@ -208,11 +267,25 @@ impl UiValue for egui::Ui {
     }
 }
 
-#[derive(Default)]
 struct DebugPhysicsRender {
     lines: Vec<blade_render::DebugLine>,
+    layer_mask: u32,
 }
 impl rapier3d::pipeline::DebugRenderBackend for DebugPhysicsRender {
+    fn filter_object(&self, object: rapier3d::pipeline::DebugRenderObject) -> bool {
+        use rapier3d::pipeline::DebugRenderObject as Dro;
+        let is_visible = |collider: &rapier3d::geometry::Collider| {
+            collider.collision_groups().memberships.bits() & self.layer_mask != 0
+        };
+        match object {
+            Dro::Collider(_, collider) | Dro::ColliderAabb(_, collider, _) => is_visible(collider),
+            Dro::ContactPair(_, collider1, collider2) => {
+                is_visible(collider1) || is_visible(collider2)
+            }
+            _ => true,
+        }
+    }
+
     fn draw_line(
         &mut self,
         _object: rapier3d::pipeline::DebugRenderObject,
@@ -261,6 +334,7 @@ struct Physics {
     colliders: rapier3d::geometry::ColliderSet,
     broad_phase: rapier3d::geometry::DefaultBroadPhase,
     narrow_phase: rapier3d::geometry::NarrowPhase,
+    query_pipeline: rapier3d::pipeline::QueryPipeline,
     gravity: rapier3d::math::Vector<f32>,
     pipeline: rapier3d::pipeline::PhysicsPipeline,
     debug_pipeline: rapier3d::pipeline::DebugRenderPipeline,
@@ -269,7 +343,7 @@ struct Physics {
 
 impl Physics {
     fn step(&mut self) {
-        let query_pipeline = None;
+        let query_pipeline = Some(&mut self.query_pipeline);
         let physics_hooks = ();
         let event_handler = ();
         self.pipeline.step(
@@ -289,8 +363,11 @@ impl Physics {
         );
         self.last_time += self.integration_params.dt;
     }
-    fn render_debug(&mut self) -> Vec<blade_render::DebugLine> {
-        let mut backend = DebugPhysicsRender::default();
+    fn render_debug(&mut self, layer_mask: u32) -> Vec<blade_render::DebugLine> {
+        let mut backend = DebugPhysicsRender {
+            lines: Vec::new(),
+            layer_mask,
+        };
         self.debug_pipeline.render(
             &mut backend,
             &self.rigid_bodies,
@@ -339,6 +416,15 @@ struct Object {
     prev_isometry: nalgebra::Isometry3<f32>,
     colliders: Vec<rapier3d::geometry::ColliderHandle>,
     visuals: Vec<Visual>,
+    character: Option<character::Character>,
+}
+
+/// Result of a ray cast into the scene.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RayHit {
+    pub object: ObjectHandle,
+    /// Distance along the ray, in units of the ray direction length.
+    pub distance: f32,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -364,6 +450,7 @@ impl From<blade_render::Camera> for FrameCamera {
 /// asset hub, physics context, task processing, and more.
 pub struct Engine {
     pacer: blade_render::util::FramePacer,
+    profiler: blade_helpers::Profiler,
     renderer: blade_render::Renderer,
     physics: Physics,
     load_tasks: Vec<choir::RunningTask>,
@@ -373,8 +460,11 @@ pub struct Engine {
     gpu_context: Arc<gpu::Context>,
     environment_map: Option<blade_asset::Handle<blade_render::Texture>>,
     objects: slab::Slab<Object>,
+    vehicles: slab::Slab<vehicle::Vehicle>,
     selected_object_handle: Option<ObjectHandle>,
     selected_collider: Option<rapier3d::geometry::ColliderHandle>,
+    collision_layers: Vec<String>,
+    debug_layer_mask: u32,
     render_objects: Vec<blade_render::Object>,
     debug: blade_render::DebugConfig,
     pub frame_config: blade_render::FrameConfig,
@@ -468,6 +558,7 @@ impl Engine {
 
         Self {
             pacer,
+            profiler: blade_helpers::Profiler::default(),
             renderer,
             physics,
             load_tasks: Vec::new(),
@@ -477,8 +568,11 @@ impl Engine {
             gpu_context,
             environment_map: None,
             objects: slab::Slab::new(),
+            vehicles: slab::Slab::new(),
             selected_object_handle: None,
             selected_collider: None,
+            collision_layers: config.collision_layers.clone(),
+            debug_layer_mask: !0,
             render_objects: Vec::new(),
             debug: blade_render::DebugConfig::default(),
             frame_config: blade_render::FrameConfig {
@@ -515,17 +609,19 @@ impl Engine {
         self.asset_hub.destroy();
     }
 
-    #[profiling::function]
     pub fn update(&mut self, dt: f32) {
         self.choir.check_panic();
+        blade_helpers::profile_scope!(self.profiler, "update");
         self.time_ahead += dt;
         while self.time_ahead >= self.physics.integration_params.dt {
+            self.step_characters();
+            self.step_vehicles();
             self.physics.step();
+            self.place_vehicle_wheels();
             self.time_ahead -= self.physics.integration_params.dt;
         }
     }
 
-    #[profiling::function]
     pub fn render(
         &mut self,
         camera: &FrameCamera,
@@ -533,6 +629,29 @@ impl Engine {
         gui_textures: &egui::TexturesDelta,
         physical_size: winit::dpi::PhysicalSize<u32>,
         scale_factor: f32,
+    ) {
+        {
+            blade_helpers::profile_scope!(self.profiler, "render");
+            self.render_frame(
+                camera,
+                gui_primitives,
+                gui_textures,
+                physical_size,
+                scale_factor,
+            );
+        }
+        // The frame is finished after the render scope, following the update.
+        self.profiler.end_frame(self.pacer.timings());
+        profiling::finish_frame!();
+    }
+
+    fn render_frame(
+        &mut self,
+        camera: &FrameCamera,
+        gui_primitives: &[egui::ClippedPrimitive],
+        gui_textures: &egui::TexturesDelta,
+        physical_size: winit::dpi::PhysicalSize<u32>,
+        scale_factor: f32,
     ) {
         if self.track_hot_reloads {
             self.renderer.hot_reload(
@@ -561,10 +680,21 @@ impl Engine {
         }
         self.frame_config.reset_variance = self.debug.mouse_pos.is_none();
 
-        self.gui_painter
-            .update_textures(command_encoder, gui_textures, &self.gpu_context);
-
-        self.asset_hub.flush(command_encoder, &mut temp.buffers);
+        // The profiler only records the scopes placed explicitly, not the ones
+        // of `#[profiling::function]`, so the main calls are wrapped into scopes.
+        {
+            blade_helpers::profile_scope!(self.profiler, "update_textures");
+            self.gui_painter
+                .update_textures(command_encoder, gui_textures, &self.gpu_context);
+        }
+        {
+            blade_helpers::profile_scope!(self.profiler, "stream_textures");
+            self.renderer.stream_textures(&self.asset_hub, temp);
+        }
+        {
+            blade_helpers::profile_scope!(self.profiler, "flush");
+            self.asset_hub.flush(command_encoder, temp);
+        }
 
         self.load_tasks.retain(|task| !task.is_done());
 
@@ -604,6 +734,7 @@ impl Engine {
 
             // Rebuilding every frame
             if !self.frame_config.frozen {
+                blade_helpers::profile_scope!(self.profiler, "build_scene");
                 self.renderer.build_scene(
                     command_encoder,
                     &self.render_objects,
@@ -614,28 +745,35 @@ impl Engine {
                 );
             }
 
-            self.renderer.prepare(
-                command_encoder,
-                &blade_render::Camera {
-                    pos: camera.transform.position,
-                    rot: camera.transform.orientation,
-                    fov_y: camera.fov_y,
-                    depth: MAX_DEPTH,
-                },
-                self.frame_config,
-            );
+            {
+                blade_helpers::profile_scope!(self.profiler, "prepare");
+                self.renderer.prepare(
+                    command_encoder,
+                    &blade_render::Camera {
+                        pos: camera.transform.position,
+                        rot: camera.transform.orientation,
+                        fov_y: camera.fov_y,
+                        depth: MAX_DEPTH,
+                    },
+                    self.frame_config,
+                );
+            }
             self.frame_config.reset_reservoirs = false;
 
             if !self.render_objects.is_empty() {
-                self.renderer
-                    .ray_trace(command_encoder, self.debug, self.ray_config);
+                {
+                    blade_helpers::profile_scope!(self.profiler, "ray_trace");
+                    self.renderer
+                        .ray_trace(command_encoder, self.debug, self.ray_config);
+                }
                 if self.denoiser_enabled {
+                    blade_helpers::profile_scope!(self.profiler, "denoise");
                     self.renderer.denoise(command_encoder, self.denoiser_config);
                 }
             }
         }
 
-        let mut debug_lines = self.physics.render_debug();
+        let mut debug_lines = self.physics.render_debug(self.debug_layer_mask);
         if let Some(handle) = self.selected_object_handle {
             let object = &self.objects[handle.0];
             let rb = self.physics.rigid_bodies.get(object.rigid_body).unwrap();
@@ -702,6 +840,7 @@ impl Engine {
                 scale_factor,
             };
             if self.load_tasks.is_empty() {
+                blade_helpers::profile_scope!(self.profiler, "post_proc");
                 self.renderer.post_proc(
                     &mut pass,
                     self.debug,
@@ -710,6 +849,7 @@ impl Engine {
                     &[],
                 );
             }
+            blade_helpers::profile_scope!(self.profiler, "paint");
             self.gui_painter
                 .paint(&mut pass, gui_primitives, &screen_desc, &self.gpu_context);
         }
@@ -717,8 +857,6 @@ impl Engine {
         command_encoder.present(frame);
         let sync_point = self.pacer.end_frame(&self.gpu_context);
         self.gui_painter.after_submit(sync_point);
-
-        profiling::finish_frame!();
     }
 
     #[profiling::function]
@@ -767,16 +905,27 @@ impl Engine {
                     ui.checkbox(&mut enabled, format!("{flag:?}"));
                     self.physics.debug_pipeline.mode.set(flag, enabled);
                 }
+                if !self.collision_layers.is_empty() {
+                    ui.label("Collision layers:");
+                }
+                let layers = self
+                    .collision_layers
+                    .iter()
+                    .take(config::MAX_COLLISION_LAYERS);
+                for (bit_pos, name) in layers.enumerate() {
+                    let bit = 1 << bit_pos;
+                    let mut enabled = self.debug_layer_mask & bit != 0;
+                    ui.checkbox(&mut enabled, name);
+                    if enabled {
+                        self.debug_layer_mask |= bit;
+                    } else {
+                        self.debug_layer_mask &= !bit;
+                    }
+                }
             });
 
         egui::CollapsingHeader::new("Performance").show(ui, |ui| {
-            for (name, time) in self.pacer.timings() {
-                let millis = time.as_secs_f32() * 1000.0;
-                ui.horizontal(|ui| {
-                    ui.label(name);
-                    ui.colored_label(egui::Color32::WHITE, format!("{:.2} ms", millis));
-                });
-            }
+            self.profiler.populate_hud(ui);
         });
 
         egui::CollapsingHeader::new("Objects")
@@ -888,7 +1037,10 @@ impl Engine {
         let mut index = geometry_index as usize;
         for (obj_handle, object) in self.objects.iter() {
             for visual in object.visuals.iter() {
-                let model = &self.asset_hub.models[visual.model];
+                let model = match self.asset_hub.models.get(visual.model) {
+                    Some(model) => model,
+                    None => continue,
+                };
                 match index.checked_sub(model.geometries.len()) {
                     Some(i) => index = i,
                     None => return Some(ObjectHandle(obj_handle)),
@@ -898,16 +1050,64 @@ impl Engine {
         None
     }
 
+    fn make_shape(&self, shape: &config::Shape) -> rapier3d::geometry::SharedShape {
+        use rapier3d::geometry::{SharedShape, TriMeshFlags};
+
+        match *shape {
+            config::Shape::Ball { radius } => SharedShape::ball(radius),
+            config::Shape::Cylinder {
+                half_height,
+                radius,
+            } => SharedShape::cylinder(half_height, radius),
+            config::Shape::Cuboid { half } => SharedShape::cuboid(half.x, half.y, half.z),
+            config::Shape::ConvexHull {
+                ref points,
+                border_radius,
+            } => {
+                let pv = points
+                    .iter()
+                    .map(|p| nalgebra::Vector3::from(*p).into())
+                    .collect::<Vec<_>>();
+                let result = if border_radius != 0.0 {
+                    SharedShape::round_convex_hull(&pv, border_radius)
+                } else {
+                    SharedShape::convex_hull(&pv)
+                };
+                result.expect("Unable to build convex hull shape")
+            }
+            config::Shape::TriMesh {
+                ref model,
+                convex,
+                border_radius,
+            } => {
+                let trimesh = trimesh::load(&format!("{}/{}", self.data_path, model));
+                if convex && border_radius != 0.0 {
+                    SharedShape::round_convex_mesh(
+                        trimesh.points,
+                        &trimesh.triangles,
+                        border_radius,
+                    )
+                    .expect("Unable to build rounded convex mesh")
+                } else if convex {
+                    SharedShape::convex_mesh(trimesh.points, &trimesh.triangles)
+                        .expect("Unable to build convex mesh")
+                } else {
+                    assert_eq!(border_radius, 0.0);
+                    let flags = TriMeshFlags::empty();
+                    SharedShape::trimesh_with_flags(trimesh.points, trimesh.triangles, flags)
+                        .unwrap()
+                }
+            }
+        }
+    }
+
     pub fn add_object(
         &mut self,
         config: &config::Object,
         transform: Transform,
         dynamic_input: DynamicInput,
     ) -> ObjectHandle {
-        use rapier3d::{
-            dynamics::MassProperties,
-            geometry::{ColliderBuilder, TriMeshFlags},
-        };
+        use rapier3d::{dynamics::MassProperties, geometry::ColliderBuilder};
 
         let mut visuals = Vec::new();
         for visual in config.visuals.iter() {
@@ -934,17 +1134,17 @@ impl Engine {
 
         let add_mass_properties = match config.additional_mass {
             Some(ref am) => match am.shape {
-                config::Shape::Ball { radius } => MassProperties::from_ball(am.density, radius),
-                config::Shape::Cylinder {
-                    half_height,
-                    radius,
-                } => MassProperties::from_cylinder(am.density, half_height, radius),
-                config::Shape::Cuboid { half } => {
-                    MassProperties::from_cuboid(am.density, half.into())
-                }
-                config::Shape::ConvexHull { .. } | config::Shape::TriMesh { .. } => {
-                    unimplemented!()
+                // Triangle meshes have no volume, so their mass is computed
+                // assuming they are closed.
+                config::Shape::TriMesh {
+                    ref model,
+                    convex: false,
+                    border_radius: _,
+                } => {
+                    let trimesh = trimesh::load(&format!("{}/{}", self.data_path, model));
+                    MassProperties::from_trimesh(am.density, &trimesh.points, &trimesh.triangles)
                 }
+                ref shape => self.make_shape(shape).mass_properties(am.density),
             },
             None => Default::default(),
         };
@@ -952,6 +1152,7 @@ impl Engine {
         let rigid_body = rapier3d::dynamics::RigidBodyBuilder::new(dynamic_input.into_rapier())
             .position(transform.into_isometry())
             .additional_mass_properties(add_mass_properties)
+            .user_data(self.objects.vacant_key() as u128)
             .build();
         let rb_handle = self.physics.rigid_bodies.insert(rigid_body);
 
@@ -961,60 +1162,16 @@ impl Engine {
                 nalgebra::Vector3::from(cc.pos).into(),
                 make_quaternion(cc.rot),
             );
-            let builder = match cc.shape {
-                config::Shape::Ball { radius } => ColliderBuilder::ball(radius),
-                config::Shape::Cylinder {
-                    half_height,
-                    radius,
-                } => ColliderBuilder::cylinder(half_height, radius),
-                config::Shape::Cuboid { half } => ColliderBuilder::cuboid(half.x, half.y, half.z),
-                config::Shape::ConvexHull {
-                    ref points,
-                    border_radius,
-                } => {
-                    let pv = points
-                        .iter()
-                        .map(|p| nalgebra::Vector3::from(*p).into())
-                        .collect::<Vec<_>>();
-                    let result = if border_radius != 0.0 {
-                        ColliderBuilder::round_convex_hull(&pv, border_radius)
-                    } else {
-                        ColliderBuilder::convex_hull(&pv)
-                    };
-                    result.expect("Unable to build convex hull shape")
-                }
-                config::Shape::TriMesh {
-                    ref model,
-                    convex,
-                    border_radius,
-                } => {
-                    let trimesh = trimesh::load(&format!("{}/{}", self.data_path, model));
-                    if convex && border_radius != 0.0 {
-                        ColliderBuilder::round_convex_mesh(
-                            trimesh.points,
-                            &trimesh.triangles,
-                            border_radius,
-                        )
-                        .expect("Unable to build rounded convex mesh")
-                    } else if convex {
-                        ColliderBuilder::convex_mesh(trimesh.points, &trimesh.triangles)
-                            .expect("Unable to build convex mesh")
-                    } else {
-                        assert_eq!(border_radius, 0.0);
-                        let flags = TriMeshFlags::empty();
-                        ColliderBuilder::trimesh_with_flags(
-                            trimesh.points,
-                            trimesh.triangles,
-                            flags,
-                        )
-                        .unwrap()
-                    }
-                }
+            let builder = ColliderBuilder::new(self.make_shape(&cc.shape));
+            let groups = CollisionGroups {
+                memberships: self.get_collision_mask(&cc.layers),
+                filter: self.get_collision_mask(&cc.collides_with),
             };
             let collider = builder
                 .density(cc.density)
                 .friction(cc.friction)
                 .restitution(cc.restitution)
+                .collision_groups(groups.into_rapier())
                 .position(isometry)
                 .build();
             let c_handle = self.physics.colliders.insert_with_parent(
@@ -1031,10 +1188,59 @@ impl Engine {
             prev_isometry: nalgebra::Isometry3::default(),
             colliders,
             visuals,
+            character: None,
         });
         ObjectHandle(raw_handle)
     }
 
+    /// Get the bit mask of the named collision layers.
+    ///
+    /// An empty list stands for all the layers.
+    /// Unknown layers are skipped with a warning.
+    pub fn get_collision_mask<S: AsRef<str>>(&self, layers: &[S]) -> u32 {
+        if layers.is_empty() {
+            return !0;
+        }
+        let mut mask = 0;
+        for layer in layers.iter() {
+            let name = layer.as_ref();
+            match self.collision_layers.iter().position(|l| l == name) {
+                Some(index) if index < config::MAX_COLLISION_LAYERS => mask |= 1 << index,
+                _ => log::warn!("Unknown collision layer '{}' is skipped", name),
+            }
+        }
+        mask
+    }
+
+    /// Find the closest object hit by a ray, as of the last physics step.
+    ///
+    /// Only colliders interacting with the given groups are considered.
+    pub fn cast_ray(
+        &self,
+        origin: mint::Vector3<f32>,
+        direction: mint::Vector3<f32>,
+        max_distance: f32,
+        groups: CollisionGroups,
+    ) -> Option<RayHit> {
+        let ray =
+            rapier3d::geometry::Ray::new(nalgebra::Vector3::from(origin).into(), direction.into());
+        let filter = rapier3d::pipeline::QueryFilter::new().groups(groups.into_rapier());
+        let (collider_handle, distance) = self.physics.query_pipeline.cast_ray(
+            &self.physics.rigid_bodies,
+            &self.physics.colliders,
+            &ray,
+            max_distance,
+            true,
+            filter,
+        )?;
+        let rb_handle = self.physics.colliders[collider_handle].parent()?;
+        let object_index = self.physics.rigid_bodies[rb_handle].user_data as usize;
+        Some(RayHit {
+            object: ObjectHandle(object_index),
+            distance,
+        })
+    }
+
     pub fn wake_up(&mut self, object: ObjectHandle) {
         let rb_handle = self.objects[object.0].rigid_body;
         let rb = self.physics.rigid_bodies.get_mut(rb_handle).unwrap();
@@ -1149,6 +1355,63 @@ impl Engine {
         body.set_position(transform.into_isometry(), true);
     }
 
+    /// Get the number of colliders in an object.
+    ///
+    /// The colliders are indexed in the order of the object config.
+    pub fn get_collider_count(&self, handle: ObjectHandle) -> usize {
+        self.objects[handle.0].colliders.len()
+    }
+
+    pub fn get_collider_properties(
+        &self,
+        handle: ObjectHandle,
+        index: usize,
+    ) -> ColliderProperties {
+        let object = &self.objects[handle.0];
+        let collider = &self.physics.colliders[object.colliders[index]];
+        ColliderProperties {
+            density: collider.density(),
+            friction: collider.friction(),
+            restitution: collider.restitution(),
+            groups: CollisionGroups::from_rapier(collider.collision_groups()),
+            is_sensor: collider.is_sensor(),
+        }
+    }
+
+    pub fn set_collider_properties(
+        &mut self,
+        handle: ObjectHandle,
+        index: usize,
+        properties: &ColliderProperties,
+    ) {
+        let object = &self.objects[handle.0];
+        let collider = &mut self.physics.colliders[object.colliders[index]];
+        collider.set_density(properties.density);
+        collider.set_friction(properties.friction);
+        collider.set_restitution(properties.restitution);
+        collider.set_collision_groups(properties.groups.into_rapier());
+        collider.set_sensor(properties.is_sensor);
+        self.wake_up(handle);
+    }
+
+    pub fn get_body_properties(&self, handle: ObjectHandle) -> BodyProperties {
+        let object = &self.objects[handle.0];
+        let body = &self.physics.rigid_bodies[object.rigid_body];
+        BodyProperties {
+            linear_damping: body.linear_damping(),
+            angular_damping: body.angular_damping(),
+            gravity_scale: body.gravity_scale(),
+        }
+    }
+
+    pub fn set_body_properties(&mut self, handle: ObjectHandle, properties: &BodyProperties) {
+        let object = &self.objects[handle.0];
+        let body = &mut self.physics.rigid_bodies[object.rigid_body];
+        body.set_linear_damping(properties.linear_damping);
+        body.set_angular_damping(properties.angular_damping);
+        body.set_gravity_scale(properties.gravity_scale, true);
+    }
+
     pub fn set_joint_motor(
         &mut self,
         handle: JointHandle,
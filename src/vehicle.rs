@@ -0,0 +1,418 @@
+use crate::{
+    config, CollisionGroups, DynamicInput, Engine, FreedomAxis, JointAxis, JointDesc, JointHandle,
+    ObjectHandle, Prediction, Transform,
+};
+use rapier3d::control::{DynamicRayCastVehicleController, WheelTuning};
+use std::f32::consts;
+
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd, Hash)]
+pub struct VehicleHandle(usize);
+
+/// Driver controls of a vehicle.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct VehicleInput {
+    /// Steering, from -1 (full right) to 1 (full left).
+    pub steering: f32,
+    /// Throttle, from -1 (full reverse) to 1 (full forward).
+    pub throttle: f32,
+    /// Brake, from 0 (released) to 1 (fully pressed).
+    pub brake: f32,
+}
+
+struct WheelJoints {
+    spin: JointHandle,
+    steer: Option<JointHandle>,
+    suspender: Option<ObjectHandle>,
+}
+
+struct Wheel {
+    object: ObjectHandle,
+    max_steering_angle: f32,
+    /// Orientation of the wheel model in the vehicle space.
+    base_rotation: nalgebra::UnitQuaternion<f32>,
+    /// Only present for the wheels simulated as rigid bodies.
+    joints: Option<WheelJoints>,
+}
+
+struct RayCast {
+    controller: DynamicRayCastVehicleController,
+    groups: CollisionGroups,
+    engine_force: f32,
+    brake_force: f32,
+}
+
+pub(super) struct Vehicle {
+    body: ObjectHandle,
+    wheels: Vec<Wheel>,
+    ray_cast: Option<RayCast>,
+    drive_factor: f32,
+    max_speed: f32,
+    max_reverse_speed: f32,
+    brake_factor: f32,
+}
+
+impl Engine {
+    /// Add a vehicle, consisting of a body and a number of wheels.
+    ///
+    /// Note: in the vehicle coordinate system X=left, Y=up, Z=forward.
+    pub fn add_vehicle(&mut self, config: &config::Vehicle, transform: Transform) -> VehicleHandle {
+        let base = transform.clone().into_isometry();
+        let body_config = config::Object {
+            name: "vehicle/body".to_string(),
+            visuals: vec![config.body.visual.clone()],
+            colliders: vec![config.body.collider.clone()],
+            additional_mass: None,
+        };
+        let body = self.add_object(&body_config, transform, DynamicInput::Full);
+        let mut wheel_config = config::Object {
+            name: "vehicle/wheel".to_string(),
+            visuals: vec![config.wheel.visual.clone()],
+            colliders: vec![config.wheel.collider.clone()],
+            additional_mass: None,
+        };
+        let suspender_config = config::Object {
+            name: "vehicle/suspender".to_string(),
+            visuals: vec![],
+            colliders: vec![],
+            additional_mass: Some(config.suspender.clone()),
+        };
+
+        let (mut ray_cast, wheel_input) = match config.ray_cast {
+            Some(ref rc) => {
+                // With ray casting, the wheels are purely visual.
+                wheel_config.colliders.clear();
+                let mut controller =
+                    DynamicRayCastVehicleController::new(self.objects[body.0].rigid_body);
+                controller.index_up_axis = 1;
+                controller.index_forward_axis = 2;
+                let ray_cast = RayCast {
+                    controller,
+                    groups: CollisionGroups {
+                        memberships: self.get_collision_mask(&config.body.collider.layers),
+                        filter: self.get_collision_mask(&config.body.collider.collides_with),
+                    },
+                    engine_force: rc.engine_force,
+                    brake_force: rc.brake_force,
+                };
+                (Some(ray_cast), DynamicInput::SetPosition)
+            }
+            None => (None, DynamicInput::Full),
+        };
+
+        let mut wheels = Vec::new();
+        for ac in config.axles.iter() {
+            for &wheel_x in ac.x_wheels.iter() {
+                let offset = nalgebra::Vector3::new(wheel_x, ac.y, ac.z);
+                let base_rotation = if wheel_x > 0.0 {
+                    nalgebra::UnitQuaternion::from_axis_angle(
+                        &nalgebra::Vector3::y_axis(),
+                        consts::PI,
+                    )
+                } else {
+                    nalgebra::UnitQuaternion::identity()
+                };
+                let object = self.add_object(
+                    &wheel_config,
+                    Transform::from_isometry(
+                        base * nalgebra::Isometry3::from_parts(offset.into(), base_rotation),
+                    ),
+                    wheel_input,
+                );
+
+                if let (Some(rt), Some(rc)) = (ray_cast.as_mut(), config.ray_cast.as_ref()) {
+                    let radius = match config.wheel.collider.shape {
+                        config::Shape::Ball { radius } | config::Shape::Cylinder { radius, .. } => {
+                            radius
+                        }
+                        config::Shape::Cuboid { half } => half.y,
+                        ref shape @ (config::Shape::ConvexHull { .. }
+                        | config::Shape::TriMesh { .. }) => self
+                            .make_shape(shape)
+                            .compute_local_bounding_sphere()
+                            .radius(),
+                    };
+                    let tuning = WheelTuning {
+                        suspension_stiffness: rc.suspension.stiffness,
+                        suspension_compression: rc.suspension_compression,
+                        suspension_damping: rc.suspension.damping,
+                        max_suspension_travel: ac.max_suspension_offset,
+                        side_friction_stiffness: rc.side_friction_stiffness,
+                        friction_slip: rc.friction_slip,
+                        max_suspension_force: rc.suspension.max_force,
+                    };
+                    rt.controller.add_wheel(
+                        offset.into(),
+                        -nalgebra::Vector3::y(),
+                        -nalgebra::Vector3::x(),
+                        rc.suspension_rest_length,
+                        radius,
+                        &tuning,
+                    );
+                    wheels.push(Wheel {
+                        object,
+                        max_steering_angle: ac.max_steering_angle.to_radians(),
+                        base_rotation,
+                        joints: None,
+                    });
+                    continue;
+                }
+
+                let wheel_angular_freedoms = mint::Vector3 {
+                    x: Some(FreedomAxis {
+                        limits: None,
+                        motor: Some(config::Motor {
+                            stiffness: 0.0,
+                            damping: config.drive_factor,
+                            max_force: config.max_drive_force,
+                        }),
+                    }),
+                    y: None,
+                    z: None,
+                };
+
+                let joints = if ac.max_steering_angle > 0.0 || ac.max_suspension_offset > 0.0 {
+                    let max_angle = ac.max_steering_angle.to_radians();
+                    let suspender = self.add_object(
+                        &suspender_config,
+                        Transform::from_isometry(base * nalgebra::Translation3::from(offset)),
+                        DynamicInput::Full,
+                    );
+
+                    let suspension_joint = self.add_joint(
+                        body,
+                        suspender,
+                        JointDesc {
+                            parent_anchor: Transform {
+                                position: offset.into(),
+                                ..Default::default()
+                            },
+                            linear: mint::Vector3 {
+                                x: None,
+                                y: if ac.max_suspension_offset > 0.0 {
+                                    Some(FreedomAxis {
+                                        limits: Some(0.0..ac.max_suspension_offset),
+                                        motor: Some(ac.suspension),
+                                    })
+                                } else {
+                                    None
+                                },
+                                z: None,
+                            },
+                            angular: mint::Vector3 {
+                                x: None,
+                                y: if ac.max_steering_angle > 0.0 {
+                                    Some(FreedomAxis {
+                                        limits: Some(-max_angle..max_angle),
+                                        motor: Some(ac.steering),
+                                    })
+                                } else {
+                                    None
+                                },
+                                z: None,
+                            },
+                            ..Default::default()
+                        },
+                    );
+
+                    let spin_joint = self.add_joint(
+                        suspender,
+                        object,
+                        JointDesc {
+                            child_anchor: Transform {
+                                orientation: base_rotation.into(),
+                                ..Default::default()
+                            },
+                            angular: wheel_angular_freedoms,
+                            ..Default::default()
+                        },
+                    );
+
+                    let _extra_joint = self.add_joint(
+                        body,
+                        object,
+                        JointDesc {
+                            linear: FreedomAxis::ALL_FREE,
+                            angular: FreedomAxis::ALL_FREE,
+                            ..Default::default()
+                        },
+                    );
+
+                    WheelJoints {
+                        spin: spin_joint,
+                        steer: if ac.max_steering_angle > 0.0 {
+                            Some(suspension_joint)
+                        } else {
+                            None
+                        },
+                        suspender: Some(suspender),
+                    }
+                } else {
+                    let spin_joint = self.add_joint(
+                        body,
+                        object,
+                        JointDesc {
+                            parent_anchor: Transform {
+                                position: offset.into(),
+                                ..Default::default()
+                            },
+                            child_anchor: Transform {
+                                orientation: base_rotation.into(),
+                                ..Default::default()
+                            },
+                            angular: wheel_angular_freedoms,
+                            ..Default::default()
+                        },
+                    );
+
+                    WheelJoints {
+                        spin: spin_joint,
+                        steer: None,
+                        suspender: None,
+                    }
+                };
+
+                wheels.push(Wheel {
+                    object,
+                    max_steering_angle: ac.max_steering_angle.to_radians(),
+                    base_rotation,
+                    joints: Some(joints),
+                });
+            }
+        }
+
+        let raw_handle = self.vehicles.insert(Vehicle {
+            body,
+            wheels,
+            ray_cast,
+            drive_factor: config.drive_factor,
+            max_speed: config.max_speed,
+            max_reverse_speed: config.max_reverse_speed,
+            brake_factor: config.brake_factor,
+        });
+        VehicleHandle(raw_handle)
+    }
+
+    /// Get the object representing the vehicle body.
+    pub fn get_vehicle_body(&self, handle: VehicleHandle) -> ObjectHandle {
+        self.vehicles[handle.0].body
+    }
+
+    /// Apply the driver controls to the vehicle.
+    pub fn set_vehicle_input(&mut self, handle: VehicleHandle, input: VehicleInput) {
+        let vehicle = &mut self.vehicles[handle.0];
+        let rb_handle = self.objects[vehicle.body.0].rigid_body;
+        self.physics.rigid_bodies[rb_handle].wake_up(true);
+
+        if let Some(ref mut rc) = vehicle.ray_cast {
+            let engine_force = input.throttle * rc.engine_force;
+            for (wheel, rc_wheel) in vehicle
+                .wheels
+                .iter()
+                .zip(rc.controller.wheels_mut().iter_mut())
+            {
+                rc_wheel.steering = input.steering * wheel.max_steering_angle;
+                rc_wheel.engine_force = engine_force;
+                rc_wheel.brake = input.brake * rc.brake_force;
+            }
+            return;
+        }
+
+        let max_speed = if input.throttle >= 0.0 {
+            vehicle.max_speed
+        } else {
+            vehicle.max_reverse_speed
+        };
+        let velocity = input.throttle * max_speed * (1.0 - input.brake);
+        let damping = vehicle.drive_factor + input.brake * vehicle.brake_factor;
+        for wheel in vehicle.wheels.iter() {
+            let joints = match wheel.joints {
+                Some(ref joints) => joints,
+                None => continue,
+            };
+            self.physics[joints.spin].set_motor_velocity(
+                JointAxis::AngularX.into_rapier(),
+                velocity,
+                damping,
+            );
+            if let Some(steer) = joints.steer {
+                let joint = &mut self.physics[steer];
+                let rapier_axis = JointAxis::AngularY.into_rapier();
+                if let Some(&rapier3d::dynamics::JointMotor {
+                    damping, stiffness, ..
+                }) = joint.motor(rapier_axis)
+                {
+                    let angle = input.steering * wheel.max_steering_angle;
+                    joint.set_motor(rapier_axis, angle, 0.0, stiffness, damping);
+                }
+            }
+        }
+    }
+
+    /// Move the whole vehicle, keeping the relative placement of its parts.
+    pub fn teleport_vehicle(&mut self, handle: VehicleHandle, transform: Transform) {
+        let vehicle = &self.vehicles[handle.0];
+        let mut parts = vec![vehicle.body];
+        for wheel in vehicle.wheels.iter() {
+            parts.push(wheel.object);
+            if let Some(suspender) = wheel.joints.as_ref().and_then(|j| j.suspender) {
+                parts.push(suspender);
+            }
+        }
+
+        let old_isometry = self
+            .get_object_transform(vehicle.body, Prediction::LastKnown)
+            .into_isometry();
+        let relative = transform.into_isometry() * old_isometry.inverse();
+        for part in parts {
+            let isometry = self
+                .get_object_transform(part, Prediction::LastKnown)
+                .into_isometry();
+            self.teleport_object(part, Transform::from_isometry(relative * isometry));
+        }
+    }
+
+    /// Apply the ray-casted wheel forces before a physics step.
+    pub(super) fn step_vehicles(&mut self) {
+        let physics = &mut self.physics;
+        for (_, vehicle) in self.vehicles.iter_mut() {
+            if let Some(ref mut rc) = vehicle.ray_cast {
+                let filter = rapier3d::pipeline::QueryFilter::new()
+                    .exclude_sensors()
+                    .exclude_rigid_body(rc.controller.chassis)
+                    .groups(rc.groups.into_rapier());
+                rc.controller.update_vehicle(
+                    physics.integration_params.dt,
+                    &mut physics.rigid_bodies,
+                    &physics.colliders,
+                    &physics.query_pipeline,
+                    filter,
+                );
+            }
+        }
+    }
+
+    /// Place the ray-casted wheels after a physics step.
+    pub(super) fn place_vehicle_wheels(&mut self) {
+        for (_, vehicle) in self.vehicles.iter() {
+            let rc = match vehicle.ray_cast {
+                Some(ref rc) => rc,
+                None => continue,
+            };
+            let chassis = *self.physics.rigid_bodies[rc.controller.chassis].position();
+            for (wheel, rc_wheel) in vehicle.wheels.iter().zip(rc.controller.wheels()) {
+                let center = rc_wheel.chassis_connection_point_cs
+                    + rc_wheel.direction_cs * rc_wheel.raycast_info().suspension_length;
+                let rotation = nalgebra::UnitQuaternion::from_axis_angle(
+                    &nalgebra::Vector3::y_axis(),
+                    rc_wheel.steering,
+                ) * nalgebra::UnitQuaternion::from_axis_angle(
+                    &nalgebra::Vector3::x_axis(),
+                    rc_wheel.rotation,
+                ) * wheel.base_rotation;
+                let local = nalgebra::Isometry3::from_parts(center.coords.into(), rotation);
+                let rb_handle = self.objects[wheel.object.0].rigid_body;
+                self.physics.rigid_bodies[rb_handle].set_position(chassis * local, false);
+            }
+        }
+    }
+}
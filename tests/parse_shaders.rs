@@ -45,7 +45,8 @@ fn parse_wgsl() {
             };
 
             let cooker = blade_asset::Cooker::new(&example, Default::default());
-            let text_out = blade_render::shader::parse_shader(&shader_raw, &cooker, &expansions);
+            let text_out = blade_render::shader::parse_shader(&shader_raw, &cooker, &expansions)
+                .unwrap_or_else(|e| panic!("Unable to parse {:?}: {}", path, e));
 
             let module = match wgsl::parse_str(&text_out) {
                 Ok(module) => module,